@@ -1,7 +1,9 @@
 use envconfig::Envconfig;
+use ipnetwork::IpNetwork;
 use rsip::HostWithPort;
 use std::convert::TryInto;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
 
 #[derive(envconfig::Envconfig, Debug, Clone)]
 pub struct EnvConfig {
@@ -9,6 +11,48 @@ pub struct EnvConfig {
     pub database_url: String,
     #[envconfig(from = "LISTEN_ADDRS")]
     pub listen_addrs: Option<String>,
+    #[envconfig(from = "TLS_CERT_PATH")]
+    pub tls_cert_path: Option<String>,
+    #[envconfig(from = "TLS_KEY_PATH")]
+    pub tls_key_path: Option<String>,
+    #[envconfig(from = "TLS_CA_PATH")]
+    pub tls_ca_path: Option<String>,
+    #[envconfig(from = "PATH_MTU")]
+    pub path_mtu: Option<usize>,
+    #[envconfig(from = "HEP_COLLECTOR")]
+    pub hep_collector: Option<SocketAddr>,
+    #[envconfig(from = "HEP_CAPTURE_ID")]
+    pub hep_capture_id: Option<u32>,
+    #[envconfig(from = "HEP_PASSWORD")]
+    pub hep_password: Option<String>,
+    #[envconfig(from = "PCAP_DIR")]
+    pub pcap_dir: Option<String>,
+    #[envconfig(from = "PCAP_MAX_FILE_SIZE")]
+    pub pcap_max_file_size: Option<u64>,
+    #[envconfig(from = "PCAP_MAX_FILES")]
+    pub pcap_max_files: Option<usize>,
+    #[envconfig(from = "SIP_TIMER_T1")]
+    pub sip_timer_t1: Option<u64>,
+    #[envconfig(from = "SIP_TIMER_T2")]
+    pub sip_timer_t2: Option<u64>,
+    #[envconfig(from = "SIP_TIMER_T4")]
+    pub sip_timer_t4: Option<u64>,
+    #[envconfig(from = "SIP_TIMER_OVERRIDES")]
+    pub sip_timer_overrides: Option<String>,
+    #[envconfig(from = "TRANSACTION_LINGER")]
+    pub transaction_linger: Option<u64>,
+    #[envconfig(from = "DIALOG_LINGER")]
+    pub dialog_linger: Option<u64>,
+    #[envconfig(from = "RATE_LIMIT_PER_IP")]
+    pub rate_limit_per_ip: Option<String>,
+    #[envconfig(from = "RATE_LIMIT_PER_METHOD")]
+    pub rate_limit_per_method: Option<String>,
+    #[envconfig(from = "RATE_LIMIT_BAN")]
+    pub rate_limit_ban: Option<u64>,
+    #[envconfig(from = "RATE_LIMIT_ALLOW")]
+    pub rate_limit_allow: Option<String>,
+    #[envconfig(from = "RATE_LIMIT_DENY")]
+    pub rate_limit_deny: Option<String>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -18,23 +62,303 @@ impl EnvConfig {
     }
 }
 
-//TODO: add port config
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub listen_addrs: Vec<HostWithPort>,
     pub default_listen_addr: HostWithPort,
+    pub listeners: Vec<ListenerConfig>,
+    pub tls: Option<TlsConfig>,
+    //unknown most of the time, in which case RFC3261 18.1.1 assumes 1500 bytes
+    pub path_mtu: Option<usize>,
+    pub hep: Option<HepConfig>,
+    pub pcap: Option<PcapConfig>,
+    pub timers: TimersConfig,
+    pub rate_limits: RateLimits,
+}
+
+//an address the server binds a socket to, for a single transport
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ListenerConfig {
+    pub addr: SocketAddr,
+    pub transport: rsip::Transport,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    //if missing, the bundled web PKI roots are used to verify peers
+    pub ca_path: Option<String>,
+}
+
+//where captured traffic is exported to, as HEPv3 over UDP (Homer)
+#[derive(Debug, Clone)]
+pub struct HepConfig {
+    pub collector: SocketAddr,
+    pub capture_id: u32,
+    pub password: Option<String>,
+}
+
+//rotating pcap files of all SIP traffic, for offline debugging with Wireshark
+#[derive(Debug, Clone)]
+pub struct PcapConfig {
+    pub dir: String,
+    //in bytes, after that a new file is started
+    pub max_file_size: u64,
+    //the oldest files are deleted so that there are never more than that
+    pub max_files: usize,
+}
+
+//a token bucket: `burst` requests at once, refilled with `per_second` tokens each second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl Rate {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self { per_second, burst }
+    }
+
+    //how long it takes for an empty bucket to be full again, forever for rates too small
+    //to ever refill it
+    pub fn refill_time(&self) -> Duration {
+        let secs = self.burst as f64 / self.per_second;
+        if secs.is_finite() && secs >= 0.0 && secs < u64::MAX as f64 {
+            Duration::from_secs_f64(secs)
+        } else {
+            Duration::from_secs(u64::MAX)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimits {
+    //limit of all requests coming from a single IP
+    pub per_ip: Option<Rate>,
+    //limits of a single IP for specific methods, for instance REGISTER floods
+    pub per_method: Vec<(rsip::Method, Rate)>,
+    //how long an IP that goes over its limits is banned for. Off unless configured, since
+    //a single burst of a legitimate client would otherwise lock it out
+    pub ban_duration: Option<Duration>,
+    //trusted networks, like the ones of carriers, that are never limited
+    pub allow: Vec<IpNetwork>,
+    //networks that are always dropped
+    pub deny: Vec<IpNetwork>,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            per_ip: Some(Rate::new(50.0, 100)),
+            per_method: vec![
+                (rsip::Method::Register, Rate::new(2.0, 10)),
+                (rsip::Method::Invite, Rate::new(5.0, 20)),
+                (rsip::Method::Options, Rate::new(2.0, 10)),
+            ],
+            ban_duration: None,
+            allow: vec![],
+            deny: vec![],
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn method_limit_index(&self, method: &rsip::Method) -> Option<usize> {
+        self.per_method
+            .iter()
+            .position(|(candidate, _)| candidate == method)
+    }
+}
+
+//RFC3261 base timers (17.1.1.1), all the others are derived from them
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SipTimers {
+    pub t1: Duration,
+    pub t2: Duration,
+    pub t4: Duration,
+}
+
+impl Default for SipTimers {
+    fn default() -> Self {
+        Self {
+            t1: Duration::from_millis(500),
+            t2: Duration::from_secs(4),
+            t4: Duration::from_secs(5),
+        }
+    }
+}
+
+impl SipTimers {
+    //T1 drives every retransmission, and T2 caps the intervals that start at T1
+    pub fn is_valid(&self) -> bool {
+        self.t1 > Duration::from_millis(0) && self.t2 >= self.t1
+    }
+
+    pub fn b(&self) -> Duration {
+        self.t1 * 64
+    }
+
+    //at least 32s, but has to outlive the retransmissions of the final response
+    pub fn d(&self) -> Duration {
+        std::cmp::max(Duration::from_secs(32), self.t1 * 64)
+    }
+
+    pub fn f(&self) -> Duration {
+        self.t1 * 64
+    }
+
+    pub fn g(&self) -> Duration {
+        self.t1
+    }
+
+    pub fn h(&self) -> Duration {
+        self.t1 * 64
+    }
+
+    pub fn i(&self) -> Duration {
+        self.t4
+    }
+
+    pub fn j(&self) -> Duration {
+        self.t1 * 64
+    }
+
+    pub fn k(&self) -> Duration {
+        self.t4
+    }
+
+    pub fn l(&self) -> Duration {
+        self.t1 * 64
+    }
+
+    pub fn m(&self) -> Duration {
+        self.t1 * 64
+    }
+}
+
+//timers to use with destinations (like high latency links) that need different ones
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TimerOverride {
+    pub destination: Destination,
+    pub timers: SipTimers,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Destination {
+    Network(IpNetwork),
+    Domain(String),
+}
+
+impl Destination {
+    fn matches(&self, host: &rsip::Host) -> bool {
+        match (self, ip_addr_of(host)) {
+            (Self::Network(network), Some(ip_addr)) => network.contains(ip_addr),
+            (Self::Domain(domain), None) => domain.eq_ignore_ascii_case(&host.to_string()),
+            _ => false,
+        }
+    }
+
+    fn matches_addr(&self, ip_addr: IpAddr) -> bool {
+        match self {
+            Self::Network(network) => network.contains(ip_addr),
+            Self::Domain(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TimersConfig {
+    pub default: SipTimers,
+    pub overrides: Vec<TimerOverride>,
+    //how long terminated transactions and dialogs are kept around before being evicted
+    pub transaction_linger: Duration,
+    pub dialog_linger: Duration,
+}
+
+impl Default for TimersConfig {
+    fn default() -> Self {
+        Self {
+            default: Default::default(),
+            overrides: Default::default(),
+            transaction_linger: Duration::from_secs(5),
+            dialog_linger: Duration::from_secs(32),
+        }
+    }
+}
+
+impl TimersConfig {
+    pub fn for_host(&self, host: &rsip::Host) -> SipTimers {
+        self.overrides
+            .iter()
+            .find(|timer_override| timer_override.destination.matches(host))
+            .map(|timer_override| timer_override.timers)
+            .unwrap_or(self.default)
+    }
+
+    //a host we already know the address of, like a domain the transport resolved or the
+    //source of a message, matches both domain and network overrides
+    pub fn for_target(&self, host: &rsip::Host, ip_addr: IpAddr) -> SipTimers {
+        self.overrides
+            .iter()
+            .find(|timer_override| {
+                timer_override.destination.matches(host)
+                    || timer_override.destination.matches_addr(ip_addr)
+            })
+            .map(|timer_override| timer_override.timers)
+            .unwrap_or(self.default)
+    }
+
+    //how long a client transaction can wait for its final response (Timer B/F), whatever
+    //its destination
+    pub fn longest_timeout(&self) -> Duration {
+        self.overrides
+            .iter()
+            .map(|timer_override| timer_override.timers.b())
+            .fold(self.default.b(), std::cmp::max)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         let env_config = EnvConfig::new();
+        let listeners = figure_out_listeners(env_config.listen_addrs.as_ref());
         let (default_listen_addr, listen_addrs) = figure_out_listen_addrs(env_config.listen_addrs);
 
+        let tls = match (env_config.tls_cert_path, env_config.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+                ca_path: env_config.tls_ca_path,
+            }),
+            (None, None) => None,
+            _ => {
+                log::warn!("both TLS_CERT_PATH and TLS_KEY_PATH are needed, TLS is disabled");
+                None
+            }
+        };
+
         Self {
             database_url: env_config.database_url,
             listen_addrs,
             default_listen_addr,
+            listeners,
+            tls,
+            path_mtu: env_config.path_mtu,
+            hep: env_config.hep_collector.map(|collector| HepConfig {
+                collector,
+                capture_id: env_config.hep_capture_id.unwrap_or_default(),
+                password: env_config.hep_password,
+            }),
+            pcap: env_config.pcap_dir.map(|dir| PcapConfig {
+                dir,
+                max_file_size: env_config.pcap_max_file_size.unwrap_or(100 * 1024 * 1024),
+                max_files: env_config.pcap_max_files.unwrap_or(10),
+            }),
+            timers: figure_out_timers(&env_config),
+            rate_limits: figure_out_rate_limits(&env_config),
         }
     }
 }
@@ -44,20 +368,246 @@ impl Config {
         self.default_listen_addr.clone()
     }
 
+    //the address we should advertise to a peer, which has to be of the peer's IP family
+    pub fn default_addr_for(&self, peer: &IpAddr) -> HostWithPort {
+        let same_family = |addr: &&HostWithPort| match ip_addr_of(&addr.host) {
+            Some(ip_addr) => ip_addr.is_ipv4() == peer.is_ipv4(),
+            None => true,
+        };
+
+        std::iter::once(&self.default_listen_addr)
+            .chain(self.listen_addrs.iter())
+            .find(same_family)
+            .unwrap_or(&self.default_listen_addr)
+            .clone()
+    }
+
+    //the address a peer can reach us back on over that transport: the one of the matching
+    //listener, with the default address of the peer's IP family if it listens on all of them
+    pub fn sent_by_for(&self, peer: &IpAddr, transport: rsip::Transport) -> HostWithPort {
+        let default_addr = self.default_addr_for(peer);
+
+        let listener = self.listeners.iter().find(|listener| {
+            listener.transport == transport && listener.addr.is_ipv4() == peer.is_ipv4()
+        });
+        match listener {
+            Some(listener) if listener.addr.ip().is_unspecified() => HostWithPort {
+                host: default_addr.host,
+                port: Some(listener.addr.port().into()),
+            },
+            Some(listener) => HostWithPort {
+                host: rsip::Host::IpAddr(listener.addr.ip()),
+                port: Some(listener.addr.port().into()),
+            },
+            None => default_addr,
+        }
+    }
+
+    //requests larger than that should not be sent over UDP (RFC3261 18.1.1)
+    pub fn max_udp_request_size(&self) -> usize {
+        match self.path_mtu {
+            Some(path_mtu) => path_mtu.saturating_sub(200),
+            None => 1300,
+        }
+    }
+
     pub fn contains_addr(&self, other: &HostWithPort) -> bool {
         self.listen_addrs.iter().any(|addr| {
-            addr.host == other.host
+            same_host(&addr.host, &other.host)
                 && addr.port.unwrap_or_else(|| 5060.into())
                     == other.port.unwrap_or_else(|| 5060.into())
         })
     }
 }
 
+fn same_host(host: &rsip::Host, other: &rsip::Host) -> bool {
+    match (ip_addr_of(host), ip_addr_of(other)) {
+        (Some(ip_addr), Some(other_ip_addr)) => ip_addr == other_ip_addr,
+        _ => host == other,
+    }
+}
+
+//IPv6 hosts enclosed in brackets end up as domains
+fn ip_addr_of(host: &rsip::Host) -> Option<IpAddr> {
+    match host {
+        rsip::Host::IpAddr(ip_addr) => Some(*ip_addr),
+        rsip::Host::Domain(domain) => domain
+            .to_string()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .ok(),
+    }
+}
+
+fn figure_out_timers(env_config: &EnvConfig) -> TimersConfig {
+    let defaults = TimersConfig::default();
+    let default = SipTimers {
+        t1: env_config.sip_timer_t1.map(Duration::from_millis).unwrap_or(defaults.default.t1),
+        t2: env_config.sip_timer_t2.map(Duration::from_millis).unwrap_or(defaults.default.t2),
+        t4: env_config.sip_timer_t4.map(Duration::from_millis).unwrap_or(defaults.default.t4),
+    };
+    let default = if default.is_valid() {
+        default
+    } else {
+        log::warn!("SIP_TIMER_T1 must be above 0 and up to SIP_TIMER_T2, using the defaults");
+        defaults.default
+    };
+
+    TimersConfig {
+        default,
+        overrides: env_config
+            .sip_timer_overrides
+            .as_deref()
+            .map(|overrides| timer_overrides_from(overrides, default))
+            .unwrap_or_default(),
+        transaction_linger: env_config
+            .transaction_linger
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.transaction_linger),
+        dialog_linger: env_config
+            .dialog_linger
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.dialog_linger),
+    }
+}
+
+//SIP_TIMER_OVERRIDES looks like `sat.example.com=t1:2000,t2:16000;10.1.0.0/16=t1:1500`,
+//values are in milliseconds and anything missing is inherited from the default timers
+pub fn timer_overrides_from(overrides: &str, default: SipTimers) -> Vec<TimerOverride> {
+    overrides
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match timer_override_from(entry, default) {
+            Some(timer_override) => Some(timer_override),
+            None => {
+                log::warn!("failed to parse SIP_TIMER_OVERRIDES entry: {}", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+fn timer_override_from(entry: &str, default: SipTimers) -> Option<TimerOverride> {
+    let mut parts = entry.splitn(2, '=');
+    let destination = parts.next()?.trim();
+    let destination = match destination.parse::<IpNetwork>() {
+        Ok(network) => Destination::Network(network),
+        Err(_) if !destination.is_empty() => Destination::Domain(destination.to_lowercase()),
+        Err(_) => return None,
+    };
+
+    let mut timers = default;
+    for timer in parts.next()?.split(',') {
+        let mut timer = timer.splitn(2, ':');
+        let name = timer.next()?.trim().to_lowercase();
+        let value = Duration::from_millis(timer.next()?.trim().parse().ok()?);
+        match name.as_str() {
+            "t1" => timers.t1 = value,
+            "t2" => timers.t2 = value,
+            "t4" => timers.t4 = value,
+            _ => return None,
+        }
+    }
+    if !timers.is_valid() {
+        return None;
+    }
+
+    Some(TimerOverride {
+        destination,
+        timers,
+    })
+}
+
+//RATE_LIMIT_PER_IP is `<per second>/<burst>`, or `off`, and RATE_LIMIT_PER_METHOD replaces
+//the default method limits, like `register=2/10,invite=5/20`. RATE_LIMIT_BAN is in seconds
+fn figure_out_rate_limits(env_config: &EnvConfig) -> RateLimits {
+    let defaults = RateLimits::default();
+
+    RateLimits {
+        per_ip: match env_config.rate_limit_per_ip.as_deref().map(str::trim) {
+            Some("off") => None,
+            Some(rate) => rate_from(rate).or_else(|| {
+                log::warn!("failed to parse RATE_LIMIT_PER_IP: {}", rate);
+                defaults.per_ip
+            }),
+            None => defaults.per_ip,
+        },
+        per_method: env_config
+            .rate_limit_per_method
+            .as_deref()
+            .map(method_rates_from)
+            .unwrap_or(defaults.per_method),
+        ban_duration: env_config.rate_limit_ban.map(Duration::from_secs),
+        allow: env_config
+            .rate_limit_allow
+            .as_deref()
+            .map(|networks| networks_from("RATE_LIMIT_ALLOW", networks))
+            .unwrap_or(defaults.allow),
+        deny: env_config
+            .rate_limit_deny
+            .as_deref()
+            .map(|networks| networks_from("RATE_LIMIT_DENY", networks))
+            .unwrap_or(defaults.deny),
+    }
+}
+
+//"<per second>/<burst>", with a rate that can actually refill a bucket
+pub fn rate_from(rate: &str) -> Option<Rate> {
+    let mut parts = rate.splitn(2, '/');
+    let per_second: f64 = parts.next()?.trim().parse().ok()?;
+    let burst = parts.next()?.trim().parse().ok()?;
+    if !per_second.is_finite() || per_second <= 0.0 {
+        return None;
+    }
+
+    Some(Rate::new(per_second, burst))
+}
+
+fn method_rates_from(rates: &str) -> Vec<(rsip::Method, Rate)> {
+    rates
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match method_rate_from(entry) {
+            Some(method_rate) => Some(method_rate),
+            None => {
+                log::warn!("failed to parse RATE_LIMIT_PER_METHOD entry: {}", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+fn method_rate_from(entry: &str) -> Option<(rsip::Method, Rate)> {
+    let mut parts = entry.splitn(2, '=');
+    let method = parts.next()?.trim().to_uppercase().parse().ok()?;
+
+    Some((method, rate_from(parts.next()?)?))
+}
+
+fn networks_from(name: &str, networks: &str) -> Vec<IpNetwork> {
+    networks
+        .split(',')
+        .map(str::trim)
+        .filter(|network| !network.is_empty())
+        .filter_map(|network| match network.parse() {
+            Ok(network) => Some(network),
+            Err(err) => {
+                log::warn!("failed to parse {} entry {}: {}", name, network, err);
+                None
+            }
+        })
+        .collect()
+}
+
 fn figure_out_listen_addrs(listen_env_addrs: Option<String>) -> (HostWithPort, Vec<HostWithPort>) {
     match listen_env_addrs {
         Some(listen_env_addrs) => match listen_env_addrs
             .split(',')
-            .map(TryInto::try_into)
+            .filter_map(|addr| addr.split(';').next())
+            .map(|addr| addr.trim().try_into())
             .collect::<Result<Vec<HostWithPort>, rsip::Error>>()
         {
             Ok(addrs) if !addrs.is_empty() => (
@@ -95,6 +645,114 @@ fn figure_out_listen_addrs(listen_env_addrs: Option<String>) -> (HostWithPort, V
     }
 }
 
+//each LISTEN_ADDRS entry can be restricted to a single transport, like
+//`10.0.0.1:5061;transport=tls`, otherwise it listens on both udp and tcp
+fn figure_out_listeners(listen_env_addrs: Option<&String>) -> Vec<ListenerConfig> {
+    let listeners = listen_env_addrs
+        .map(|addrs| addrs.split(',').flat_map(listeners_from).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if listeners.is_empty() {
+        log::warn!("no listeners found in LISTEN_ADDRS env var, will listen on all interfaces");
+        default_listeners()
+    } else {
+        listeners
+    }
+}
+
+pub fn listeners_from(entry: &str) -> Vec<ListenerConfig> {
+    let mut parts = entry.trim().splitn(2, ';');
+    let host_with_port = parts.next().unwrap_or_default();
+
+    let transports = match parts.next().map(str::trim) {
+        Some(param) => match param.strip_prefix("transport=").and_then(transport_from) {
+            Some(transport) => vec![transport],
+            None => {
+                log::warn!("unknown transport in LISTEN_ADDRS entry: {}", entry);
+                return vec![];
+            }
+        },
+        None => vec![rsip::Transport::Udp, rsip::Transport::Tcp],
+    };
+
+    let host_with_port: HostWithPort = match host_with_port.try_into() {
+        Ok(host_with_port) => host_with_port,
+        Err(err) => {
+            log::warn!("failed to parse LISTEN_ADDRS entry {}: {}", entry, err);
+            return vec![];
+        }
+    };
+
+    let ip_addr = match host_with_port.host {
+        rsip::Host::IpAddr(ip_addr) => ip_addr,
+        rsip::Host::Domain(domain) => {
+            log::warn!("can't listen on {}, only IP addresses can be bound", domain);
+            return vec![];
+        }
+    };
+
+    transports
+        .into_iter()
+        .map(|transport| ListenerConfig {
+            addr: (
+                ip_addr,
+                host_with_port
+                    .port
+                    .map(|port| *port.value())
+                    .unwrap_or_else(|| default_port_for(transport)),
+            )
+                .into(),
+            transport,
+        })
+        .collect()
+}
+
+//listens on all interfaces, with separate IPv4 and IPv6 sockets if the system has IPv6
+pub fn default_listeners() -> Vec<ListenerConfig> {
+    let mut ip_addrs = vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)];
+    if all_system_ip_addrs().iter().any(IpAddr::is_ipv6) {
+        ip_addrs.push(IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+    }
+
+    ip_addrs
+        .into_iter()
+        .flat_map(|ip_addr| {
+            vec![
+                rsip::Transport::Udp,
+                rsip::Transport::Tcp,
+                rsip::Transport::Tls,
+                rsip::Transport::Ws,
+                rsip::Transport::Wss,
+            ]
+            .into_iter()
+            .map(move |transport| ListenerConfig {
+                addr: (ip_addr, default_port_for(transport)).into(),
+                transport,
+            })
+        })
+        .collect()
+}
+
+fn transport_from(transport: &str) -> Option<rsip::Transport> {
+    match transport.to_lowercase().as_str() {
+        "udp" => Some(rsip::Transport::Udp),
+        "tcp" => Some(rsip::Transport::Tcp),
+        "tls" => Some(rsip::Transport::Tls),
+        "ws" => Some(rsip::Transport::Ws),
+        "wss" => Some(rsip::Transport::Wss),
+        _ => None,
+    }
+}
+
+pub fn default_port_for(transport: rsip::Transport) -> u16 {
+    match transport {
+        rsip::Transport::Tls | rsip::Transport::TlsSctp => 5061,
+        rsip::Transport::Ws => 80,
+        rsip::Transport::Wss => 443,
+        _ => 5060,
+    }
+}
+
 fn default_ip_addr_from(ip_addrs: &[IpAddr]) -> IpAddr {
     let default_ip_addr = ip_addrs
         .iter()
@@ -1,7 +1,11 @@
 extern crate envconfig_derive;
 
 mod config;
-pub use config::Config;
+pub use config::{
+    default_listeners, default_port_for, listeners_from, rate_from, timer_overrides_from, Config,
+    Destination, HepConfig, ListenerConfig, PcapConfig, Rate, RateLimits, SipTimers,
+    TimerOverride, TimersConfig, TlsConfig,
+};
 
 use once_cell::sync::Lazy;
 use std::sync::Arc;
@@ -15,6 +19,7 @@ pub use delegate;
 pub use futures;
 pub use futures_util;
 pub use ipnetwork;
+pub use libc;
 pub use log;
 pub use md5;
 pub use once_cell;
@@ -23,7 +28,11 @@ pub use pretty_env_logger;
 pub use rand;
 pub use rand_chacha;
 pub use rsip;
-//pub use rsip_dns;
+pub use rsip_dns;
+pub use socket2;
 pub use tokio;
+pub use tokio_rustls;
+pub use tokio_tungstenite;
 pub use tokio_util;
 pub use uuid;
+pub use webpki_roots;
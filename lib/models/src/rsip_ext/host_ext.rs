@@ -0,0 +1,32 @@
+use common::rsip;
+use std::net::IpAddr;
+
+pub trait HostExt {
+    fn ip_addr(&self) -> Option<IpAddr>;
+    fn bracketed(self) -> Self;
+}
+
+impl HostExt for rsip::Host {
+    //IPv6 references are enclosed in brackets, and might end up parsed as domains
+    fn ip_addr(&self) -> Option<IpAddr> {
+        match self {
+            rsip::Host::IpAddr(ip_addr) => Some(*ip_addr),
+            rsip::Host::Domain(domain) => domain
+                .to_string()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok(),
+        }
+    }
+
+    //IPv6 addresses must be enclosed in brackets when used as SIP hosts (RFC5118 4.1)
+    fn bracketed(self) -> Self {
+        match self {
+            rsip::Host::IpAddr(IpAddr::V6(ip_addr)) => {
+                rsip::Host::Domain(format!("[{}]", ip_addr).into())
+            }
+            host => host,
+        }
+    }
+}
@@ -1,5 +1,13 @@
 mod dialog_ext;
+mod host_ext;
+mod reliable_ext;
 mod request_ext;
+mod transport_ext;
+mod via_ext;
 
 pub use dialog_ext::DialogExt;
+pub use host_ext::HostExt;
+pub use reliable_ext::{rseq_header, RAck, ReliableExt, MAX_RSEQ, OPTION_TAG_100REL};
 pub use request_ext::RequestExt;
+pub use transport_ext::TransportExt;
+pub use via_ext::ViaExt;
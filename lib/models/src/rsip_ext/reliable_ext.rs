@@ -0,0 +1,85 @@
+use common::rsip::{self, headers::UntypedHeader};
+
+pub const OPTION_TAG_100REL: &str = "100rel";
+//RSeq values are between 1 and 2**31 - 1 (RFC3262 7.1)
+pub const MAX_RSEQ: u32 = (1 << 31) - 1;
+
+//RAck of a PRACK, pointing to the reliable provisional it acknowledges (RFC3262 7.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RAck {
+    pub rseq: u32,
+    pub cseq: u32,
+    pub method: rsip::Method,
+}
+
+pub trait ReliableExt {
+    fn supports_100rel(&self) -> bool;
+    fn requires_100rel(&self) -> bool;
+    fn rseq(&self) -> Option<u32>;
+    fn rack(&self) -> Option<RAck>;
+}
+
+impl ReliableExt for rsip::Headers {
+    //Require implies support, some UAs only send the former
+    fn supports_100rel(&self) -> bool {
+        self.iter().any(|header| match header {
+            rsip::Header::Supported(supported) => has_100rel(supported.value()),
+            rsip::Header::Require(require) => has_100rel(require.value()),
+            _ => false,
+        })
+    }
+
+    fn requires_100rel(&self) -> bool {
+        self.iter().any(|header| match header {
+            rsip::Header::Require(require) => has_100rel(require.value()),
+            _ => false,
+        })
+    }
+
+    fn rseq(&self) -> Option<u32> {
+        self.iter().find_map(|header| match header {
+            rsip::Header::Other(name, value) if name.eq_ignore_ascii_case("RSeq") => {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|rseq| (1..=MAX_RSEQ).contains(rseq))
+            }
+            _ => None,
+        })
+    }
+
+    fn rack(&self) -> Option<RAck> {
+        self.iter().find_map(|header| match header {
+            rsip::Header::Other(name, value) if name.eq_ignore_ascii_case("RAck") => {
+                let mut tokens = value.split_whitespace();
+
+                Some(RAck {
+                    rseq: tokens.next()?.parse().ok()?,
+                    cseq: tokens.next()?.parse().ok()?,
+                    method: tokens.next()?.parse().ok()?,
+                })
+            }
+            _ => None,
+        })
+    }
+}
+
+pub fn rseq_header(rseq: u32) -> rsip::Header {
+    rsip::Header::Other("RSeq".into(), rseq.to_string())
+}
+
+impl From<RAck> for rsip::Header {
+    fn from(rack: RAck) -> Self {
+        rsip::Header::Other(
+            "RAck".into(),
+            format!("{} {} {}", rack.rseq, rack.cseq, rack.method),
+        )
+    }
+}
+
+fn has_100rel(value: &str) -> bool {
+    value
+        .split(',')
+        .any(|tag| tag.trim().eq_ignore_ascii_case(OPTION_TAG_100REL))
+}
@@ -1,8 +1,12 @@
+use super::{RAck, ReliableExt, TransportExt};
 use common::rsip::{self, prelude::*};
 
 pub trait RequestExt {
     fn ack_request_from(&self, response: rsip::Response) -> rsip::Request;
+    fn cancel_request(&self) -> rsip::Request;
+    fn prack_request_from(&self, response: &rsip::Response, seqn: u32) -> rsip::Request;
     fn provisional_of(&self, code: impl Into<rsip::StatusCode>) -> rsip::Response;
+    fn arrived_securely(&self) -> bool;
 }
 
 impl RequestExt for rsip::Request {
@@ -54,6 +58,117 @@ impl RequestExt for rsip::Request {
         }
     }
 
+    //RFC3261 9.1, everything that identifies the request is kept, including the branch
+    fn cancel_request(&self) -> rsip::Request {
+        use rsip::{headers::*, Header, Headers, Method};
+
+        let mut headers: Headers = Default::default();
+        headers.push(self.via_header().expect("via header").clone().into());
+        headers.push(self.from_header().expect("from header").clone().into());
+        headers.push(self.to_header().expect("to header").clone().into());
+        headers.push(
+            self.call_id_header()
+                .expect("call_id header")
+                .clone()
+                .into(),
+        );
+        headers.push(
+            typed::CSeq::from((
+                self.cseq_header()
+                    .expect("cseq header")
+                    .typed()
+                    .expect("typed cseq header")
+                    .seq,
+                Method::Cancel,
+            ))
+            .into(),
+        );
+        self.headers
+            .iter()
+            .filter(|header| matches!(header, Header::Route(_)))
+            .for_each(|header| headers.push(header.clone()));
+        headers.push(MaxForwards::default().into());
+        headers.push(Header::ContentLength(Default::default()));
+
+        rsip::Request {
+            method: Method::Cancel,
+            uri: self.uri.clone(),
+            headers,
+            version: Default::default(),
+            body: Default::default(),
+        }
+    }
+
+    //RFC3262 7.1, a PRACK is a new request inside the early dialog, so it gets its own branch
+    fn prack_request_from(&self, response: &rsip::Response, seqn: u32) -> rsip::Request {
+        use rsip::{headers::*, param::Branch, Header, Headers, Method, Param};
+
+        let mut via_header = self
+            .via_header()
+            .expect("via header")
+            .typed()
+            .expect("typed via header");
+        via_header.params = via_header
+            .params
+            .into_iter()
+            .map(|param| match param {
+                Param::Branch(_) => Param::Branch(Branch::new(format!(
+                    "z9hG4bK{}",
+                    common::uuid::Uuid::new_v4().to_simple()
+                ))),
+                param => param,
+            })
+            .collect();
+
+        let cseq_header = self
+            .cseq_header()
+            .expect("cseq header")
+            .typed()
+            .expect("typed cseq header");
+
+        let mut headers: Headers = Default::default();
+        headers.push(via_header.into());
+        headers.push(self.from_header().expect("from header").clone().into());
+        headers.push(response.to_header().expect("to header").clone().into());
+        headers.push(
+            self.call_id_header()
+                .expect("call_id header")
+                .clone()
+                .into(),
+        );
+        headers.push(typed::CSeq::from((seqn, Method::PRack)).into());
+        headers.push(
+            RAck {
+                rseq: response.headers.rseq().expect("rseq header"),
+                cseq: cseq_header.seq,
+                method: cseq_header.method,
+            }
+            .into(),
+        );
+        self.headers
+            .iter()
+            .filter(|header| matches!(header, Header::Route(_)))
+            .for_each(|header| headers.push(header.clone()));
+        headers.push(MaxForwards::default().into());
+        headers.push(Header::ContentLength(Default::default()));
+
+        //the remote target is known as soon as the early dialog is
+        let uri = response
+            .contact_header()
+            .ok()
+            .and_then(|header| header.typed().ok())
+            .map(|header| header.uri)
+            .unwrap_or_else(|| self.uri.clone());
+
+        rsip::Request {
+            method: Method::PRack,
+            uri,
+            headers,
+            version: Default::default(),
+            body: Default::default(),
+        }
+    }
+
     fn provisional_of(&self, status_code: impl Into<rsip::StatusCode>) -> rsip::Response {
         use rsip::{headers::*, Headers};
 
@@ -89,4 +204,14 @@ impl RequestExt for rsip::Request {
             body: Default::default(),
         }
     }
+
+    //the transport layer makes sure that the topmost Via of incoming requests
+    //claims a secure transport only if the request did arrive over one
+    fn arrived_securely(&self) -> bool {
+        self.via_header()
+            .ok()
+            .and_then(|h| h.typed().ok())
+            .map(|h| h.transport.is_secure())
+            .unwrap_or(false)
+    }
 }
@@ -0,0 +1,14 @@
+use common::rsip;
+
+pub trait TransportExt {
+    fn is_secure(&self) -> bool;
+}
+
+impl TransportExt for rsip::Transport {
+    fn is_secure(&self) -> bool {
+        matches!(
+            self,
+            rsip::Transport::Tls | rsip::Transport::TlsSctp | rsip::Transport::Wss
+        )
+    }
+}
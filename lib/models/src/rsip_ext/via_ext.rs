@@ -0,0 +1,55 @@
+use super::HostExt;
+use common::rsip;
+use std::net::IpAddr;
+
+pub trait ViaExt {
+    fn received_ip_addr(&self) -> Option<IpAddr>;
+    fn peer_ip_addr(&self) -> Option<IpAddr>;
+    fn has_rport(&self) -> bool;
+    fn rport_value(&self) -> Option<u16>;
+    fn peer_port(&self) -> Option<u16>;
+}
+
+impl ViaExt for rsip::typed::Via {
+    //received holds IPv6 addresses without brackets (RFC5118 4.5),
+    //but some implementations add them anyway
+    fn received_ip_addr(&self) -> Option<IpAddr> {
+        self.params.iter().find_map(|param| match param {
+            rsip::Param::Received(received) => received
+                .value()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok(),
+            _ => None,
+        })
+    }
+
+    //the address the message was sent from, as far as the Via can tell
+    fn peer_ip_addr(&self) -> Option<IpAddr> {
+        self.received_ip_addr()
+            .or_else(|| self.sent_by().host().ip_addr())
+    }
+
+    fn has_rport(&self) -> bool {
+        self.params.iter().any(is_rport)
+    }
+
+    //rport is empty in requests, and holds the source port once a server has seen it (RFC3581)
+    fn rport_value(&self) -> Option<u16> {
+        self.params.iter().find_map(|param| match param {
+            rsip::Param::Other(_, Some(value)) if is_rport(param) => value.value().parse().ok(),
+            _ => None,
+        })
+    }
+
+    //the port the message was sent from, as far as the Via can tell
+    fn peer_port(&self) -> Option<u16> {
+        self.rport_value()
+            .or_else(|| self.sent_by().port().map(|port| *port.value()))
+    }
+}
+
+fn is_rport(param: &rsip::Param) -> bool {
+    matches!(param, rsip::Param::Other(name, _) if name.value().eq_ignore_ascii_case("rport"))
+}
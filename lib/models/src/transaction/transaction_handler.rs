@@ -1,11 +1,13 @@
 use crate::{
     transaction::{TransactionId, TransactionLayerMsg},
+    transport::TransportError,
     Error,
 };
 use common::{
     rsip,
     tokio::sync::{mpsc, oneshot},
 };
+use std::net::SocketAddr;
 
 #[derive(Debug, Clone)]
 pub struct TransactionHandler {
@@ -25,7 +27,16 @@ impl TransactionHandler {
         Ok(self.tx.send(TransactionLayerMsg::Reply(msg)).await?)
     }
 
-    pub async fn transport_error(&self, msg: rsip::SipMessage, error: String) -> Result<(), Error> {
+    //abandons a pending INVITE client transaction, possible only once a provisional arrived
+    pub async fn cancel(&self, transaction_id: TransactionId) -> Result<(), Error> {
+        Ok(self.tx.send(TransactionLayerMsg::Cancel(transaction_id)).await?)
+    }
+
+    pub async fn transport_error(
+        &self,
+        msg: rsip::SipMessage,
+        error: TransportError,
+    ) -> Result<(), Error> {
         Ok(self
             .tx
             .send(TransactionLayerMsg::TransportError(msg, error))
@@ -62,6 +73,19 @@ impl TransactionHandler {
             .await?)
     }
 
+    //where, and over what, the transport sent a request of a client transaction
+    pub async fn resolved(
+        &self,
+        msg: rsip::Request,
+        peer: SocketAddr,
+        transport: rsip::Transport,
+    ) -> Result<(), Error> {
+        Ok(self
+            .tx
+            .send(TransactionLayerMsg::Resolved(msg, peer, transport))
+            .await?)
+    }
+
     pub async fn has_transaction_for(&self, transaction_id: TransactionId) -> Result<bool, Error> {
         let (tx, rx) = oneshot::channel();
 
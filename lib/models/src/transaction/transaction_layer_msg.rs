@@ -1,4 +1,6 @@
+use crate::transport::TransportError;
 use common::{rsip, tokio::sync::oneshot::Sender};
+use std::net::SocketAddr;
 
 #[derive(Debug)]
 pub enum TransactionLayerMsg {
@@ -7,12 +9,12 @@ pub enum TransactionLayerMsg {
     NewUac(rsip::Request),                               //from tu
     NewUas(rsip::Request, Option<rsip::Response>),       //from tu
     Reply(rsip::Response),                               //from tu
+    Cancel(TransactionId),                               //from tu
     Incoming(rsip::SipMessage),                          //from transport
     TransportError(rsip::SipMessage, TransportError),
-    HasTransaction(TransactionId, Sender<bool>), //from transport
+    HasTransaction(TransactionId, Sender<bool>),         //from transport
+    Resolved(rsip::Request, SocketAddr, rsip::Transport), //from transport
 }
 
-//TODO: add proper error type here
-pub type TransportError = String;
 //TODO: add proper (rsip) type here
 pub type TransactionId = rsip::param::Branch;
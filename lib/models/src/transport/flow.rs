@@ -0,0 +1,30 @@
+use common::rsip::Transport;
+use std::{fmt, net::SocketAddr};
+
+//the path a UA reaches us over (RFC5626 3.3): the transport and the address we see the UA
+//at, which behind a NAT is the public one. For stream transports it maps to a connection
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Flow {
+    pub transport: Transport,
+    pub peer: SocketAddr,
+}
+
+impl fmt::Display for Flow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.transport.to_string().to_lowercase(),
+            self.peer
+        )
+    }
+}
+
+impl From<(Transport, SocketAddr)> for Flow {
+    fn from(tuple: (Transport, SocketAddr)) -> Self {
+        Self {
+            transport: tuple.0,
+            peer: tuple.1,
+        }
+    }
+}
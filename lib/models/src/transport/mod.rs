@@ -1,13 +1,19 @@
+mod flow;
 mod request_msg;
 mod response_msg;
 mod transport_handler;
 mod transport_layer_msg;
+mod transport_error;
 mod transport_msg;
+mod transport_tuple;
 mod udp_tuple;
 
+pub use flow::Flow;
 pub use request_msg::RequestMsg;
 pub use response_msg::ResponseMsg;
 pub use transport_handler::TransportHandler;
 pub use transport_layer_msg::TransportLayerMsg;
+pub use transport_error::TransportError;
 pub use transport_msg::TransportMsg;
+pub use transport_tuple::TransportTuple;
 pub use udp_tuple::UdpTuple;
@@ -1,7 +1,7 @@
 use crate::{
-    rsip_ext::DialogExt,
+    rsip_ext::{DialogExt, TransportExt},
     transaction::TransactionId,
-    transport::{TransportMsg, UdpTuple},
+    transport::{TransportMsg, TransportTuple, UdpTuple},
     tu::DialogId,
     Error,
 };
@@ -33,6 +33,11 @@ impl RequestMsg {
     pub fn dialog_id(&self) -> Result<DialogId, Error> {
         self.sip_request.dialog_id()
     }
+
+    //whether it was (or is about to be) carried over TLS
+    pub fn is_secure(&self) -> bool {
+        self.transport.is_secure()
+    }
 }
 
 impl From<(rsip::Request, SocketAddr, Transport)> for RequestMsg {
@@ -57,6 +62,18 @@ impl TryFrom<UdpTuple> for RequestMsg {
     }
 }
 
+impl TryFrom<TransportTuple> for RequestMsg {
+    type Error = crate::Error;
+
+    fn try_from(transport_tuple: TransportTuple) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sip_request: transport_tuple.bytes.try_into()?,
+            peer: transport_tuple.peer,
+            transport: transport_tuple.transport,
+        })
+    }
+}
+
 impl TryFrom<TransportMsg> for RequestMsg {
     type Error = crate::Error;
 
@@ -1,7 +1,7 @@
 use crate::{
-    rsip_ext::DialogExt,
+    rsip_ext::{DialogExt, TransportExt},
     transaction::TransactionId,
-    transport::{TransportMsg, UdpTuple},
+    transport::{TransportMsg, TransportTuple, UdpTuple},
     tu::DialogId,
     Error,
 };
@@ -34,6 +34,10 @@ impl ResponseMsg {
     pub fn dialog_id(&self) -> Result<DialogId, Error> {
         self.sip_response.dialog_id()
     }
+
+    pub fn is_secure(&self) -> bool {
+        self.transport.is_secure()
+    }
 }
 
 impl From<(rsip::Response, SocketAddr, Transport)> for ResponseMsg {
@@ -58,6 +62,18 @@ impl TryFrom<UdpTuple> for ResponseMsg {
     }
 }
 
+impl TryFrom<TransportTuple> for ResponseMsg {
+    type Error = crate::Error;
+
+    fn try_from(transport_tuple: TransportTuple) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sip_response: transport_tuple.bytes.try_into()?,
+            peer: transport_tuple.peer,
+            transport: transport_tuple.transport,
+        })
+    }
+}
+
 impl TryFrom<TransportMsg> for ResponseMsg {
     type Error = crate::Error;
 
@@ -0,0 +1,57 @@
+use common::libc::{EHOSTUNREACH, EMSGSIZE, ENETUNREACH};
+use std::{fmt, io};
+
+//why a message could not be delivered, so that transactions and dialogs can react to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    Dns(String),
+    Unreachable(String),
+    ConnectionRefused(String),
+    TlsHandshake(String),
+    Timeout(String),
+    MessageTooLarge(String),
+    Other(String),
+}
+
+impl TransportError {
+    //another target of the same destination might still work (RFC3263 4.3), while a TLS
+    //or a size problem would only repeat itself
+    pub fn should_fail_over(&self) -> bool {
+        matches!(
+            self,
+            Self::Dns(_) | Self::Unreachable(_) | Self::ConnectionRefused(_) | Self::Timeout(_)
+        )
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Dns(reason) => write!(f, "dns failure: {}", reason),
+            Self::Unreachable(reason) => write!(f, "destination unreachable: {}", reason),
+            Self::ConnectionRefused(reason) => write!(f, "connection refused: {}", reason),
+            Self::TlsHandshake(reason) => write!(f, "tls handshake failed: {}", reason),
+            Self::Timeout(reason) => write!(f, "timed out: {}", reason),
+            Self::MessageTooLarge(reason) => write!(f, "message too large: {}", reason),
+            Self::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+//ICMP errors reach us as errors of the next socket operation, with errno values that
+//io::ErrorKind doesn't tell apart
+impl From<&io::Error> for TransportError {
+    fn from(error: &io::Error) -> Self {
+        let reason = error.to_string();
+
+        match (error.kind(), error.raw_os_error()) {
+            (io::ErrorKind::ConnectionRefused, _) => Self::ConnectionRefused(reason),
+            (io::ErrorKind::TimedOut, _) => Self::Timeout(reason),
+            (_, Some(ENETUNREACH)) | (_, Some(EHOSTUNREACH)) => Self::Unreachable(reason),
+            (_, Some(EMSGSIZE)) => Self::MessageTooLarge(reason),
+            _ => Self::Other(reason),
+        }
+    }
+}
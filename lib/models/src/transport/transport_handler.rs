@@ -1,8 +1,9 @@
 use crate::{
-    transport::{TransportLayerMsg, UdpTuple},
+    transport::{Flow, TransportLayerMsg, TransportTuple},
     Error,
 };
 use common::{rsip, tokio::sync::mpsc::Sender};
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct TransportHandler {
@@ -14,13 +15,34 @@ impl TransportHandler {
         Self { tx }
     }
 
-    pub async fn process(&self, msg: UdpTuple) -> Result<(), Error> {
-        Ok(self.tx.send(TransportLayerMsg::Incoming(msg)).await?)
+    pub async fn process(&self, msg: impl Into<TransportTuple>) -> Result<(), Error> {
+        Ok(self.tx.send(TransportLayerMsg::Incoming(msg.into())).await?)
     }
 
     pub async fn send(&self, msg: rsip::SipMessage) -> Result<(), Error> {
         Ok(self.tx.send(TransportLayerMsg::Outgoing(msg)).await?)
     }
+
+    pub async fn failover(&self, msg: rsip::Request) -> Result<(), Error> {
+        Ok(self.tx.send(TransportLayerMsg::Failover(msg)).await?)
+    }
+
+    //requests to the contact go over the flow it registered over, until the binding expires
+    pub async fn bind(
+        &self,
+        contact: rsip::Uri,
+        flow: Flow,
+        expires: Duration,
+    ) -> Result<(), Error> {
+        Ok(self
+            .tx
+            .send(TransportLayerMsg::Bind(contact, flow, expires))
+            .await?)
+    }
+
+    pub async fn unbind(&self, contact: rsip::Uri) -> Result<(), Error> {
+        Ok(self.tx.send(TransportLayerMsg::Unbind(contact)).await?)
+    }
 }
 
 impl From<Sender<TransportLayerMsg>> for TransportHandler {
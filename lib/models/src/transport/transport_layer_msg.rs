@@ -1,11 +1,15 @@
-use crate::transport::UdpTuple;
+use crate::transport::{Flow, TransportTuple, UdpTuple};
 use common::rsip;
+use std::time::Duration;
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum TransportLayerMsg {
-    Outgoing(rsip::SipMessage), //from transaction or tu
-    Incoming(UdpTuple),         //from network
+    Outgoing(rsip::SipMessage),      //from transaction or tu
+    Incoming(TransportTuple),        //from network
+    Failover(rsip::Request),         //from transaction, when the current target timed out
+    Bind(rsip::Uri, Flow, Duration), //from tu, when a contact registers over a flow
+    Unbind(rsip::Uri),               //from tu, when a contact unregisters
 }
 
 impl From<rsip::SipMessage> for TransportLayerMsg {
@@ -28,6 +32,12 @@ impl From<rsip::Response> for TransportLayerMsg {
 
 impl From<UdpTuple> for TransportLayerMsg {
     fn from(from: UdpTuple) -> Self {
+        Self::Incoming(from.into())
+    }
+}
+
+impl From<TransportTuple> for TransportLayerMsg {
+    fn from(from: TransportTuple) -> Self {
         Self::Incoming(from)
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
-    rsip_ext::DialogExt,
+    rsip_ext::{DialogExt, TransportExt},
     transaction::TransactionId,
-    transport::{RequestMsg, ResponseMsg, UdpTuple},
+    transport::{RequestMsg, ResponseMsg, TransportTuple, UdpTuple},
     tu::DialogId,
     Error,
 };
@@ -30,6 +30,10 @@ impl TransportMsg {
     pub fn dialog_id(&self) -> Result<DialogId, Error> {
         self.sip_message.dialog_id()
     }
+
+    pub fn is_secure(&self) -> bool {
+        self.transport.is_secure()
+    }
 }
 
 impl From<(rsip::SipMessage, SocketAddr, Transport)> for TransportMsg {
@@ -54,6 +58,18 @@ impl TryFrom<UdpTuple> for TransportMsg {
     }
 }
 
+impl TryFrom<TransportTuple> for TransportMsg {
+    type Error = crate::Error;
+
+    fn try_from(transport_tuple: TransportTuple) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sip_message: transport_tuple.bytes.try_into()?,
+            peer: transport_tuple.peer,
+            transport: transport_tuple.transport,
+        })
+    }
+}
+
 impl From<RequestMsg> for TransportMsg {
     fn from(from: RequestMsg) -> Self {
         TransportMsg {
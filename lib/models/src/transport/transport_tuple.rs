@@ -0,0 +1,62 @@
+use crate::transport::{RequestMsg, ResponseMsg, TransportMsg, UdpTuple};
+use common::{bytes::Bytes, rsip::Transport};
+use std::net::SocketAddr;
+
+//raw bytes as they arrived from (or are about to leave to) the network,
+//together with the transport they were carried over
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransportTuple {
+    pub bytes: Bytes,
+    pub peer: SocketAddr,
+    pub transport: Transport,
+}
+
+impl From<(Bytes, SocketAddr, Transport)> for TransportTuple {
+    fn from(triple: (Bytes, SocketAddr, Transport)) -> Self {
+        Self {
+            bytes: triple.0,
+            peer: triple.1,
+            transport: triple.2,
+        }
+    }
+}
+
+impl From<UdpTuple> for TransportTuple {
+    fn from(from: UdpTuple) -> Self {
+        Self {
+            bytes: from.bytes,
+            peer: from.peer,
+            transport: Transport::Udp,
+        }
+    }
+}
+
+impl From<RequestMsg> for TransportTuple {
+    fn from(from: RequestMsg) -> Self {
+        Self {
+            bytes: from.sip_request.into(),
+            peer: from.peer,
+            transport: from.transport,
+        }
+    }
+}
+
+impl From<ResponseMsg> for TransportTuple {
+    fn from(from: ResponseMsg) -> Self {
+        Self {
+            bytes: from.sip_response.into(),
+            peer: from.peer,
+            transport: from.transport,
+        }
+    }
+}
+
+impl From<TransportMsg> for TransportTuple {
+    fn from(from: TransportMsg) -> Self {
+        Self {
+            bytes: from.sip_message.into(),
+            peer: from.peer,
+            transport: from.transport,
+        }
+    }
+}
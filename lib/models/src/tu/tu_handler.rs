@@ -1,4 +1,8 @@
-use crate::{tu::TuLayerMsg, Error};
+use crate::{
+    transport::{RequestMsg, TransportError},
+    tu::TuLayerMsg,
+    Error,
+};
 use common::{rsip, tokio::sync::mpsc::Sender};
 
 #[derive(Debug, Clone)]
@@ -15,7 +19,15 @@ impl TuHandler {
         Ok(self.tx.send(TuLayerMsg::Incoming(msg)).await?)
     }
 
-    pub async fn transport_error(&self, msg: rsip::SipMessage, error: String) -> Result<(), Error> {
+    pub async fn process_request(&self, msg: RequestMsg) -> Result<(), Error> {
+        Ok(self.tx.send(TuLayerMsg::IncomingRequest(msg)).await?)
+    }
+
+    pub async fn transport_error(
+        &self,
+        msg: rsip::SipMessage,
+        error: TransportError,
+    ) -> Result<(), Error> {
         Ok(self.tx.send(TuLayerMsg::TransportError(msg, error)).await?)
     }
 }
@@ -1,3 +1,4 @@
+use crate::transport::{RequestMsg, TransportError};
 use common::rsip;
 
 //TODO: probably makes sense to split incoming from transport
@@ -5,9 +6,8 @@ use common::rsip;
 #[derive(Debug, Clone)]
 pub enum TuLayerMsg {
     Incoming(rsip::SipMessage),
+    //requests from the network, along with the flow they arrived over
+    IncomingRequest(RequestMsg),
     Outgoing(rsip::SipMessage),
     TransportError(rsip::SipMessage, TransportError),
 }
-
-//TODO: add proper error type here
-pub type TransportError = String;
@@ -4,7 +4,11 @@ use common::{
     rsip,
     tokio::sync::{mpsc::error::SendError, oneshot::error::RecvError},
 };
-use models::{transaction::TransactionLayerMsg, transport::TransportLayerMsg, tu::TuLayerMsg};
+use models::{
+    transaction::TransactionLayerMsg,
+    transport::{TransportError, TransportLayerMsg},
+    tu::TuLayerMsg,
+};
 use std::{error::Error as StdError, fmt};
 
 #[derive(Debug)]
@@ -21,8 +25,10 @@ pub enum ErrorKind {
     Custom(String),
     SipHelpers(String),
     Io(std::io::Error),
+    Transport(TransportError),
     Transaction(TransactionError),
     Dialog(DialogError),
+    Sip(SipError),
     Channel(String),
     Store(store::Error),
 }
@@ -39,12 +45,65 @@ pub enum DialogError {
     UnexpectedState,
 }
 
+//a request we can't serve, each maps to the final response the peer gets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SipError {
+    BadRequest(String),
+    Forbidden(String),
+    NotFound(String),
+    UnsupportedUriScheme(String),
+    BadExtension(Vec<String>),
+    IntervalTooBrief(u32),
+    ServerInternal(String),
+}
+
+impl SipError {
+    pub fn status_code(&self) -> rsip::StatusCode {
+        match self {
+            Self::BadRequest(_) => 400.into(),
+            Self::Forbidden(_) => 403.into(),
+            Self::NotFound(_) => 404.into(),
+            Self::UnsupportedUriScheme(_) => 416.into(),
+            Self::BadExtension(_) => 420.into(),
+            Self::IntervalTooBrief(_) => 423.into(),
+            Self::ServerInternal(_) => 500.into(),
+        }
+    }
+}
+
+impl fmt::Display for SipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BadRequest(reason)
+            | Self::Forbidden(reason)
+            | Self::NotFound(reason)
+            | Self::UnsupportedUriScheme(reason)
+            | Self::ServerInternal(reason) => write!(f, "{}", reason),
+            Self::BadExtension(option_tags) => {
+                write!(f, "unsupported extensions: {}", option_tags.join(", "))
+            }
+            Self::IntervalTooBrief(min_expires) => {
+                write!(f, "expiration must be at least {} seconds", min_expires)
+            }
+        }
+    }
+}
+
 impl Error {
     pub fn custom(reason: impl Into<String>) -> Self {
         Self {
             kind: ErrorKind::from(reason.into()),
         }
     }
+
+    //the final response a request failing with this error should get
+    pub fn status_code(&self) -> rsip::StatusCode {
+        match &self.kind {
+            ErrorKind::Sip(inner) => inner.status_code(),
+            ErrorKind::Rsip(_) => 400.into(),
+            _ => 500.into(),
+        }
+    }
 }
 
 impl From<Option<ErrorKind>> for ErrorKind {
@@ -62,6 +121,8 @@ impl fmt::Display for ErrorKind {
         match self {
             ErrorKind::Models(ref inner) => write!(f, "models transformation error: {}", inner),
             ErrorKind::Custom(ref inner) => write!(f, "{}", inner),
+            ErrorKind::Transport(ref inner) => write!(f, "{}", inner),
+            ErrorKind::Sip(ref inner) => write!(f, "{}", inner),
             _ => write!(f, "unknown error, {:?}", self),
         }
     }
@@ -114,6 +175,29 @@ impl From<std::io::Error> for ErrorKind {
     }
 }
 
+impl From<TransportError> for ErrorKind {
+    fn from(e: TransportError) -> Self {
+        ErrorKind::Transport(e)
+    }
+}
+
+//what the upper layers get to know when a message could not be sent
+impl From<&Error> for TransportError {
+    fn from(e: &Error) -> Self {
+        match &e.kind {
+            ErrorKind::Transport(inner) => inner.clone(),
+            ErrorKind::Io(inner) => inner.into(),
+            _ => TransportError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<SipError> for ErrorKind {
+    fn from(e: SipError) -> Self {
+        ErrorKind::Sip(e)
+    }
+}
+
 impl From<TransactionError> for ErrorKind {
     fn from(e: TransactionError) -> Self {
         ErrorKind::Transaction(e)
@@ -7,7 +7,7 @@ pub mod transaction;
 pub mod transport;
 pub mod tu;
 
-pub use error::{Error, ErrorKind};
+pub use error::{Error, ErrorKind, SipError};
 pub use transaction::Transaction;
 pub use transport::Transport;
 pub use tu::ReqProcessor;
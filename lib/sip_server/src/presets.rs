@@ -54,6 +54,47 @@ pub fn response_from(
     })
 }
 
+//the final response to a request the TU failed to serve, with the reason in a Warning header
+//(RFC3261 20.43), and what the peer needs in order to retry for 420 and 423
+pub fn error_response_from(
+    request: rsip::Request,
+    error: &crate::Error,
+) -> Result<rsip::Response, crate::Error> {
+    use crate::{ErrorKind, SipError};
+    use rsip::headers::{MinExpires, Unsupported};
+
+    let mut response = response_from(request, error.status_code())?;
+    let reason = match &error.kind {
+        ErrorKind::Sip(SipError::BadExtension(option_tags)) => {
+            response
+                .headers
+                .push(Unsupported::new(option_tags.join(", ")).into());
+            error.to_string()
+        }
+        ErrorKind::Sip(SipError::IntervalTooBrief(min_expires)) => {
+            response
+                .headers
+                .push(MinExpires::new(min_expires.to_string()).into());
+            error.to_string()
+        }
+        ErrorKind::Sip(_) | ErrorKind::Rsip(_) => error.to_string(),
+        //internals are not the peer's business
+        _ => "internal server error".into(),
+    };
+    response.headers.push(warning_for(&reason).into());
+
+    Ok(response)
+}
+
+//a miscellaneous warning (RFC3261 20.43) from us, telling the peer why its request failed
+pub fn warning_for(reason: &str) -> rsip::headers::Warning {
+    rsip::headers::Warning::new(format!(
+        "399 {} \"{}\"",
+        common::CONFIG.default_addr(),
+        reason.replace('"', "'")
+    ))
+}
+
 pub fn create_404_from(request: rsip::Request) -> Result<rsip::Response, crate::Error> {
     let mut headers: rsip::Headers = Default::default();
     headers.push(request.via_header()?.clone().into());
@@ -1,17 +1,31 @@
+mod scheduler;
 pub mod sm;
 
+pub use scheduler::{Deadlines, Scheduler};
+
 use crate::{error::TransactionError, Error};
 use common::{
-    rsip::{self, message::HeadersExt},
+    rsip::{self, prelude::*},
     tokio::{self, sync::RwLock},
+    SipTimers, TimersConfig,
 };
 use models::{
     receivers::TrxReceiver,
+    rsip_ext::{ReliableExt, ViaExt},
     transaction::{TransactionHandler, TransactionId, TransactionLayerMsg},
+    transport::TransportError,
     Handlers,
 };
 use sm::TrxStateSm;
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -22,20 +36,39 @@ pub struct Transaction {
 #[derive(Debug)]
 pub struct Inner {
     handlers: Handlers,
+    timers: TimersConfig,
+    scheduler: Scheduler,
+    reaped: AtomicUsize,
     pub state: RwLock<HashMap<TransactionId, TrxStateSm>>,
+    //INVITE server transactions sending reliable provisionals, by the RAck a PRACK would carry
+    reliables: RwLock<HashMap<String, TransactionId>>,
 }
 
 //TODO: make impl here thinner by moving stuff over to TransactionsSm, like in dialogs
 impl Transaction {
     pub fn new(handlers: Handlers, messages_rx: TrxReceiver) -> Result<Self, Error> {
+        Self::with_timers(handlers, common::CONFIG.timers.clone(), messages_rx)
+    }
+
+    //same as new, but with other timers than the configured ones
+    pub fn with_timers(
+        handlers: Handlers,
+        timers: TimersConfig,
+        messages_rx: TrxReceiver,
+    ) -> Result<Self, Error> {
+        let (scheduler, deadlines) = Scheduler::new();
         let me = Self {
             inner: Arc::new(Inner {
                 handlers,
+                timers,
+                scheduler,
+                reaped: Default::default(),
                 state: RwLock::new(Default::default()),
+                reliables: RwLock::new(Default::default()),
             }),
         };
 
-        me.run(messages_rx);
+        me.run(messages_rx, deadlines);
 
         Ok(me)
     }
@@ -44,11 +77,21 @@ impl Transaction {
         self.inner.handlers.transaction.clone()
     }
 
-    fn run(&self, messages: TrxReceiver) {
+    //transactions kept in memory, including finished ones that still linger
+    pub async fn transactions(&self) -> usize {
+        self.inner.state.read().await.len()
+    }
+
+    //finished transactions that have been evicted so far
+    pub fn reaped_transactions(&self) -> usize {
+        self.inner.reaped.load(Ordering::Relaxed)
+    }
+
+    fn run(&self, messages: TrxReceiver, deadlines: Deadlines) {
         let inner = self.inner.clone();
         tokio::spawn(async move { inner.run(messages).await });
         let inner_trx = self.inner.clone();
-        tokio::spawn(async move { inner_trx.run_transactions().await });
+        tokio::spawn(async move { inner_trx.run_transactions(deadlines).await });
     }
 }
 
@@ -73,6 +116,9 @@ impl Inner {
                 self.new_uas_transaction(msg, response).await?
             }
             TransactionLayerMsg::Reply(msg) => self.process_tu_reply(msg).await?,
+            TransactionLayerMsg::Cancel(transaction_id) => {
+                self.cancel_uac_transaction(transaction_id).await?
+            }
             TransactionLayerMsg::Incoming(msg) => self.process_incoming(msg).await?,
             TransactionLayerMsg::TransportError(msg, error) => {
                 self.process_transport_error(msg, error).await?
@@ -80,6 +126,9 @@ impl Inner {
             TransactionLayerMsg::HasTransaction(transaction_id, tx) => tx
                 .send(self.exists(transaction_id).await)
                 .map_err(|e| Error::custom(format!("could not send respond: {}", e)))?,
+            TransactionLayerMsg::Resolved(request, peer, transport) => {
+                self.process_resolved(request, peer, transport).await?
+            }
         };
 
         Ok(())
@@ -88,17 +137,22 @@ impl Inner {
     pub async fn exists(&self, transaction_id: TransactionId) -> bool {
         let state = self.state.read().await;
 
-        state.get(&transaction_id).is_some()
+        state.get(&cancel_id_of(&transaction_id)).is_some() || state.get(&transaction_id).is_some()
     }
 
     async fn process_transport_error(
         &self,
         msg: rsip::SipMessage,
-        reason: String,
+        error: TransportError,
     ) -> Result<(), Error> {
-        let transaction_id = msg.transaction_id()?.expect("transaction_id");
+        let cseq_method = match &msg {
+            rsip::SipMessage::Request(request) => request.method,
+            rsip::SipMessage::Response(response) => response.cseq_header()?.typed()?.method,
+        };
+        let transaction_id = key_of(msg.transaction_id()?.expect("transaction_id"), cseq_method);
         if let Some(sm) = self.state.read().await.get(&transaction_id) {
-            sm.transport_error(reason).await;
+            sm.transport_error(error).await;
+            self.schedule(sm).await;
 
             return Ok(());
         }
@@ -108,11 +162,12 @@ impl Inner {
 
     async fn new_uac_invite_transaction(&self, msg: rsip::Request) -> Result<(), Error> {
         self.handlers.transport.send(msg.clone().into()).await?;
-        let transaction_data = sm::uac::TrxStateMachine::new(self.handlers.clone(), msg.clone())?;
-        {
-            let mut data = self.state.write().await;
-            data.insert(transaction_data.id.clone(), transaction_data.into());
-        }
+        let transaction_data = sm::uac::TrxStateMachine::new(
+            self.handlers.clone(),
+            msg.clone(),
+            self.client_timers_for(&msg),
+        )?;
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
         Ok(())
     }
 
@@ -122,34 +177,208 @@ impl Inner {
         response: Option<rsip::Response>,
     ) -> Result<(), Error> {
         self.handlers.transport.send(request.clone().into()).await?;
-        let transaction_data =
-            sm::uas::TrxStateMachine::new(self.handlers.clone(), request.clone(), response)?;
-
-        {
-            let mut data = self.state.write().await;
-            data.insert(transaction_data.id.clone(), transaction_data.into());
+        let timers = self.server_timers_for(&request);
+        let transaction_data = sm::uas::TrxStateMachine::new(
+            self.handlers.clone(),
+            request.clone(),
+            response,
+            timers,
+        )?;
+
+        if let Some(reliable_key) = transaction_data.reliable_key.clone() {
+            self.reliables
+                .write()
+                .await
+                .insert(reliable_key, transaction_data.id.clone());
         }
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
 
         Ok(())
     }
 
-    async fn new_uac_transaction(&self, _: rsip::Request) -> Result<(), Error> {
-        unimplemented!("");
+    async fn new_uac_transaction(&self, msg: rsip::Request) -> Result<(), Error> {
+        self.handlers.transport.send(msg.clone().into()).await?;
+        let transaction_data = sm::uac_non_invite::TrxStateMachine::new(
+            self.handlers.clone(),
+            msg.clone(),
+            self.client_timers_for(&msg),
+        )?;
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
+        Ok(())
     }
 
     async fn new_uas_transaction(
         &self,
-        _: rsip::Request,
-        _: Option<rsip::Response>,
+        request: rsip::Request,
+        response: Option<rsip::Response>,
+    ) -> Result<(), Error> {
+        if request.method == rsip::Method::PRack {
+            return self.new_uas_prack_transaction(request, response).await;
+        }
+
+        let timers = self.server_timers_for(&request);
+        let mut transaction_data =
+            sm::uas_non_invite::TrxStateMachine::new(self.handlers.clone(), request, timers)?;
+        if let Some(response) = response {
+            transaction_data.next(Some(response.into())).await;
+        }
+
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
+
+        Ok(())
+    }
+
+    //RFC3262 3, a PRACK is a non-INVITE transaction of its own, answered with a 200 when it
+    //acknowledges a reliable provisional of ours, and 481 when there is nothing to acknowledge
+    async fn new_uas_prack_transaction(
+        &self,
+        request: rsip::Request,
+        response: Option<rsip::Response>,
+    ) -> Result<(), Error> {
+        let acknowledged = match request.headers.rack() {
+            Some(rack) if rack.method == rsip::Method::Invite => {
+                let reliable_key = reliable_key_of(&request, rack.cseq)?;
+                let transaction_id = self.reliables.read().await.get(&reliable_key).cloned();
+                match transaction_id {
+                    Some(transaction_id) => match self.state.read().await.get(&transaction_id) {
+                        Some(sm) => {
+                            let acknowledged = sm.uas_prack(&rack).await?;
+                            self.schedule(sm).await;
+                            acknowledged
+                        }
+                        None => false,
+                    },
+                    None => false,
+                }
+            }
+            _ => false,
+        };
+
+        let response = match (response, acknowledged) {
+            (Some(response), true) => response,
+            (_, true) => crate::presets::response_from(request.clone(), 200.into())?,
+            (_, false) => crate::presets::response_from(request.clone(), 481.into())?,
+        };
+
+        let timers = self.server_timers_for(&request);
+        let mut transaction_data =
+            sm::uas_non_invite::TrxStateMachine::new(self.handlers.clone(), request, timers)?;
+        transaction_data.next(Some(response.into())).await;
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
+
+        Ok(())
+    }
+
+    //RFC3261 9.1, a CANCEL goes out in a transaction of its own, over the same branch
+    async fn cancel_uac_transaction(&self, transaction_id: TransactionId) -> Result<(), Error> {
+        let request = match self.state.read().await.get(&transaction_id) {
+            Some(sm) => sm.uac_cancel_request().await?,
+            None => return Err(Error::from(TransactionError::NotFound)),
+        };
+
+        match request {
+            Some(request) => self.start_cancel(transaction_id, request).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn start_cancel(
+        &self,
+        transaction_id: TransactionId,
+        request: rsip::Request,
+    ) -> Result<(), Error> {
+        self.handlers.transport.send(request.clone().into()).await?;
+        let timers = self.client_timers_for(&request);
+        let mut transaction_data =
+            sm::uac_non_invite::TrxStateMachine::new(self.handlers.clone(), request, timers)?;
+        transaction_data.id = cancel_id_of(&transaction_id);
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
+
+        Ok(())
+    }
+
+    //RFC3261 9.2, the INVITE server transaction is terminated and the CANCEL is answered
+    //by a transaction of its own, which also takes care of any retransmissions of it
+    async fn process_incoming_cancel(&self, request: rsip::Request) -> Result<(), Error> {
+        let transaction_id = request.transaction_id()?.expect("transaction_id");
+
+        let response = match self.state.read().await.get(&transaction_id) {
+            Some(sm) => {
+                let response = sm.uas_cancel(request.clone()).await?;
+                self.schedule(sm).await;
+                response
+            }
+            None => return Err(Error::from(TransactionError::NotFound)),
+        };
+
+        let timers = self.server_timers_for(&request);
+        let mut transaction_data =
+            sm::uas_non_invite::TrxStateMachine::new(self.handlers.clone(), request, timers)?;
+        transaction_data.id = cancel_id_of(&transaction_id);
+        transaction_data.next(Some(response.into())).await;
+        self.insert(transaction_data.id.clone(), transaction_data.into()).await;
+
+        Ok(())
+    }
+
+    async fn insert(&self, transaction_id: TransactionId, sm: TrxStateSm) {
+        let mut state = self.state.write().await;
+        self.schedule(&sm).await;
+        state.insert(transaction_id, sm);
+    }
+
+    async fn schedule(&self, sm: &TrxStateSm) {
+        sm.schedule(&self.scheduler, self.timers.transaction_linger).await
+    }
+
+    //timers depend on where our messages go: the request target for client transactions,
+    //until the transport tells which address that resolved to
+    fn client_timers_for(&self, request: &rsip::Request) -> SipTimers {
+        self.timers.for_host(request.uri.host())
+    }
+
+    //and the source of the request for server ones, since that's where responses go. The
+    //transport records it in received whenever it differs from sent-by
+    fn server_timers_for(&self, request: &rsip::Request) -> SipTimers {
+        match request.via_header().and_then(|via_header| via_header.typed()) {
+            Ok(via) => match via.peer_ip_addr() {
+                Some(ip_addr) => self.timers.for_target(via.sent_by().host(), ip_addr),
+                None => self.timers.for_host(via.sent_by().host()),
+            },
+            Err(_) => self.timers.default,
+        }
+    }
+
+    //network overrides can't match a domain target, only the address it resolved to
+    async fn process_resolved(
+        &self,
+        request: rsip::Request,
+        peer: SocketAddr,
+        transport: rsip::Transport,
     ) -> Result<(), Error> {
-        unimplemented!("");
+        let transaction_id = match request.transaction_id()? {
+            Some(transaction_id) => key_of(transaction_id, request.method),
+            None => return Ok(()),
+        };
+
+        if let Some(sm) = self.state.read().await.get(&transaction_id) {
+            let timers = self.timers.for_target(request.uri.host(), peer.ip());
+            sm.uac_resolved(timers, transport).await;
+            self.schedule(sm).await;
+        }
+
+        Ok(())
     }
 
     async fn process_tu_reply(&self, response: rsip::Response) -> Result<(), Error> {
         let transaction_id = response.transaction_id()?.expect("transaction_id");
 
         match self.state.read().await.get(&transaction_id) {
-            Some(sm) => Ok(sm.uas_process_tu_reply(response).await?),
+            Some(sm) => {
+                sm.uas_process_tu_reply(response).await?;
+                self.schedule(sm).await;
+                Ok(())
+            }
             None => Err(Error::from(TransactionError::NotFound)),
         }
     }
@@ -166,41 +395,100 @@ impl Inner {
     }
 
     async fn process_incoming_request(&self, request: rsip::Request) -> Result<(), Error> {
-        let transaction_id = request.transaction_id()?.expect("transaction_id");
+        let transaction_id = key_of(
+            request.transaction_id()?.expect("transaction_id"),
+            request.method,
+        );
 
-        match self.state.read().await.get(&transaction_id) {
-            Some(sm) => Ok(sm.uas_process_request(request).await?),
+        let state = self.state.read().await;
+        if request.method == rsip::Method::Cancel && !state.contains_key(&transaction_id) {
+            drop(state);
+            return self.process_incoming_cancel(request).await;
+        }
+
+        match state.get(&transaction_id) {
+            Some(sm) => {
+                sm.uas_process_request(request).await?;
+                self.schedule(sm).await;
+                Ok(())
+            }
             None => Err(Error::from(TransactionError::NotFound)),
         }
     }
 
     async fn process_incoming_response(&self, response: rsip::Response) -> Result<(), Error> {
-        let transaction_id = response.transaction_id()?.expect("transaction_id");
+        let transaction_id = key_of(
+            response.transaction_id()?.expect("transaction_id"),
+            response.cseq_header()?.typed()?.method,
+        );
+
+        let pending_cancel = match self.state.read().await.get(&transaction_id) {
+            Some(sm) => {
+                sm.uac_process_response(response).await?;
+                self.schedule(sm).await;
+                sm.uac_pending_cancel_request().await
+            }
+            None => return Err(Error::from(TransactionError::NotFound)),
+        };
 
-        match self.state.read().await.get(&transaction_id) {
-            Some(sm) => Ok(sm.uac_process_response(response).await?),
-            None => Err(Error::from(TransactionError::NotFound)),
+        match pending_cancel {
+            Some(request) => self.start_cancel(transaction_id, request).await,
+            None => Ok(()),
         }
     }
 
-    async fn run_transactions(&self) {
-        use tokio::time;
+    //only transactions with a timer due are woken up, instead of polling all of them
+    async fn run_transactions(&self, mut deadlines: Deadlines) {
+        while let Some(transaction_id) = deadlines.next().await {
+            self.check_transaction(transaction_id).await
+        }
+    }
 
-        let mut ticker = time::interval(time::Duration::from_millis(100));
-        loop {
-            ticker.tick().await;
+    async fn check_transaction(&self, transaction_id: TransactionId) {
+        let has_lingered = match self.state.read().await.get(&transaction_id) {
+            Some(sm) => {
+                let has_lingered = sm.has_lingered(self.timers.transaction_linger).await;
+                if !has_lingered {
+                    sm.tick().await;
+                    self.schedule(sm).await;
+                }
+                has_lingered
+            }
+            None => false,
+        };
 
-            self.check_transactions().await
+        if has_lingered {
+            self.reap(transaction_id).await
         }
     }
 
-    async fn check_transactions(&self) {
-        let state = self.state.read().await;
-        for transaction_data in (*state).values() {
-            match transaction_data {
-                TrxStateSm::Uac(sm) => sm.lock().await.next(None).await,
-                TrxStateSm::Uas(sm) => sm.lock().await.next(None).await,
-            };
+    async fn reap(&self, transaction_id: TransactionId) {
+        let removed = self.state.write().await.remove(&transaction_id);
+        if let Some(sm) = removed {
+            if let Some(reliable_key) = sm.uas_reliable_key().await {
+                self.reliables.write().await.remove(&reliable_key);
+            }
+            self.reaped.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
+
+//CANCEL shares the branch of the INVITE it cancels (RFC3261 9.1), but is a transaction of
+//its own, so it's kept under a key of its own
+fn cancel_id_of(transaction_id: &TransactionId) -> TransactionId {
+    let branch: String = transaction_id.clone().into();
+
+    TransactionId::new(format!("{}-cancel", branch))
+}
+
+//a PRACK is matched to the INVITE it acknowledges through the Call-ID and the RAck CSeq
+fn reliable_key_of(request: &rsip::Request, cseq: u32) -> Result<String, Error> {
+    Ok(format!("{} {}", request.call_id_header()?.value(), cseq))
+}
+
+fn key_of(transaction_id: TransactionId, cseq_method: rsip::Method) -> TransactionId {
+    match cseq_method {
+        rsip::Method::Cancel => cancel_id_of(&transaction_id),
+        _ => transaction_id,
+    }
+}
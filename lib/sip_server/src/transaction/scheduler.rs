@@ -0,0 +1,83 @@
+use common::{
+    futures::future::poll_fn,
+    tokio::{self, sync::mpsc, time::Instant},
+    tokio_util::time::{delay_queue, DelayQueue},
+};
+use models::transaction::TransactionId;
+use std::collections::HashMap;
+
+//hands out transaction deadlines to a timer wheel, so that only the transactions that
+//have a timer due are woken up, no matter how many are alive
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    tx: mpsc::UnboundedSender<(TransactionId, Option<Instant>)>,
+}
+
+#[derive(Debug)]
+pub struct Deadlines {
+    rx: mpsc::UnboundedReceiver<(TransactionId, Option<Instant>)>,
+    queue: DelayQueue<TransactionId>,
+    keys: HashMap<TransactionId, delay_queue::Key>,
+}
+
+impl Scheduler {
+    pub fn new() -> (Self, Deadlines) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        (
+            Self { tx },
+            Deadlines {
+                rx,
+                queue: DelayQueue::new(),
+                keys: HashMap::new(),
+            },
+        )
+    }
+
+    //replaces any previous deadline of the transaction, None unschedules it
+    pub fn schedule(&self, transaction_id: TransactionId, deadline: Option<Instant>) {
+        if self.tx.send((transaction_id, deadline)).is_err() {
+            common::log::warn!("transaction scheduler has gone away");
+        }
+    }
+}
+
+impl Deadlines {
+    //resolves to the next transaction that is due, None when every Scheduler is dropped
+    pub async fn next(&mut self) -> Option<TransactionId> {
+        loop {
+            tokio::select! {
+                scheduled = self.rx.recv() => match scheduled {
+                    Some((transaction_id, deadline)) => self.update(transaction_id, deadline),
+                    None => return None,
+                },
+                expired = poll_fn(|cx| self.queue.poll_expired(cx)),
+                    if !self.queue.is_empty() => match expired {
+                    Some(Ok(expired)) => {
+                        let transaction_id = expired.into_inner();
+                        self.keys.remove(&transaction_id);
+                        return Some(transaction_id);
+                    }
+                    Some(Err(err)) => common::log::error!("transaction timer failed: {}", err),
+                    None => (),
+                },
+            }
+        }
+    }
+
+    fn update(&mut self, transaction_id: TransactionId, deadline: Option<Instant>) {
+        match (self.keys.get(&transaction_id), deadline) {
+            (Some(key), Some(deadline)) => self.queue.reset_at(key, deadline),
+            (Some(_), None) => {
+                if let Some(key) = self.keys.remove(&transaction_id) {
+                    self.queue.remove(&key);
+                }
+            }
+            (None, Some(deadline)) => {
+                let key = self.queue.insert_at(transaction_id.clone(), deadline);
+                self.keys.insert(transaction_id, key);
+            }
+            (None, None) => (),
+        }
+    }
+}
@@ -1,32 +1,102 @@
 pub mod uac;
+pub mod uac_non_invite;
 pub mod uas;
+pub mod uas_non_invite;
 
+use super::Scheduler;
 use crate::{error::TransactionError, Error};
-use common::{rsip, tokio::sync::Mutex};
-use std::fmt::Debug;
+use common::{
+    rsip::{self, prelude::*},
+    tokio::{sync::Mutex, time::Instant},
+    SipTimers,
+};
+use models::{rsip_ext::RAck, transport::TransportError};
+use std::{fmt::Debug, time::Duration};
 
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum TrxStateSm {
     Uac(Mutex<uac::TrxStateMachine>),
+    UacNonInvite(Mutex<uac_non_invite::TrxStateMachine>),
     Uas(Mutex<uas::TrxStateMachine>),
+    UasNonInvite(Mutex<uas_non_invite::TrxStateMachine>),
 }
 
 impl TrxStateSm {
     pub async fn is_active(&self) -> bool {
         match self {
             Self::Uac(sm) => sm.lock().await.is_active(),
+            Self::UacNonInvite(sm) => sm.lock().await.is_active(),
             Self::Uas(sm) => sm.lock().await.is_active(),
+            Self::UasNonInvite(sm) => sm.lock().await.is_active(),
         }
     }
 
-    pub async fn transport_error(&self, reason: String) {
+    //any message can move a machine to another state, so its deadline is renewed after each,
+    //while the machine is still locked so that a stale deadline can't overtake a newer one
+    pub async fn schedule(&self, scheduler: &Scheduler, linger: Duration) {
         match self {
-            Self::Uac(sm) => sm.lock().await.transport_error(reason).await,
-            Self::Uas(sm) => sm.lock().await.transport_error(reason).await,
+            Self::Uac(sm) => {
+                let sm = sm.lock().await;
+                scheduler.schedule(sm.id.clone(), deadline(sm.deadline(), sm.finished_at(), linger))
+            }
+            Self::UacNonInvite(sm) => {
+                let sm = sm.lock().await;
+                scheduler.schedule(sm.id.clone(), deadline(sm.deadline(), sm.finished_at(), linger))
+            }
+            Self::Uas(sm) => {
+                let sm = sm.lock().await;
+                scheduler.schedule(sm.id.clone(), deadline(sm.deadline(), sm.finished_at(), linger))
+            }
+            Self::UasNonInvite(sm) => {
+                let sm = sm.lock().await;
+                scheduler.schedule(sm.id.clone(), deadline(sm.deadline(), sm.finished_at(), linger))
+            }
+        }
+    }
+
+    //finished machines are kept for a while, so that HasTransaction lookups and late
+    //retransmissions keep matching them instead of reaching the TU as new requests
+    pub async fn has_lingered(&self, linger: Duration) -> bool {
+        let finished_at = match self {
+            Self::Uac(sm) => sm.lock().await.finished_at(),
+            Self::UacNonInvite(sm) => sm.lock().await.finished_at(),
+            Self::Uas(sm) => sm.lock().await.finished_at(),
+            Self::UasNonInvite(sm) => sm.lock().await.finished_at(),
+        };
+
+        matches!(finished_at, Some(finished_at) if Instant::now() >= finished_at + linger)
+    }
+
+    //runs whatever timer is due, same as next(None) on the underlying machine
+    pub async fn tick(&self) {
+        match self {
+            Self::Uac(sm) => sm.lock().await.next(None).await,
+            Self::UacNonInvite(sm) => sm.lock().await.next(None).await,
+            Self::Uas(sm) => sm.lock().await.next(None).await,
+            Self::UasNonInvite(sm) => sm.lock().await.next(None).await,
+        }
+    }
+
+    pub async fn transport_error(&self, error: TransportError) {
+        match self {
+            Self::Uac(sm) => sm.lock().await.transport_error(error).await,
+            Self::UacNonInvite(sm) => sm.lock().await.transport_error(error).await,
+            Self::Uas(sm) => sm.lock().await.transport_error(error).await,
+            Self::UasNonInvite(sm) => sm.lock().await.transport_error(error).await,
         };
     }
 
+    //client transactions learn the address of their target, and the transport their request
+    //went over, only after they started
+    pub async fn uac_resolved(&self, timers: SipTimers, transport: rsip::Transport) {
+        match self {
+            Self::Uac(sm) => sm.lock().await.timers = timers,
+            Self::UacNonInvite(sm) => sm.lock().await.resolved(timers, transport),
+            Self::Uas(_) | Self::UasNonInvite(_) => (),
+        }
+    }
+
     pub async fn uac_process_response(&self, msg: rsip::Response) -> Result<(), Error> {
         match self {
             Self::Uac(sm) => {
@@ -34,7 +104,14 @@ impl TrxStateSm {
                 sm.next(Some(msg)).await;
                 Ok(())
             }
-            Self::Uas(_) => Err(Error::from(TransactionError::UnexpectedState)),
+            Self::UacNonInvite(sm) => {
+                let mut sm = sm.lock().await;
+                sm.next(Some(msg)).await;
+                Ok(())
+            }
+            Self::Uas(_) | Self::UasNonInvite(_) => {
+                Err(Error::from(TransactionError::UnexpectedState))
+            }
         }
     }
 
@@ -45,7 +122,14 @@ impl TrxStateSm {
                 sm.next(Some(msg.into())).await;
                 Ok(())
             }
-            Self::Uac(_) => Err(Error::from(TransactionError::UnexpectedState)),
+            Self::UasNonInvite(sm) => {
+                let mut sm = sm.lock().await;
+                sm.next(Some(msg.into())).await;
+                Ok(())
+            }
+            Self::Uac(_) | Self::UacNonInvite(_) => {
+                Err(Error::from(TransactionError::UnexpectedState))
+            }
         }
     }
 
@@ -56,7 +140,53 @@ impl TrxStateSm {
                 sm.next(Some(msg.into())).await;
                 Ok(())
             }
-            Self::Uac(_) => Err(Error::from(TransactionError::UnexpectedState)),
+            Self::UasNonInvite(sm) => {
+                let mut sm = sm.lock().await;
+                sm.next(Some(msg.into())).await;
+                Ok(())
+            }
+            Self::Uac(_) | Self::UacNonInvite(_) => {
+                Err(Error::from(TransactionError::UnexpectedState))
+            }
+        }
+    }
+
+    //a CANCEL of a non-INVITE request is answered, but has no effect (RFC3261 9.2)
+    pub async fn uas_cancel(&self, cancel: rsip::Request) -> Result<rsip::Response, Error> {
+        match self {
+            Self::Uas(sm) => sm.lock().await.cancel(cancel).await,
+            Self::UasNonInvite(_) => crate::presets::response_from(cancel, 200.into()),
+            Self::Uac(_) | Self::UacNonInvite(_) => {
+                Err(Error::from(TransactionError::UnexpectedState))
+            }
+        }
+    }
+
+    pub async fn uas_reliable_key(&self) -> Option<String> {
+        match self {
+            Self::Uas(sm) => sm.lock().await.reliable_key.clone(),
+            _ => None,
+        }
+    }
+
+    pub async fn uas_prack(&self, rack: &RAck) -> Result<bool, Error> {
+        match self {
+            Self::Uas(sm) => sm.lock().await.prack(rack).await,
+            _ => Err(Error::from(TransactionError::UnexpectedState)),
+        }
+    }
+
+    pub async fn uac_cancel_request(&self) -> Result<Option<rsip::Request>, Error> {
+        match self {
+            Self::Uac(sm) => sm.lock().await.cancel_request(),
+            _ => Err(Error::from(TransactionError::UnexpectedState)),
+        }
+    }
+
+    pub async fn uac_pending_cancel_request(&self) -> Option<rsip::Request> {
+        match self {
+            Self::Uac(sm) => sm.lock().await.pending_cancel_request(),
+            _ => None,
         }
     }
 }
@@ -67,8 +197,41 @@ impl From<uac::TrxStateMachine> for TrxStateSm {
     }
 }
 
+impl From<uac_non_invite::TrxStateMachine> for TrxStateSm {
+    fn from(from: uac_non_invite::TrxStateMachine) -> Self {
+        Self::UacNonInvite(Mutex::new(from))
+    }
+}
+
 impl From<uas::TrxStateMachine> for TrxStateSm {
     fn from(from: uas::TrxStateMachine) -> Self {
         Self::Uas(Mutex::new(from))
     }
 }
+
+impl From<uas_non_invite::TrxStateMachine> for TrxStateSm {
+    fn from(from: uas_non_invite::TrxStateMachine) -> Self {
+        Self::UasNonInvite(Mutex::new(from))
+    }
+}
+
+//once the machine has no timers left, the next thing to do is evicting it
+fn deadline(
+    deadline: Option<Instant>,
+    finished_at: Option<Instant>,
+    linger: Duration,
+) -> Option<Instant> {
+    deadline.or_else(|| finished_at.map(|finished_at| finished_at + linger))
+}
+
+//only UDP needs retransmissions, anything else is a reliable transport (RFC3261 17.1.1.2).
+//Until the transport tells otherwise, the request goes over what its Via says
+fn is_reliable(request: &rsip::Request) -> bool {
+    let transport = request
+        .via_header()
+        .ok()
+        .and_then(|via_header| via_header.typed().ok())
+        .map(|via| via.transport);
+
+    !matches!(transport, Some(rsip::Transport::Udp) | None)
+}
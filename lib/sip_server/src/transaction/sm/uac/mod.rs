@@ -2,21 +2,17 @@ mod states;
 
 pub use states::{Accepted, Calling, Completed, Errored, Proceeding, Terminated};
 
-use crate::Error;
+use crate::{error::TransactionError, Error};
 use common::{
-    rsip::{self, message::HeadersExt},
+    rsip::{self, prelude::*},
     tokio::time::Instant,
+    SipTimers,
 };
-use models::{rsip_ext::*, transaction::TransactionId, Handlers};
+use models::{rsip_ext::*, transaction::TransactionId, transport::TransportError, Handlers};
+use std::collections::HashMap;
 
 //TODO: add state checks as well for better guarantees, look at dialogs
 
-//should come from config
-pub static TIMER_T1: u64 = 500;
-pub static TIMER_B: u64 = 64 * TIMER_T1;
-pub static TIMER_M: u64 = 64 * TIMER_T1;
-pub static TIMER_D: u64 = 32000;
-
 //implements RFC6026 as well
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -25,6 +21,12 @@ pub struct TrxStateMachine {
     pub state: TrxState,
     pub request: rsip::Request,
     pub created_at: Instant,
+    pub timers: SipTimers,
+    //RSeq of the last reliable provisional passed to the TU, per To-tag since a forked
+    //INVITE gets provisionals from each of its early dialogs (RFC3262 4)
+    pub last_rseqs: HashMap<String, u32>,
+    //the TU cancelled the INVITE before any provisional response arrived
+    pub cancel_pending: bool,
     handlers: Handlers,
 }
 
@@ -52,12 +54,19 @@ impl std::fmt::Display for TrxState {
 }
 
 impl TrxStateMachine {
-    pub fn new(handlers: Handlers, request: rsip::Request) -> Result<Self, Error> {
+    pub fn new(
+        handlers: Handlers,
+        request: rsip::Request,
+        timers: SipTimers,
+    ) -> Result<Self, Error> {
         Ok(Self {
             id: request.transaction_id()?.expect("transaction_id"),
             state: TrxState::Calling(Default::default()),
             request,
             created_at: Instant::now(),
+            timers,
+            last_rseqs: Default::default(),
+            cancel_pending: false,
             handlers,
         })
     }
@@ -78,16 +87,81 @@ impl TrxStateMachine {
         !matches!(self.state, TrxState::Errored(_) | TrxState::Terminated(_))
     }
 
-    //TODO: use proper error type here
-    pub async fn transport_error(&mut self, reason: String) {
-        self.error(reason, None);
+    //the next time a timer fires and next(None) has something to do
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Calling(calling) => Some(std::cmp::min(
+                calling.retransmission_at(&self.timers),
+                calling.timeout_at(&self.timers),
+            )),
+            TrxState::Completed(completed) => Some(completed.terminates_at(&self.timers)),
+            TrxState::Accepted(accepted) => Some(accepted.terminates_at(&self.timers)),
+            _ => None,
+        }
+    }
+
+    //when the transaction reached a final state, if it has
+    pub fn finished_at(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Terminated(terminated) => Some(terminated.entered_at),
+            TrxState::Errored(errored) => Some(errored.entered_at),
+            _ => None,
+        }
+    }
+
+    //a CANCEL can't be sent before a provisional response has arrived (RFC3261 9.1),
+    //so until then it waits for one
+    pub fn cancel_request(&mut self) -> Result<Option<rsip::Request>, Error> {
+        match self.state {
+            TrxState::Calling(_) => {
+                self.cancel_pending = true;
+                Ok(None)
+            }
+            TrxState::Proceeding(_) => Ok(Some(self.request.cancel_request())),
+            _ => Err(Error::from(TransactionError::UnexpectedState)),
+        }
+    }
+
+    //the waiting CANCEL, once a provisional response let it go out, and only once
+    pub fn pending_cancel_request(&mut self) -> Option<rsip::Request> {
+        match self.state {
+            TrxState::Proceeding(_) if self.cancel_pending => {
+                self.cancel_pending = false;
+                Some(self.request.cancel_request())
+            }
+            _ => None,
+        }
+    }
+
+    //the TU must know that its request never made it (RFC3261 17.1.4)
+    pub async fn transport_error(&mut self, error: TransportError) {
+        self.error(error.to_string(), None);
+
+        if let Err(err) = self
+            .handlers
+            .tu
+            .transport_error(self.request.clone().into(), error)
+            .await
+        {
+            common::log::error!("could not report transport error to TU: {}", err)
+        }
     }
 
     async fn next_step(&mut self) -> Result<(), Error> {
         match &self.state {
             TrxState::Calling(calling) => {
-                match (calling.has_timedout(), calling.should_retransmit()) {
-                    (true, _) => self.terminate(),
+                match (
+                    calling.has_timedout(&self.timers),
+                    calling.should_retransmit(&self.timers),
+                ) {
+                    //the transport retries the next DNS target in a new transaction, if any
+                    (true, _) => {
+                        self.terminate();
+                        self.handlers
+                            .transport
+                            .failover(self.request.clone())
+                            .await?;
+                    }
                     (false, true) => {
                         self.handlers
                             .transport
@@ -99,12 +173,12 @@ impl TrxStateMachine {
                 }
             }
             TrxState::Completed(completed) => {
-                if completed.should_terminate() {
+                if completed.should_terminate(&self.timers) {
                     self.terminate();
                 }
             }
             TrxState::Accepted(accepted) => {
-                if accepted.should_terminate() {
+                if accepted.should_terminate(&self.timers) {
                     self.terminate();
                 }
             }
@@ -117,6 +191,23 @@ impl TrxStateMachine {
     async fn next_step_with(&mut self, response: rsip::Response) -> Result<(), Error> {
         use rsip::common::StatusCodeKind;
 
+        //retransmitted reliable provisionals, and out of order ones, never reach the TU
+        if let (StatusCodeKind::Provisional, Some(rseq)) =
+            (response.status_code.kind(), response.headers.rseq())
+        {
+            let to_tag = response
+                .to_header()?
+                .tag()?
+                .map(|tag| tag.to_string())
+                .unwrap_or_default();
+            match self.last_rseqs.get(&to_tag) {
+                Some(last_rseq) if last_rseq.checked_add(1) != Some(rseq) => return Ok(()),
+                _ => {
+                    self.last_rseqs.insert(to_tag, rseq);
+                }
+            }
+        }
+
         match (&self.state, response.status_code.kind()) {
             (TrxState::Calling(_), StatusCodeKind::Provisional) => {
                 self.handlers.tu.process(response.clone().into()).await?;
@@ -152,6 +243,8 @@ impl TrxStateMachine {
                 self.send_ack_request_from(response.clone()).await?;
                 self.complete(response);
             }
+            //late retransmissions while the transaction lingers are absorbed
+            (TrxState::Terminated(_), _) => (),
             (_, _) => {
                 self.error(
                     format!(
@@ -1,7 +1,4 @@
-use common::{rsip, tokio::time::Instant};
-use std::time::Duration;
-
-use super::super::TIMER_M;
+use common::{rsip, tokio::time::Instant, SipTimers};
 
 #[derive(Debug, Clone)]
 pub struct Accepted {
@@ -10,7 +7,12 @@ pub struct Accepted {
 }
 
 impl Accepted {
-    pub fn should_terminate(&self) -> bool {
-        self.entered_at.elapsed() > Duration::from_millis(TIMER_M)
+    //timer M, RFC6026
+    pub fn terminates_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.m()
+    }
+
+    pub fn should_terminate(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.terminates_at(timers)
     }
 }
@@ -1,8 +1,6 @@
-use common::tokio::time::Instant;
+use common::{tokio::time::Instant, SipTimers};
 use std::time::Duration;
 
-use super::super::{TIMER_B, TIMER_T1};
-
 #[derive(Debug, Clone, Copy)]
 pub struct Calling {
     pub entered_at: Instant,
@@ -11,20 +9,29 @@ pub struct Calling {
 }
 
 impl Calling {
-    pub fn next_retrasmission(&self) -> Duration {
+    pub fn next_retrasmission(&self, timers: &SipTimers) -> Duration {
         use std::iter;
 
-        iter::repeat(Duration::from_millis(TIMER_T1))
+        iter::repeat(timers.t1)
             .take(2_i32.pow(self.retransmissions_count.into()) as usize)
             .fold(Duration::from_secs(0), |acc, x| acc + x)
     }
 
-    pub fn has_timedout(&self) -> bool {
-        self.entered_at.elapsed() >= Duration::from_millis(TIMER_B)
+    //timer B
+    pub fn timeout_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.b()
+    }
+
+    pub fn retransmission_at(&self, timers: &SipTimers) -> Instant {
+        self.last_retransmission_at + self.next_retrasmission(timers)
+    }
+
+    pub fn has_timedout(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.timeout_at(timers)
     }
 
-    pub fn should_retransmit(&self) -> bool {
-        self.last_retransmission_at.elapsed() > self.next_retrasmission()
+    pub fn should_retransmit(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.retransmission_at(timers)
     }
 
     pub fn retransmit(self) -> Self {
@@ -0,0 +1,285 @@
+mod states;
+
+pub use states::{Completed, Errored, Proceeding, Terminated, Trying};
+
+use crate::Error;
+use common::{
+    rsip::{self, message::HeadersExt},
+    tokio::time::Instant,
+    SipTimers,
+};
+use models::{transaction::TransactionId, transport::TransportError, Handlers};
+use std::time::Duration;
+
+//non-INVITE client transaction, RFC3261 17.1.2
+#[derive(Debug)]
+pub struct TrxStateMachine {
+    pub id: TransactionId,
+    pub state: TrxState,
+    pub request: rsip::Request,
+    pub created_at: Instant,
+    pub timers: SipTimers,
+    reliable: bool,
+    handlers: Handlers,
+}
+
+#[derive(Debug)]
+pub enum TrxState {
+    Trying(Trying),
+    Proceeding(Proceeding),
+    Completed(Completed),
+    Terminated(Terminated),
+    Errored(Errored),
+}
+
+impl std::fmt::Display for TrxState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Trying(_) => write!(f, "TrxState::Trying"),
+            Self::Proceeding(_) => write!(f, "TrxState::Proceeding"),
+            Self::Completed(_) => write!(f, "TrxState::Completed"),
+            Self::Terminated(_) => write!(f, "TrxState::Terminated"),
+            Self::Errored(_) => write!(f, "TrxState::Errored"),
+        }
+    }
+}
+
+impl TrxStateMachine {
+    pub fn new(
+        handlers: Handlers,
+        request: rsip::Request,
+        timers: SipTimers,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            id: request.transaction_id()?.expect("transaction_id"),
+            state: TrxState::Trying(Default::default()),
+            reliable: super::is_reliable(&request),
+            request,
+            created_at: Instant::now(),
+            timers,
+            handlers,
+        })
+    }
+
+    //DNS, or the size of the request, may have picked another transport than the Via of the TU
+    pub fn resolved(&mut self, timers: SipTimers, transport: rsip::Transport) {
+        self.timers = timers;
+        self.reliable = transport != rsip::Transport::Udp;
+    }
+
+    pub async fn next(&mut self, response: Option<rsip::Response>) {
+        let result = match response {
+            Some(response) => self.next_step_with(response).await,
+            None => self.next_step().await,
+        };
+
+        match result {
+            Ok(()) => (),
+            Err(error) => self.error(format!("transaction {} errored: {}", self.id, error), None),
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        !matches!(self.state, TrxState::Errored(_) | TrxState::Terminated(_))
+    }
+
+    //the next time a timer fires and next(None) has something to do
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Trying(trying) if self.reliable => Some(trying.timeout_at(&self.timers)),
+            TrxState::Trying(trying) => Some(std::cmp::min(
+                trying.retransmission_at(&self.timers),
+                trying.timeout_at(&self.timers),
+            )),
+            TrxState::Proceeding(proceeding) if self.reliable => {
+                Some(proceeding.timeout_at(&self.timers))
+            }
+            TrxState::Proceeding(proceeding) => Some(std::cmp::min(
+                proceeding.retransmission_at(&self.timers),
+                proceeding.timeout_at(&self.timers),
+            )),
+            TrxState::Completed(completed) => Some(completed.terminates_at()),
+            _ => None,
+        }
+    }
+
+    //when the transaction reached a final state, if it has
+    pub fn finished_at(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Terminated(terminated) => Some(terminated.entered_at),
+            TrxState::Errored(errored) => Some(errored.entered_at),
+            _ => None,
+        }
+    }
+
+    //the TU must know that its request never made it (RFC3261 17.1.4)
+    pub async fn transport_error(&mut self, error: TransportError) {
+        self.error(error.to_string(), None);
+
+        if let Err(err) = self
+            .handlers
+            .tu
+            .transport_error(self.request.clone().into(), error)
+            .await
+        {
+            common::log::error!("could not report transport error to TU: {}", err)
+        }
+    }
+
+    async fn next_step(&mut self) -> Result<(), Error> {
+        match &self.state {
+            TrxState::Trying(trying) => {
+                match (
+                    trying.has_timedout(&self.timers),
+                    trying.should_retransmit(&self.timers),
+                ) {
+                    //the transport retries the next DNS target in a new transaction, if any
+                    (true, _) => {
+                        self.terminate();
+                        self.handlers
+                            .transport
+                            .failover(self.request.clone())
+                            .await?;
+                    }
+                    (false, true) if !self.reliable => {
+                        self.retransmit().await?;
+                        self.state = TrxState::Trying(trying.retransmit());
+                    }
+                    (false, _) => (),
+                }
+            }
+            TrxState::Proceeding(proceeding) => {
+                match (
+                    proceeding.has_timedout(&self.timers),
+                    proceeding.should_retransmit(&self.timers),
+                ) {
+                    (true, _) => {
+                        self.terminate();
+                        self.handlers
+                            .transport
+                            .failover(self.request.clone())
+                            .await?;
+                    }
+                    (false, true) if !self.reliable => {
+                        let proceeding = proceeding.clone().retransmit();
+                        self.retransmit().await?;
+                        self.state = TrxState::Proceeding(proceeding);
+                    }
+                    (false, _) => (),
+                }
+            }
+            TrxState::Completed(completed) => {
+                if completed.should_terminate() {
+                    self.terminate();
+                }
+            }
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    async fn next_step_with(&mut self, response: rsip::Response) -> Result<(), Error> {
+        use rsip::common::StatusCodeKind;
+
+        match (&self.state, response.status_code.kind()) {
+            (TrxState::Trying(_), StatusCodeKind::Provisional) => {
+                self.handlers.tu.process(response.clone().into()).await?;
+                self.proceed(response);
+            }
+            (TrxState::Proceeding(_), StatusCodeKind::Provisional) => {
+                self.handlers.tu.process(response.clone().into()).await?;
+                self.update_response(response);
+            }
+            (TrxState::Trying(_), _) | (TrxState::Proceeding(_), _) => {
+                self.handlers.tu.process(response.clone().into()).await?;
+                self.complete(response);
+            }
+            //retransmissions of the final response are absorbed
+            (TrxState::Completed(_), _) => (),
+            //late retransmissions while the transaction lingers are absorbed
+            (TrxState::Terminated(_), _) => (),
+            (_, _) => {
+                self.error(
+                    format!(
+                        "unknown match: {}, {} for transaction {}",
+                        response.status_code, self.state, self.id
+                    ),
+                    Some(response),
+                );
+            }
+        };
+
+        Ok(())
+    }
+
+    async fn retransmit(&self) -> Result<(), Error> {
+        Ok(self
+            .handlers
+            .transport
+            .send(self.request.clone().into())
+            .await?)
+    }
+
+    fn proceed(&mut self, response: rsip::Response) {
+        let trying_since = match &self.state {
+            TrxState::Trying(trying) => trying.entered_at,
+            _ => self.created_at,
+        };
+
+        self.state = TrxState::Proceeding(Proceeding {
+            response,
+            entered_at: Instant::now(),
+            trying_since,
+            last_retransmission_at: Instant::now(),
+        });
+    }
+
+    fn update_response(&mut self, response: rsip::Response) {
+        match &self.state {
+            TrxState::Proceeding(state) => {
+                self.state = TrxState::Proceeding(Proceeding {
+                    response,
+                    ..state.clone()
+                })
+            }
+            _ => self.error(
+                format!("Asking to update response when state is {}", self.state),
+                Some(response),
+            ),
+        };
+    }
+
+    fn complete(&mut self, response: rsip::Response) {
+        let linger = match self.reliable {
+            true => Duration::from_millis(0),
+            false => self.timers.k(),
+        };
+
+        self.state = TrxState::Completed(Completed {
+            response,
+            entered_at: Instant::now(),
+            linger,
+        });
+    }
+
+    fn terminate(&mut self) {
+        let response: Option<rsip::Response> = match &self.state {
+            TrxState::Completed(completed) => Some(completed.clone().response),
+            _ => None,
+        };
+
+        self.state = TrxState::Terminated(Terminated {
+            response,
+            entered_at: Instant::now(),
+        });
+    }
+
+    fn error(&mut self, error: String, response: Option<rsip::Response>) {
+        self.state = TrxState::Errored(Errored {
+            entered_at: Instant::now(),
+            response,
+            error,
+        });
+    }
+}
@@ -0,0 +1,20 @@
+use common::{rsip, tokio::time::Instant};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Completed {
+    pub response: rsip::Response,
+    pub entered_at: Instant,
+    //timer K, zero for reliable transports since there are no retransmissions to absorb
+    pub linger: Duration,
+}
+
+impl Completed {
+    pub fn terminates_at(&self) -> Instant {
+        self.entered_at + self.linger
+    }
+
+    pub fn should_terminate(&self) -> bool {
+        Instant::now() >= self.terminates_at()
+    }
+}
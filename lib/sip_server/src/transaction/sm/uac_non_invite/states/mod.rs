@@ -0,0 +1,11 @@
+mod completed;
+mod errored;
+mod proceeding;
+mod terminated;
+mod trying;
+
+pub use completed::Completed;
+pub use errored::Errored;
+pub use proceeding::Proceeding;
+pub use terminated::Terminated;
+pub use trying::Trying;
@@ -0,0 +1,36 @@
+use common::{rsip, tokio::time::Instant, SipTimers};
+
+#[derive(Debug, Clone)]
+pub struct Proceeding {
+    pub response: rsip::Response,
+    pub entered_at: Instant,
+    //timer F keeps running from the moment the request was first sent
+    pub trying_since: Instant,
+    pub last_retransmission_at: Instant,
+}
+
+impl Proceeding {
+    pub fn timeout_at(&self, timers: &SipTimers) -> Instant {
+        self.trying_since + timers.f()
+    }
+
+    //once a provisional response arrives, timer E fires every T2
+    pub fn retransmission_at(&self, timers: &SipTimers) -> Instant {
+        self.last_retransmission_at + timers.t2
+    }
+
+    pub fn has_timedout(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.timeout_at(timers)
+    }
+
+    pub fn should_retransmit(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.retransmission_at(timers)
+    }
+
+    pub fn retransmit(self) -> Self {
+        Self {
+            last_retransmission_at: Instant::now(),
+            ..self
+        }
+    }
+}
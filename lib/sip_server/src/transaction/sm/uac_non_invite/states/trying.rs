@@ -0,0 +1,56 @@
+use common::{tokio::time::Instant, SipTimers};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Trying {
+    pub entered_at: Instant,
+    pub retransmissions_count: u8,
+    pub last_retransmission_at: Instant,
+}
+
+impl Trying {
+    //timer E, doubles on each retransmission but never goes over T2
+    pub fn next_retrasmission(&self, timers: &SipTimers) -> Duration {
+        let backoff = 2_u32.saturating_pow(self.retransmissions_count.into());
+
+        timers
+            .t1
+            .checked_mul(backoff)
+            .map_or(timers.t2, |timer_e| timer_e.min(timers.t2))
+    }
+
+    //timer F
+    pub fn timeout_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.f()
+    }
+
+    pub fn retransmission_at(&self, timers: &SipTimers) -> Instant {
+        self.last_retransmission_at + self.next_retrasmission(timers)
+    }
+
+    pub fn has_timedout(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.timeout_at(timers)
+    }
+
+    pub fn should_retransmit(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.retransmission_at(timers)
+    }
+
+    pub fn retransmit(self) -> Self {
+        Self {
+            retransmissions_count: self.retransmissions_count.saturating_add(1),
+            last_retransmission_at: Instant::now(),
+            ..self
+        }
+    }
+}
+
+impl Default for Trying {
+    fn default() -> Self {
+        Self {
+            entered_at: Instant::now(),
+            retransmissions_count: 0,
+            last_retransmission_at: Instant::now(),
+        }
+    }
+}
@@ -1,28 +1,25 @@
 mod states;
 
-pub use states::{Accepted, Completed, Confirmed, Errored, Proceeding, Terminated};
+pub use states::{Accepted, Completed, Confirmed, Errored, Proceeding, Terminated, Unacked};
 
 use crate::Error;
 use common::{
     rsip::{self, prelude::*},
     tokio::time::Instant,
+    SipTimers,
+};
+use models::{
+    rsip_ext::{rseq_header, RAck, ReliableExt, MAX_RSEQ, OPTION_TAG_100REL},
+    transaction::TransactionId,
+    transport::TransportError,
+    Handlers,
 };
-use models::{transaction::TransactionId, Handlers};
 
 //TODO: add state checks as well for better guarantees, look at dialogs
 
 static TIMED_OUT: bool = true;
 static DID_NOT_TIME_OUT: bool = false;
 
-//should come from config
-pub static TIMER_T1: u64 = 500;
-pub static TIMER_T2: u64 = 4000;
-pub static TIMER_G: u64 = TIMER_T1;
-pub static TIMER_H: u64 = 64 * TIMER_T1;
-pub static TIMER_T4: u64 = 5000;
-pub static TIMER_I: u64 = TIMER_T4;
-pub static TIMER_L: u64 = 64 * TIMER_T1;
-
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct TrxStateMachine {
@@ -32,6 +29,12 @@ pub struct TrxStateMachine {
     //uas (final) response, uas in this case is us
     pub response: rsip::Response,
     pub created_at: Instant,
+    pub timers: SipTimers,
+    //the INVITE supports 100rel, so our provisionals are sent reliably (RFC3262 3)
+    pub reliable: bool,
+    //what a PRACK for our reliable provisionals is matched with, if we send any
+    pub reliable_key: Option<String>,
+    rseq: u32,
     handlers: Handlers,
 }
 
@@ -63,15 +66,31 @@ impl TrxStateMachine {
         handlers: Handlers,
         request: rsip::Request,
         response: Option<rsip::Response>,
+        timers: SipTimers,
     ) -> Result<Self, Error> {
+        use common::rand::Rng;
         use models::rsip_ext::*;
 
+        let reliable = request.headers.supports_100rel();
+        let reliable_key = match reliable {
+            true => Some(super::super::reliable_key_of(
+                &request,
+                request.cseq_header()?.typed()?.seq,
+            )?),
+            false => None,
+        };
+
         Ok(Self {
             id: request.transaction_id()?.expect("transaction_id"),
+            reliable,
+            reliable_key,
             state: TrxState::Proceeding(Default::default()),
             response: response.unwrap_or_else(|| request.provisional_of(100)),
             request,
             created_at: Instant::now(),
+            timers,
+            //leaves room for the ones that follow, which must stay within MAX_RSEQ too
+            rseq: common::rand::thread_rng().gen_range(1, MAX_RSEQ / 2),
             handlers,
         })
     }
@@ -80,6 +99,35 @@ impl TrxStateMachine {
         !matches!(self.state, TrxState::Errored(_) | TrxState::Terminated(_))
     }
 
+    //the next time a timer fires and next(None) has something to do
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Proceeding(Proceeding {
+                unacked: Some(unacked),
+                ..
+            }) => Some(std::cmp::min(
+                unacked.retransmission_at(&self.timers),
+                unacked.timeout_at(&self.timers),
+            )),
+            TrxState::Completed(completed) => Some(std::cmp::min(
+                completed.retransmission_at(&self.timers),
+                completed.timeout_at(&self.timers),
+            )),
+            TrxState::Accepted(accepted) => Some(accepted.terminates_at(&self.timers)),
+            TrxState::Confirmed(confirmed) => Some(confirmed.terminates_at(&self.timers)),
+            _ => None,
+        }
+    }
+
+    //when the transaction reached a final state, if it has
+    pub fn finished_at(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Terminated(terminated) => Some(terminated.entered_at),
+            TrxState::Errored(errored) => Some(errored.entered_at),
+            _ => None,
+        }
+    }
+
     pub async fn next(&mut self, sip_message: Option<rsip::SipMessage>) {
         use rsip::SipMessage;
         let result = match sip_message {
@@ -98,15 +146,63 @@ impl TrxStateMachine {
         }
     }
 
-    //TODO: use proper error type here
-    pub async fn transport_error(&mut self, reason: String) {
-        self.error(reason, None);
+    pub async fn transport_error(&mut self, error: TransportError) {
+        self.error(error.to_string(), None);
+    }
+
+    //RFC3261 9.2, a CANCEL terminates the INVITE with a 487, unless a final response has
+    //already been sent, and the TU gets the CANCEL so that it stops working on the INVITE.
+    //Either way the CANCEL itself gets a 200, with the To tag we use
+    pub async fn cancel(&mut self, cancel: rsip::Request) -> Result<rsip::Response, Error> {
+        let mut cancel_response = crate::presets::response_from(cancel.clone(), 200.into())?;
+        cancel_response
+            .headers
+            .unique_push(self.response.to_header()?.clone().into());
+
+        if let TrxState::Proceeding(_) = self.state {
+            let mut response = self.response.clone();
+            response.status_code = 487.into();
+            self.next(Some(response.into())).await;
+            self.handlers.tu.process(cancel.into()).await?;
+        }
+
+        Ok(cancel_response)
+    }
+
+    //RFC3262 3, a PRACK for the pending reliable provisional stops its retransmissions,
+    //and lets the next queued one go out
+    pub async fn prack(&mut self, rack: &RAck) -> Result<bool, Error> {
+        let proceeding = match &mut self.state {
+            TrxState::Proceeding(proceeding) => proceeding,
+            _ => return Ok(false),
+        };
+        match &proceeding.unacked {
+            Some(unacked) if unacked.rseq == rack.rseq => (),
+            _ => return Ok(false),
+        };
+
+        proceeding.unacked = proceeding.queued.pop_front().map(|response| {
+            let rseq = response.headers.rseq().expect("rseq header");
+            Unacked::new(response, rseq)
+        });
+        if let Some(unacked) = &proceeding.unacked {
+            self.handlers
+                .transport
+                .send(unacked.response.clone().into())
+                .await?;
+        }
+
+        Ok(true)
     }
 
     async fn next_step(&mut self) -> Result<(), Error> {
         match &self.state {
+            TrxState::Proceeding(_) => self.next_reliable_step().await?,
             TrxState::Completed(completed) => {
-                match (completed.has_timedout(), completed.should_retransmit()) {
+                match (
+                    completed.has_timedout(&self.timers),
+                    completed.should_retransmit(&self.timers),
+                ) {
                     (true, _) => self.terminate(TIMED_OUT),
                     (false, true) => {
                         self.handlers
@@ -119,12 +215,12 @@ impl TrxStateMachine {
                 }
             }
             TrxState::Accepted(accepted) => {
-                if accepted.should_terminate() {
+                if accepted.should_terminate(&self.timers) {
                     self.terminate(TIMED_OUT);
                 }
             }
             TrxState::Confirmed(confirmed) => {
-                if confirmed.should_terminate() {
+                if confirmed.should_terminate(&self.timers) {
                     self.terminate(DID_NOT_TIME_OUT);
                 }
             }
@@ -134,6 +230,44 @@ impl TrxStateMachine {
         Ok(())
     }
 
+    async fn next_reliable_step(&mut self) -> Result<(), Error> {
+        let proceeding = match &mut self.state {
+            TrxState::Proceeding(proceeding) => proceeding,
+            _ => return Ok(()),
+        };
+
+        match proceeding.unacked.clone() {
+            //RFC3262 3, a reliable provisional that never gets its PRACK fails the INVITE,
+            //with a 500 of the same early dialog, and the TU must stop working on it
+            Some(unacked) if unacked.has_timedout(&self.timers) => {
+                let mut response = crate::presets::response_from(self.request.clone(), 500.into())?;
+                response
+                    .headers
+                    .unique_push(unacked.response.to_header()?.clone().into());
+                self.response = response.clone();
+                self.handlers.transport.send(response.clone().into()).await?;
+                self.complete();
+                self.handlers
+                    .tu
+                    .transport_error(
+                        response.into(),
+                        TransportError::Timeout("reliable provisional was never pracked".into()),
+                    )
+                    .await?;
+            }
+            Some(unacked) if unacked.should_retransmit(&self.timers) => {
+                self.handlers
+                    .transport
+                    .send(unacked.response.clone().into())
+                    .await?;
+                proceeding.unacked = Some(unacked.retransmit());
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     //TODO: how do we make sure that the request is the same as the original ?
     //we need to implement that on transaction id, making sure whatever enters here is correct
     //we should take into account the TU response as well
@@ -172,6 +306,8 @@ impl TrxStateMachine {
             (TrxState::Confirmed(_), Method::Ack) => {
                 //absorb ack
             }
+            //late retransmissions while the transaction lingers are absorbed
+            (TrxState::Terminated(_), _) => (),
             _ => self.error(
                 format!(
                     "unknown transition for {} and {}",
@@ -191,6 +327,12 @@ impl TrxStateMachine {
         use rsip::common::StatusCodeKind;
 
         match (&self.state, response.status_code.kind()) {
+            (TrxState::Proceeding(_), StatusCodeKind::Provisional)
+                if self.reliable && response.status_code != 100.into() =>
+            {
+                self.response = response.clone();
+                self.send_reliably(response).await?;
+            }
             (TrxState::Proceeding(_), StatusCodeKind::Provisional) => {
                 self.response = response.clone();
                 self.handlers.transport.send(response.into()).await?;
@@ -223,6 +365,28 @@ impl TrxStateMachine {
         Ok(())
     }
 
+    //only one reliable provisional is in flight at a time, the rest wait for its PRACK
+    async fn send_reliably(&mut self, mut response: rsip::Response) -> Result<(), Error> {
+        response
+            .headers
+            .push(rsip::headers::Require::new(OPTION_TAG_100REL).into());
+        response.headers.push(rseq_header(self.rseq));
+        let rseq = self.rseq;
+        self.rseq += 1;
+
+        if let TrxState::Proceeding(proceeding) = &mut self.state {
+            match proceeding.unacked {
+                Some(_) => proceeding.queued.push_back(response),
+                None => {
+                    self.handlers.transport.send(response.clone().into()).await?;
+                    proceeding.unacked = Some(Unacked::new(response, rseq));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn complete(&mut self) {
         self.state = TrxState::Completed(Default::default());
     }
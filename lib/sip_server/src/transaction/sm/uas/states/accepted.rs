@@ -1,7 +1,4 @@
-use common::tokio::time::Instant;
-use std::time::Duration;
-
-use super::super::TIMER_L;
+use common::{tokio::time::Instant, SipTimers};
 
 #[derive(Debug)]
 pub struct Accepted {
@@ -9,8 +6,13 @@ pub struct Accepted {
 }
 
 impl Accepted {
-    pub fn should_terminate(&self) -> bool {
-        self.entered_at.elapsed() > Duration::from_millis(TIMER_L)
+    //timer L, RFC6026
+    pub fn terminates_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.l()
+    }
+
+    pub fn should_terminate(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.terminates_at(timers)
     }
 }
 
@@ -1,8 +1,6 @@
-use common::tokio::time::Instant;
+use common::{tokio::time::Instant, SipTimers};
 use std::time::Duration;
 
-use super::super::{TIMER_G, TIMER_H, TIMER_T2};
-
 #[derive(Debug, Clone, Copy)]
 pub struct Completed {
     pub entered_at: Instant,
@@ -11,23 +9,32 @@ pub struct Completed {
 }
 
 impl Completed {
-    pub fn next_retrasmission(&self) -> Duration {
+    pub fn next_retrasmission(&self, timers: &SipTimers) -> Duration {
         use std::iter;
 
         std::cmp::min(
-            iter::repeat(Duration::from_millis(TIMER_G))
+            iter::repeat(timers.g())
                 .take(2_i32.pow(self.retransmissions_count.into()) as usize)
                 .fold(Duration::from_secs(0), |acc, x| acc + x),
-            Duration::from_millis(TIMER_T2),
+            timers.t2,
         )
     }
 
-    pub fn has_timedout(&self) -> bool {
-        self.entered_at.elapsed() >= Duration::from_millis(TIMER_H)
+    //timer H
+    pub fn timeout_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.h()
+    }
+
+    pub fn retransmission_at(&self, timers: &SipTimers) -> Instant {
+        self.last_retransmission_at + self.next_retrasmission(timers)
+    }
+
+    pub fn has_timedout(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.timeout_at(timers)
     }
 
-    pub fn should_retransmit(&self) -> bool {
-        self.last_retransmission_at.elapsed() > self.next_retrasmission()
+    pub fn should_retransmit(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.retransmission_at(timers)
     }
 
     pub fn retransmit(self) -> Self {
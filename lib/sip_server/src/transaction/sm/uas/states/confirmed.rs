@@ -1,7 +1,4 @@
-use common::{rsip, tokio::time::Instant};
-use std::time::Duration;
-
-use super::super::TIMER_I;
+use common::{rsip, tokio::time::Instant, SipTimers};
 
 #[derive(Debug)]
 pub struct Confirmed {
@@ -10,7 +7,12 @@ pub struct Confirmed {
 }
 
 impl Confirmed {
-    pub fn should_terminate(&self) -> bool {
-        self.entered_at.elapsed() > Duration::from_millis(TIMER_I)
+    //timer I
+    pub fn terminates_at(&self, timers: &SipTimers) -> Instant {
+        self.entered_at + timers.i()
+    }
+
+    pub fn should_terminate(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.terminates_at(timers)
     }
 }
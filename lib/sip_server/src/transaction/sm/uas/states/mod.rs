@@ -9,5 +9,5 @@ pub use accepted::Accepted;
 pub use completed::Completed;
 pub use confirmed::Confirmed;
 pub use errored::Errored;
-pub use proceeding::Proceeding;
+pub use proceeding::{Proceeding, Unacked};
 pub use terminated::Terminated;
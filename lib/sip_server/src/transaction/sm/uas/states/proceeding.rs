@@ -1,14 +1,74 @@
-use common::tokio::time::Instant;
+use common::{rsip, tokio::time::Instant, SipTimers};
+use std::{collections::VecDeque, time::Duration};
 
 #[derive(Debug)]
 pub struct Proceeding {
     pub entered_at: Instant,
+    //reliable provisional waiting for its PRACK, and the ones queued behind it (RFC3262 3)
+    pub unacked: Option<Unacked>,
+    pub queued: VecDeque<rsip::Response>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Unacked {
+    pub response: rsip::Response,
+    pub rseq: u32,
+    pub sent_at: Instant,
+    pub retransmissions_count: u8,
+    pub last_retransmission_at: Instant,
+}
+
+impl Unacked {
+    pub fn new(response: rsip::Response, rseq: u32) -> Self {
+        Self {
+            response,
+            rseq,
+            sent_at: Instant::now(),
+            retransmissions_count: 0,
+            last_retransmission_at: Instant::now(),
+        }
+    }
+
+    //starts at T1 and doubles each time, without a T2 cap
+    pub fn next_retrasmission(&self, timers: &SipTimers) -> Duration {
+        timers
+            .t1
+            .checked_mul(2_u32.pow(self.retransmissions_count.into()))
+            .unwrap_or(timers.t2)
+    }
+
+    //64*T1, same as timer B
+    pub fn timeout_at(&self, timers: &SipTimers) -> Instant {
+        self.sent_at + timers.b()
+    }
+
+    pub fn retransmission_at(&self, timers: &SipTimers) -> Instant {
+        self.last_retransmission_at + self.next_retrasmission(timers)
+    }
+
+    pub fn has_timedout(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.timeout_at(timers)
+    }
+
+    pub fn should_retransmit(&self, timers: &SipTimers) -> bool {
+        Instant::now() >= self.retransmission_at(timers)
+    }
+
+    pub fn retransmit(self) -> Self {
+        Self {
+            retransmissions_count: self.retransmissions_count + 1,
+            last_retransmission_at: Instant::now(),
+            ..self
+        }
+    }
 }
 
 impl Default for Proceeding {
     fn default() -> Self {
         Self {
             entered_at: Instant::now(),
+            unacked: None,
+            queued: VecDeque::new(),
         }
     }
 }
@@ -0,0 +1,223 @@
+mod states;
+
+pub use states::{Completed, Errored, Proceeding, Terminated, Trying};
+
+use crate::Error;
+use common::{
+    rsip::{self, prelude::*},
+    tokio::time::Instant,
+    SipTimers,
+};
+use models::{transaction::TransactionId, transport::TransportError, Handlers};
+use std::time::Duration;
+
+//non-INVITE server transaction, RFC3261 17.2.2
+#[derive(Debug)]
+pub struct TrxStateMachine {
+    pub id: TransactionId,
+    pub state: TrxState,
+    pub request: rsip::Request,
+    //last response sent by the TU, resent whenever the request is retransmitted
+    pub response: Option<rsip::Response>,
+    pub created_at: Instant,
+    pub timers: SipTimers,
+    reliable: bool,
+    handlers: Handlers,
+}
+
+#[derive(Debug)]
+pub enum TrxState {
+    Trying(Trying),
+    Proceeding(Proceeding),
+    Completed(Completed),
+    Terminated(Terminated),
+    Errored(Errored),
+}
+
+impl std::fmt::Display for TrxState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Trying(_) => write!(f, "TrxState::Trying"),
+            Self::Proceeding(_) => write!(f, "TrxState::Proceeding"),
+            Self::Completed(_) => write!(f, "TrxState::Completed"),
+            Self::Terminated(_) => write!(f, "TrxState::Terminated"),
+            Self::Errored(_) => write!(f, "TrxState::Errored"),
+        }
+    }
+}
+
+impl TrxStateMachine {
+    pub fn new(
+        handlers: Handlers,
+        request: rsip::Request,
+        timers: SipTimers,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            id: request.transaction_id()?.expect("transaction_id"),
+            state: TrxState::Trying(Default::default()),
+            reliable: super::is_reliable(&request),
+            request,
+            response: None,
+            created_at: Instant::now(),
+            timers,
+            handlers,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !matches!(self.state, TrxState::Errored(_) | TrxState::Terminated(_))
+    }
+
+    //the next time a timer fires and next(None) has something to do
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Completed(completed) => Some(completed.terminates_at()),
+            _ => None,
+        }
+    }
+
+    //when the transaction reached a final state, if it has
+    pub fn finished_at(&self) -> Option<Instant> {
+        match &self.state {
+            TrxState::Terminated(terminated) => Some(terminated.entered_at),
+            TrxState::Errored(errored) => Some(errored.entered_at),
+            _ => None,
+        }
+    }
+
+    pub async fn next(&mut self, sip_message: Option<rsip::SipMessage>) {
+        use rsip::SipMessage;
+        let result = match sip_message {
+            Some(SipMessage::Request(request)) => {
+                self.next_step_with_incoming_request(request).await
+            }
+            Some(SipMessage::Response(response)) => {
+                self.next_step_with_outgoing_response(response).await
+            }
+            None => self.next_step().await,
+        };
+
+        match result {
+            Ok(()) => (),
+            Err(error) => self.error(format!("transaction {} errored: {}", self.id, error), None),
+        }
+    }
+
+    //the response could not be delivered, the TU must know (RFC3261 17.2.4)
+    pub async fn transport_error(&mut self, error: TransportError) {
+        self.error(error.to_string(), None);
+
+        let sip_message: rsip::SipMessage = match &self.response {
+            Some(response) => response.clone().into(),
+            None => self.request.clone().into(),
+        };
+        if let Err(err) = self.handlers.tu.transport_error(sip_message, error).await {
+            common::log::error!("could not report transport error to TU: {}", err)
+        }
+    }
+
+    async fn next_step(&mut self) -> Result<(), Error> {
+        if let TrxState::Completed(completed) = &self.state {
+            if completed.should_terminate() {
+                self.terminate();
+            }
+        }
+
+        Ok(())
+    }
+
+    //a retransmission of the request is answered with the last response, if any
+    async fn next_step_with_incoming_request(
+        &mut self,
+        request: rsip::Request,
+    ) -> Result<(), Error> {
+        match &self.state {
+            TrxState::Trying(_) => (),
+            TrxState::Proceeding(_) | TrxState::Completed(_) => self.resend_response().await?,
+            //late retransmissions while the transaction lingers are absorbed
+            TrxState::Terminated(_) => (),
+            _ => self.error(
+                format!(
+                    "unknown transition for {} and {}",
+                    self.state, request.method
+                ),
+                Some(request.into()),
+            ),
+        }
+
+        Ok(())
+    }
+
+    async fn next_step_with_outgoing_response(
+        &mut self,
+        response: rsip::Response,
+    ) -> Result<(), Error> {
+        use rsip::common::StatusCodeKind;
+
+        match (&self.state, response.status_code.kind()) {
+            (TrxState::Trying(_), StatusCodeKind::Provisional) => {
+                self.response = Some(response.clone());
+                self.handlers.transport.send(response.into()).await?;
+                self.proceed();
+            }
+            (TrxState::Proceeding(_), StatusCodeKind::Provisional) => {
+                self.response = Some(response.clone());
+                self.handlers.transport.send(response.into()).await?;
+            }
+            (TrxState::Trying(_), _) | (TrxState::Proceeding(_), _) => {
+                self.response = Some(response.clone());
+                self.handlers.transport.send(response.into()).await?;
+                self.complete();
+            }
+            //the final response has already been sent, anything else from the TU is discarded
+            (TrxState::Completed(_), _) => (),
+            _ => self.error(
+                format!(
+                    "unknown transition for {} and {}",
+                    self.state, response.status_code
+                ),
+                Some(response.into()),
+            ),
+        }
+
+        Ok(())
+    }
+
+    async fn resend_response(&self) -> Result<(), Error> {
+        if let Some(response) = &self.response {
+            self.handlers.transport.send(response.clone().into()).await?;
+        }
+
+        Ok(())
+    }
+
+    fn proceed(&mut self) {
+        self.state = TrxState::Proceeding(Default::default());
+    }
+
+    fn complete(&mut self) {
+        let linger = match self.reliable {
+            true => Duration::from_millis(0),
+            false => self.timers.j(),
+        };
+
+        self.state = TrxState::Completed(Completed {
+            entered_at: Instant::now(),
+            linger,
+        });
+    }
+
+    fn terminate(&mut self) {
+        self.state = TrxState::Terminated(Terminated {
+            entered_at: Instant::now(),
+        });
+    }
+
+    fn error(&mut self, error: String, sip_message: Option<rsip::SipMessage>) {
+        self.state = TrxState::Errored(Errored {
+            entered_at: Instant::now(),
+            sip_message,
+            error,
+        });
+    }
+}
@@ -0,0 +1,19 @@
+use common::tokio::time::Instant;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct Completed {
+    pub entered_at: Instant,
+    //timer J, zero for reliable transports since no request retransmissions will arrive
+    pub linger: Duration,
+}
+
+impl Completed {
+    pub fn terminates_at(&self) -> Instant {
+        self.entered_at + self.linger
+    }
+
+    pub fn should_terminate(&self) -> bool {
+        Instant::now() >= self.terminates_at()
+    }
+}
@@ -0,0 +1,14 @@
+use common::tokio::time::Instant;
+
+#[derive(Debug)]
+pub struct Trying {
+    pub entered_at: Instant,
+}
+
+impl Default for Trying {
+    fn default() -> Self {
+        Self {
+            entered_at: Instant::now(),
+        }
+    }
+}
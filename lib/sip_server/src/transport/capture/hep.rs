@@ -0,0 +1,142 @@
+use super::CapturedMessage;
+use crate::Error;
+use common::{rsip, tokio::net::UdpSocket, HepConfig};
+use std::{convert::TryFrom, net::IpAddr, time::UNIX_EPOCH};
+
+const HEP_ID: &[u8] = b"HEP3";
+const HEP_HEADER_LEN: usize = 6;
+const CHUNK_HEADER_LEN: usize = 6;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const HEP_PROTOCOL_SIP: u8 = 0x01;
+
+//generic chunk types of the HEPv3 spec, all of them under vendor 0x0000
+#[derive(Debug, Clone, Copy)]
+#[repr(u16)]
+enum ChunkType {
+    IpFamily = 0x0001,
+    IpProtocol = 0x0002,
+    Ipv4Src = 0x0003,
+    Ipv4Dst = 0x0004,
+    Ipv6Src = 0x0005,
+    Ipv6Dst = 0x0006,
+    SrcPort = 0x0007,
+    DstPort = 0x0008,
+    TimestampSecs = 0x0009,
+    TimestampMicros = 0x000a,
+    ProtocolType = 0x000b,
+    CaptureId = 0x000c,
+    AuthKey = 0x000e,
+    Payload = 0x000f,
+    CorrelationId = 0x0011,
+}
+
+//sends every captured message to a HEP collector, like Homer. Capturing is best effort,
+//a collector that is down should never affect the traffic itself
+#[derive(Debug)]
+pub struct HepCollector {
+    socket: UdpSocket,
+    config: HepConfig,
+}
+
+impl HepCollector {
+    pub fn new(config: HepConfig) -> Result<Self, Error> {
+        let socket = std::net::UdpSocket::bind(super::unspecified_addr_like(&config.collector))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket: UdpSocket::from_std(socket)?,
+            config,
+        })
+    }
+
+    pub async fn send(&self, message: &CapturedMessage<'_>) {
+        let password = self.config.password.as_deref();
+        let packet = match encode(message, self.config.capture_id, password) {
+            Some(packet) => packet,
+            None => {
+                common::log::warn!("message too large for hep, not captured");
+                return;
+            }
+        };
+
+        if let Err(err) = self.socket.send_to(&packet, self.config.collector).await {
+            common::log::warn!("failed to send hep packet to {}: {}", self.config.collector, err)
+        }
+    }
+}
+
+//HEPv3 packet of the message, or None if it doesn't fit in one (64KB)
+pub fn encode(
+    message: &CapturedMessage,
+    capture_id: u32,
+    password: Option<&str>,
+) -> Option<Vec<u8>> {
+    let mut chunks = vec![];
+
+    match (message.src.ip(), message.dst.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            push_chunk(&mut chunks, ChunkType::IpFamily, &[AF_INET])?;
+            push_chunk(&mut chunks, ChunkType::Ipv4Src, &src.octets())?;
+            push_chunk(&mut chunks, ChunkType::Ipv4Dst, &dst.octets())?;
+        }
+        (src, dst) => {
+            push_chunk(&mut chunks, ChunkType::IpFamily, &[AF_INET6])?;
+            push_chunk(&mut chunks, ChunkType::Ipv6Src, &super::ipv6_octets_of(src))?;
+            push_chunk(&mut chunks, ChunkType::Ipv6Dst, &super::ipv6_octets_of(dst))?;
+        }
+    };
+    push_chunk(&mut chunks, ChunkType::IpProtocol, &[ip_protocol_of(message.transport)])?;
+    push_chunk(&mut chunks, ChunkType::SrcPort, &message.src.port().to_be_bytes())?;
+    push_chunk(&mut chunks, ChunkType::DstPort, &message.dst.port().to_be_bytes())?;
+
+    let since_epoch = message.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    push_chunk(
+        &mut chunks,
+        ChunkType::TimestampSecs,
+        &(since_epoch.as_secs() as u32).to_be_bytes(),
+    )?;
+    push_chunk(
+        &mut chunks,
+        ChunkType::TimestampMicros,
+        &since_epoch.subsec_micros().to_be_bytes(),
+    )?;
+    push_chunk(&mut chunks, ChunkType::ProtocolType, &[HEP_PROTOCOL_SIP])?;
+    push_chunk(&mut chunks, ChunkType::CaptureId, &capture_id.to_be_bytes())?;
+    if let Some(password) = password {
+        push_chunk(&mut chunks, ChunkType::AuthKey, password.as_bytes())?;
+    }
+    if let Some(call_id) = message.call_id() {
+        push_chunk(&mut chunks, ChunkType::CorrelationId, call_id.as_bytes())?;
+    }
+    push_chunk(&mut chunks, ChunkType::Payload, message.bytes)?;
+
+    let total_len = u16::try_from(HEP_HEADER_LEN + chunks.len()).ok()?;
+    let mut packet = Vec::with_capacity(total_len as usize);
+    packet.extend_from_slice(HEP_ID);
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    packet.extend_from_slice(&chunks);
+
+    Some(packet)
+}
+
+fn push_chunk(chunks: &mut Vec<u8>, chunk_type: ChunkType, payload: &[u8]) -> Option<()> {
+    let len = u16::try_from(CHUNK_HEADER_LEN + payload.len()).ok()?;
+
+    chunks.extend_from_slice(&0u16.to_be_bytes());
+    chunks.extend_from_slice(&(chunk_type as u16).to_be_bytes());
+    chunks.extend_from_slice(&len.to_be_bytes());
+    chunks.extend_from_slice(payload);
+
+    Some(())
+}
+
+//what's captured is always the SIP message, so secure transports are just TCP
+fn ip_protocol_of(transport: rsip::Transport) -> u8 {
+    match transport {
+        rsip::Transport::Udp => IPPROTO_UDP,
+        _ => IPPROTO_TCP,
+    }
+}
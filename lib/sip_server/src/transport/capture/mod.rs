@@ -0,0 +1,87 @@
+mod hep;
+mod pcap;
+
+pub use hep::{encode as hep_encode, HepCollector};
+pub use pcap::PcapWriter;
+
+use crate::Error;
+use common::rsip;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::SystemTime,
+};
+
+//a message as it went over the wire, in either direction
+#[derive(Debug, Clone)]
+pub struct CapturedMessage<'a> {
+    pub bytes: &'a [u8],
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub transport: rsip::Transport,
+    pub timestamp: SystemTime,
+}
+
+impl<'a> CapturedMessage<'a> {
+    //the Call-ID, used to correlate the messages of a call in the capture server
+    pub fn call_id(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.bytes)
+            .ok()?
+            .split("\r\n\r\n")
+            .next()?
+            .split("\r\n")
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            })
+            .find(|(name, _)| name.eq_ignore_ascii_case("call-id") || *name == "i")
+            .map(|(_, value)| value)
+    }
+}
+
+//exports traffic to whatever capture servers are configured, nothing by default
+#[derive(Debug, Default)]
+pub struct Capture {
+    hep: Option<HepCollector>,
+    pcap: Option<PcapWriter>,
+}
+
+impl Capture {
+    pub fn new(config: &common::Config) -> Result<Self, Error> {
+        Ok(Self {
+            hep: match &config.hep {
+                Some(hep_config) => Some(HepCollector::new(hep_config.clone())?),
+                None => None,
+            },
+            pcap: match &config.pcap {
+                Some(pcap_config) => Some(PcapWriter::new(pcap_config.clone())?),
+                None => None,
+            },
+        })
+    }
+
+    pub async fn capture(&self, message: CapturedMessage<'_>) {
+        if let Some(pcap) = &self.pcap {
+            pcap.write(&message);
+        }
+        if let Some(hep) = &self.hep {
+            hep.send(&message).await;
+        }
+    }
+}
+
+//stands in for a local address we don't know, like the one of an incoming message
+//that arrived on a listener bound to all interfaces
+pub fn unspecified_addr_like(addr: &SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0).into(),
+        SocketAddr::V6(_) => (IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0).into(),
+    }
+}
+
+//both ends of a packet are of the same family, unless one of them is unknown
+fn ipv6_octets_of(ip_addr: IpAddr) -> [u8; 16] {
+    match ip_addr {
+        IpAddr::V4(ip_addr) => ip_addr.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip_addr) => ip_addr.octets(),
+    }
+}
@@ -0,0 +1,362 @@
+use super::CapturedMessage;
+use crate::Error;
+use common::{rsip, PcapConfig};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    fs::File,
+    io::{BufWriter, Write},
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION: (u16, u16) = (2, 4);
+const PCAP_SNAPLEN: u32 = 262_144;
+//packets start with the IP header, v4 or v6
+const LINKTYPE_RAW: u32 = 101;
+const PCAP_HEADER_LEN: u64 = 24;
+const RECORD_HEADER_LEN: u64 = 16;
+
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+const TCP_HEADER_LEN: usize = 20;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const TTL: u8 = 64;
+
+//records waiting for the disk, after that new ones are dropped instead of slowing down traffic
+const QUEUE_SIZE: usize = 1024;
+//sequence numbers are only needed per file, but a busy server can open lots of streams
+//before a file fills up
+const MAX_TCP_STREAMS: usize = 4096;
+
+//writes captured messages to pcap files, wrapped in made up IP and UDP/TCP headers,
+//so that Wireshark dissects them as SIP and can show call flows. The files are written by
+//a thread of their own, so that the disk never blocks the runtime
+#[derive(Debug)]
+pub struct PcapWriter {
+    commands: SyncSender<Command>,
+    files: Arc<Mutex<VecDeque<PathBuf>>>,
+}
+
+#[derive(Debug)]
+enum Command {
+    Write(Record),
+    Sync(SyncSender<()>),
+}
+
+#[derive(Debug)]
+struct Record {
+    bytes: Vec<u8>,
+    src: SocketAddr,
+    dst: SocketAddr,
+    transport: rsip::Transport,
+    timestamp: SystemTime,
+}
+
+#[derive(Debug)]
+struct Writer {
+    config: PcapConfig,
+    file: Option<BufWriter<File>>,
+    file_size: u64,
+    files: Arc<Mutex<VecDeque<PathBuf>>>,
+    files_created: usize,
+    //Wireshark needs proper sequence numbers to reassemble TCP streams
+    tcp_seqs: HashMap<(SocketAddr, SocketAddr), u32>,
+}
+
+impl PcapWriter {
+    pub fn new(config: PcapConfig) -> Result<Self, Error> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let (commands, commands_rx) = mpsc::sync_channel(QUEUE_SIZE);
+        let files: Arc<Mutex<VecDeque<PathBuf>>> = Default::default();
+        let writer = Writer {
+            config,
+            file: None,
+            file_size: 0,
+            files: files.clone(),
+            files_created: 0,
+            tcp_seqs: Default::default(),
+        };
+        std::thread::Builder::new()
+            .name("pcap-writer".into())
+            .spawn(move || writer.run(commands_rx))?;
+
+        Ok(Self { commands, files })
+    }
+
+    //like any capture, failing to write is logged and otherwise ignored
+    pub fn write(&self, message: &CapturedMessage) {
+        let record = Record {
+            bytes: message.bytes.to_vec(),
+            src: message.src,
+            dst: message.dst,
+            transport: message.transport,
+            timestamp: message.timestamp,
+        };
+
+        match self.commands.try_send(Command::Write(record)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                common::log::warn!("pcap writer is falling behind, dropping record")
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                common::log::warn!("pcap writer is gone, dropping record")
+            }
+        }
+    }
+
+    //blocks until everything written so far is on disk, which only tests should need
+    pub fn sync(&self) {
+        let (done, done_rx) = mpsc::sync_channel(1);
+        if self.commands.send(Command::Sync(done)).is_ok() {
+            let _ = done_rx.recv();
+        }
+    }
+
+    //the paths of the files written so far and not rotated away, oldest first
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files
+            .lock()
+            .expect("pcap files lock")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+impl Writer {
+    //the file is flushed whenever the queue runs empty, instead of after every record
+    fn run(mut self, commands: Receiver<Command>) {
+        while let Ok(command) = commands.recv() {
+            self.handle(command);
+            while let Ok(command) = commands.try_recv() {
+                self.handle(command);
+            }
+            self.flush();
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Write(record) => {
+                if let Err(err) = self.write(&record) {
+                    common::log::warn!("failed to write pcap record: {}", err);
+                }
+            }
+            Command::Sync(done) => {
+                self.flush();
+                let _ = done.send(());
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(file) = self.file.as_mut() {
+            if let Err(err) = file.flush() {
+                common::log::warn!("failed to flush pcap file: {}", err);
+            }
+        }
+    }
+
+    fn write(&mut self, record: &Record) -> Result<(), Error> {
+        let message = CapturedMessage {
+            bytes: &record.bytes,
+            src: record.src,
+            dst: record.dst,
+            transport: record.transport,
+            timestamp: record.timestamp,
+        };
+
+        if self.tcp_seqs.len() >= MAX_TCP_STREAMS {
+            self.tcp_seqs.clear();
+        }
+        let packet = packet_from(&message, &mut self.tcp_seqs)
+            .ok_or_else(|| Error::custom("message too large for an IP packet"))?;
+        let record_len = RECORD_HEADER_LEN + packet.len() as u64;
+
+        if self.file.is_none() || self.file_size + record_len > self.config.max_file_size {
+            self.rotate()?;
+        }
+
+        let since_epoch = message
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let file = self.file.as_mut().expect("pcap file");
+        file.write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        file.write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        file.write_all(&(packet.len() as u32).to_le_bytes())?;
+        file.write_all(&(packet.len() as u32).to_le_bytes())?;
+        file.write_all(&packet)?;
+        self.file_size += record_len;
+
+        Ok(())
+    }
+
+    //streams start over in each file, so the sequence numbers of the previous one are dropped
+    fn rotate(&mut self) -> Result<(), Error> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        self.tcp_seqs.clear();
+
+        let mut files = self.files.lock().expect("pcap files lock");
+        while files.len() >= self.config.max_files.max(1) {
+            if let Some(path) = files.pop_front() {
+                if let Err(err) = std::fs::remove_file(&path) {
+                    common::log::warn!("failed to remove {}: {}", path.display(), err);
+                }
+            }
+        }
+
+        self.files_created += 1;
+        let path = PathBuf::from(&self.config.dir).join(format!(
+            "viska-{}-{}.pcap",
+            UNIX_EPOCH.elapsed().unwrap_or_default().as_secs(),
+            self.files_created
+        ));
+
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        file.write_all(&PCAP_VERSION.0.to_le_bytes())?;
+        file.write_all(&PCAP_VERSION.1.to_le_bytes())?;
+        file.write_all(&0i32.to_le_bytes())?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        file.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+
+        self.file = Some(file);
+        self.file_size = PCAP_HEADER_LEN;
+        files.push_back(path);
+
+        Ok(())
+    }
+}
+
+//the message as an IP packet. Secure transports are written as plain TCP,
+//since what we have is the decrypted message anyway
+fn packet_from(
+    message: &CapturedMessage,
+    tcp_seqs: &mut HashMap<(SocketAddr, SocketAddr), u32>,
+) -> Option<Vec<u8>> {
+    let (protocol, transport_header) = match message.transport {
+        rsip::Transport::Udp => (IPPROTO_UDP, udp_header_for(message)?),
+        _ => {
+            let seq = tcp_seqs.entry((message.src, message.dst)).or_insert(1);
+            let header = tcp_header_for(message, *seq);
+            *seq = seq.wrapping_add(message.bytes.len() as u32);
+            (IPPROTO_TCP, header)
+        }
+    };
+    let payload_len = transport_header.len() + message.bytes.len();
+
+    let mut packet = match (message.src.ip(), message.dst.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            ipv4_header_for(src.octets(), dst.octets(), protocol, payload_len)?
+        }
+        (src, dst) => ipv6_header_for(
+            super::ipv6_octets_of(src),
+            super::ipv6_octets_of(dst),
+            protocol,
+            payload_len,
+        )?,
+    };
+    packet.extend_from_slice(&transport_header);
+    packet.extend_from_slice(message.bytes);
+
+    Some(packet)
+}
+
+fn ipv4_header_for(
+    src: [u8; 4],
+    dst: [u8; 4],
+    protocol: u8,
+    payload_len: usize,
+) -> Option<Vec<u8>> {
+    let total_len = u16::try_from(IPV4_HEADER_LEN + payload_len).ok()?;
+
+    let mut header = Vec::with_capacity(IPV4_HEADER_LEN);
+    header.push(0x45);
+    header.push(0);
+    header.extend_from_slice(&total_len.to_be_bytes());
+    //identification, then the don't fragment flag
+    header.extend_from_slice(&[0, 0, 0x40, 0]);
+    header.push(TTL);
+    header.push(protocol);
+    header.extend_from_slice(&[0, 0]);
+    header.extend_from_slice(&src);
+    header.extend_from_slice(&dst);
+
+    let checksum = ipv4_checksum_of(&header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    Some(header)
+}
+
+fn ipv6_header_for(
+    src: [u8; 16],
+    dst: [u8; 16],
+    protocol: u8,
+    payload_len: usize,
+) -> Option<Vec<u8>> {
+    let payload_len = u16::try_from(payload_len).ok()?;
+
+    let mut header = Vec::with_capacity(IPV6_HEADER_LEN);
+    header.extend_from_slice(&[0x60, 0, 0, 0]);
+    header.extend_from_slice(&payload_len.to_be_bytes());
+    header.push(protocol);
+    header.push(TTL);
+    header.extend_from_slice(&src);
+    header.extend_from_slice(&dst);
+
+    Some(header)
+}
+
+//the checksum is left empty, Wireshark doesn't verify it by default
+fn udp_header_for(message: &CapturedMessage) -> Option<Vec<u8>> {
+    let len = u16::try_from(UDP_HEADER_LEN + message.bytes.len()).ok()?;
+
+    let mut header = Vec::with_capacity(UDP_HEADER_LEN);
+    header.extend_from_slice(&message.src.port().to_be_bytes());
+    header.extend_from_slice(&message.dst.port().to_be_bytes());
+    header.extend_from_slice(&len.to_be_bytes());
+    header.extend_from_slice(&[0, 0]);
+
+    Some(header)
+}
+
+//a PSH/ACK segment, without handshake, which Wireshark handles just fine
+fn tcp_header_for(message: &CapturedMessage, seq: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(TCP_HEADER_LEN);
+    header.extend_from_slice(&message.src.port().to_be_bytes());
+    header.extend_from_slice(&message.dst.port().to_be_bytes());
+    header.extend_from_slice(&seq.to_be_bytes());
+    header.extend_from_slice(&0u32.to_be_bytes());
+    header.push((TCP_HEADER_LEN as u8 / 4) << 4);
+    header.push(0x18);
+    header.extend_from_slice(&u16::MAX.to_be_bytes());
+    header.extend_from_slice(&[0, 0, 0, 0]);
+
+    header
+}
+
+fn ipv4_checksum_of(header: &[u8]) -> u16 {
+    let mut sum = header
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
@@ -0,0 +1,113 @@
+use super::{DnsAnswer, DnsResolver, NaptrRecord, SrvRecord};
+use crate::Error;
+use common::{
+    async_trait::async_trait,
+    tokio::{sync::Mutex, time::Instant},
+};
+use std::{collections::HashMap, future::Future, net::IpAddr};
+
+//keeps the answers of the wrapped resolver for as long as their TTL says. Empty answers
+//are cached as well (negative caching, RFC2308), failed queries are not
+#[derive(Debug)]
+pub struct CachingResolver<R: DnsResolver> {
+    resolver: R,
+    naptr: Cache<NaptrRecord>,
+    srv: Cache<SrvRecord>,
+    ip: Cache<IpAddr>,
+}
+
+impl<R: DnsResolver> CachingResolver<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            naptr: Default::default(),
+            srv: Default::default(),
+            ip: Default::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.resolver
+    }
+}
+
+#[async_trait]
+impl<R: DnsResolver> DnsResolver for CachingResolver<R> {
+    async fn naptr_lookup(&self, domain: &str) -> Result<DnsAnswer<NaptrRecord>, Error> {
+        self.naptr
+            .get_or_insert_with(domain, self.resolver.naptr_lookup(domain))
+            .await
+    }
+
+    async fn srv_lookup(&self, name: &str) -> Result<DnsAnswer<SrvRecord>, Error> {
+        self.srv
+            .get_or_insert_with(name, self.resolver.srv_lookup(name))
+            .await
+    }
+
+    async fn ip_lookup(&self, domain: &str) -> Result<DnsAnswer<IpAddr>, Error> {
+        self.ip
+            .get_or_insert_with(domain, self.resolver.ip_lookup(domain))
+            .await
+    }
+}
+
+#[derive(Debug)]
+struct Cache<T>(Mutex<HashMap<String, CacheEntry<T>>>);
+
+#[derive(Debug)]
+struct CacheEntry<T> {
+    answer: DnsAnswer<T>,
+    expires_at: Instant,
+}
+
+impl<T> Default for Cache<T> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<T: Clone> Cache<T> {
+    //the query only runs on a cache miss. The lock is not held while it runs,
+    //so a slow DNS server doesn't block lookups of other names
+    async fn get_or_insert_with<F>(&self, name: &str, query: F) -> Result<DnsAnswer<T>, Error>
+    where
+        F: Future<Output = Result<DnsAnswer<T>, Error>>,
+    {
+        let name = super::normalized(name);
+
+        if let Some(answer) = self.get(&name).await {
+            return Ok(answer);
+        }
+
+        let answer = query.await?;
+
+        let mut entries = self.0.lock().await;
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.insert(
+            name,
+            CacheEntry {
+                answer: answer.clone(),
+                expires_at: now + answer.ttl,
+            },
+        );
+
+        Ok(answer)
+    }
+
+    //the TTL of a cached answer counts down, like it would on a DNS server
+    async fn get(&self, name: &str) -> Option<DnsAnswer<T>> {
+        let entries = self.0.lock().await;
+        let entry = entries.get(name)?;
+        let now = Instant::now();
+        if entry.expires_at <= now {
+            return None;
+        }
+
+        Some(DnsAnswer {
+            records: entry.answer.records.clone(),
+            ttl: entry.expires_at - now,
+        })
+    }
+}
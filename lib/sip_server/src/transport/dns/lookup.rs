@@ -0,0 +1,389 @@
+use super::{CachingResolver, DnsResolver, SrvRecord, StaticResolver, Target, TrustDnsResolver};
+use crate::{transport::DnsLookup, Error};
+use common::{
+    async_trait::async_trait,
+    rand::Rng,
+    rsip::{self, prelude::*},
+    tokio::{sync::Mutex, time::Instant},
+};
+use models::{
+    rsip_ext::{HostExt, TransportExt, ViaExt},
+    transaction::TransactionId,
+    transport::{RequestMsg, ResponseMsg, TransportMsg},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+//websockets can't be discovered through DNS (RFC7118 6)
+const SUPPORTED_TRANSPORTS: [rsip::Transport; 3] =
+    [rsip::Transport::Udp, rsip::Transport::Tcp, rsip::Transport::Tls];
+
+//queries the DNS servers of the system, caching their answers
+pub type DefaultDnsLookup = Rfc3263Lookup<CachingResolver<TrustDnsResolver>>;
+//resolves only the records it has been configured with
+pub type StaticDnsLookup = Rfc3263Lookup<StaticResolver>;
+
+//resolves targets as described in RFC3263, and keeps the remaining targets of each
+//transaction around, so that it can fail over to the next one
+#[derive(Debug)]
+pub struct Rfc3263Lookup<R: DnsResolver> {
+    resolver: R,
+    lookups: Mutex<Lookups>,
+    //no transaction lives longer than Timer B/F, after that its targets are useless
+    lookup_ttl: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Lookups {
+    by_transaction: HashMap<TransactionId, TransactionTargets>,
+    //all lookups live as long, so the oldest one is always in front
+    expirations: VecDeque<(Instant, TransactionId)>,
+}
+
+#[derive(Debug)]
+struct TransactionTargets {
+    current: Target,
+    remaining: VecDeque<Target>,
+    created_at: Instant,
+}
+
+impl DefaultDnsLookup {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::with_resolver(CachingResolver::new(
+            TrustDnsResolver::from_system_conf()?,
+        )))
+    }
+}
+
+impl<R: DnsResolver> Rfc3263Lookup<R> {
+    pub fn with_resolver(resolver: R) -> Self {
+        Self::with_lookup_ttl(resolver, common::CONFIG.timers.longest_timeout())
+    }
+
+    //same as with_resolver, but keeps the targets of each transaction for that long instead
+    pub fn with_lookup_ttl(resolver: R, lookup_ttl: Duration) -> Self {
+        Self {
+            resolver,
+            lookups: Default::default(),
+            lookup_ttl,
+        }
+    }
+
+    //retransmissions of a transaction must go to the target it already uses
+    async fn resolve(
+        &self,
+        transaction_id: Option<TransactionId>,
+        uri: rsip::Uri,
+    ) -> Result<Target, Error> {
+        if let Some(transaction_id) = &transaction_id {
+            let lookups = self.lookups.lock().await;
+            if let Some(transaction_targets) = lookups.by_transaction.get(transaction_id) {
+                return Ok(transaction_targets.current);
+            }
+        }
+
+        let mut remaining = self.targets_for(&uri).await?;
+        let current = remaining
+            .pop_front()
+            .ok_or_else(|| Error::custom(format!("could not resolve any target for {}", uri)))?;
+
+        if let Some(transaction_id) = transaction_id {
+            let mut lookups = self.lookups.lock().await;
+            lookups.expire(self.lookup_ttl);
+            lookups.insert(
+                transaction_id,
+                TransactionTargets {
+                    current,
+                    remaining,
+                    created_at: Instant::now(),
+                },
+            );
+        }
+
+        Ok(current)
+    }
+
+    //all targets of the uri, in the order they should be tried (RFC3263 4)
+    pub async fn targets_for(&self, uri: &rsip::Uri) -> Result<VecDeque<Target>, Error> {
+        let secure = uri.is_sips()?;
+        let transport = uri.params.iter().find_map(|param| match param {
+            rsip::Param::Transport(transport) => Some(*transport),
+            _ => None,
+        });
+        let default_transport = if secure {
+            rsip::Transport::Tls
+        } else {
+            rsip::Transport::Udp
+        };
+        let port = uri.host_with_port.port.map(|port| *port.value());
+
+        if let Some(ip_addr) = uri.host_with_port.host.ip_addr() {
+            let transport = transport.unwrap_or(default_transport);
+            return Ok(vec![Target {
+                ip_addr,
+                port: port.unwrap_or_else(|| common::default_port_for(transport)),
+                transport,
+            }]
+            .into());
+        }
+
+        let domain = uri.host_with_port.host.to_string();
+
+        //an explicit port skips NAPTR and SRV (RFC3263 4.1, 4.2)
+        if let Some(port) = port {
+            let transport = transport.unwrap_or(default_transport);
+            return self.ip_targets_for(&domain, port, transport).await;
+        }
+
+        let mut targets = match transport {
+            Some(transport) => self.srv_targets_for(&domain, transport).await?,
+            None => self.naptr_targets_for(&domain, secure).await?,
+        };
+
+        if targets.is_empty() {
+            let transport = transport.unwrap_or(default_transport);
+            targets = self
+                .ip_targets_for(&domain, common::default_port_for(transport), transport)
+                .await?;
+        }
+
+        Ok(targets)
+    }
+
+    //without NAPTR records, the SRV records of each supported transport are tried
+    async fn naptr_targets_for(
+        &self,
+        domain: &str,
+        secure: bool,
+    ) -> Result<VecDeque<Target>, Error> {
+        let is_usable = |transport: &rsip::Transport| {
+            SUPPORTED_TRANSPORTS.contains(transport) && (!secure || transport.is_secure())
+        };
+
+        let mut records = self.resolver.naptr_lookup(domain).await?.records;
+        records.sort_by_key(|record| (record.order, record.preference));
+        let services = records
+            .iter()
+            .filter_map(|record| Some((record.transport()?, record.replacement.clone())))
+            .filter(|(transport, _)| is_usable(transport))
+            .collect::<Vec<_>>();
+
+        let mut targets = VecDeque::new();
+        if services.is_empty() {
+            for transport in SUPPORTED_TRANSPORTS.iter().filter(|t| is_usable(t)) {
+                targets.extend(self.srv_targets_for(domain, *transport).await?);
+            }
+        } else {
+            for (transport, replacement) in services {
+                targets.extend(self.srv_records_to_targets(&replacement, transport).await?);
+            }
+        }
+
+        Ok(targets)
+    }
+
+    async fn srv_targets_for(
+        &self,
+        domain: &str,
+        transport: rsip::Transport,
+    ) -> Result<VecDeque<Target>, Error> {
+        self.srv_records_to_targets(&srv_name_for(domain, transport), transport)
+            .await
+    }
+
+    async fn srv_records_to_targets(
+        &self,
+        name: &str,
+        transport: rsip::Transport,
+    ) -> Result<VecDeque<Target>, Error> {
+        let records = self.resolver.srv_lookup(name).await?.records;
+        let records = weighted_order(records, &mut common::rand::thread_rng());
+
+        let mut targets = VecDeque::new();
+        for SrvRecord { port, target, .. } in records {
+            targets.extend(self.ip_targets_for(&target, port, transport).await?);
+        }
+
+        Ok(targets)
+    }
+
+    async fn ip_targets_for(
+        &self,
+        domain: &str,
+        port: u16,
+        transport: rsip::Transport,
+    ) -> Result<VecDeque<Target>, Error> {
+        Ok(self
+            .resolver
+            .ip_lookup(domain)
+            .await?
+            .records
+            .into_iter()
+            .map(|ip_addr| Target {
+                ip_addr,
+                port,
+                transport,
+            })
+            .collect())
+    }
+}
+
+//lower priority first, and within the same priority each record is picked with a probability
+//proportional to its weight (RFC2782), so that the load spreads over the targets
+pub fn weighted_order<G: Rng + ?Sized>(mut records: Vec<SrvRecord>, rng: &mut G) -> Vec<SrvRecord> {
+    //records of weight 0 go first, so that they only get picked when nothing else is left
+    records.sort_by_key(|record| (record.priority, record.weight));
+
+    let mut ordered = Vec::with_capacity(records.len());
+    while let Some(priority) = records.first().map(|record| record.priority) {
+        let candidates = records
+            .iter()
+            .take_while(|record| record.priority == priority)
+            .count();
+        let total_weight: u32 = records[..candidates]
+            .iter()
+            .map(|record| u32::from(record.weight))
+            .sum();
+
+        let pick = rng.gen_range(0, total_weight + 1);
+        let mut running_weight = 0;
+        let index = records[..candidates]
+            .iter()
+            .position(|record| {
+                running_weight += u32::from(record.weight);
+                running_weight >= pick
+            })
+            .unwrap_or(0);
+
+        ordered.push(records.remove(index));
+    }
+
+    ordered
+}
+
+#[async_trait]
+impl<R: DnsResolver> DnsLookup for Rfc3263Lookup<R> {
+    async fn request_msg_from(&self, request: rsip::Request) -> Result<RequestMsg, Error> {
+        let target = self
+            .resolve(request.transaction_id()?, request.uri.clone())
+            .await?;
+
+        Ok(RequestMsg {
+            sip_request: request,
+            peer: target.socket_addr(),
+            transport: target.transport,
+        })
+    }
+
+    //RFC3263 5: received (along with rport) is preferred, then sent-by if it is an IP.
+    //Websocket clients always end up with received, since their sent-by is `.invalid`
+    async fn response_msg_from(&self, response: rsip::Response) -> Result<ResponseMsg, Error> {
+        let via_header = response.via_header()?.typed()?;
+        let transport = via_header.transport;
+
+        if let Some(ip_addr) = via_header.peer_ip_addr() {
+            let port = via_header
+                .peer_port()
+                .unwrap_or_else(|| common::default_port_for(transport));
+
+            return Ok(ResponseMsg {
+                sip_response: response,
+                peer: (ip_addr, port).into(),
+                transport,
+            });
+        }
+
+        let target = self
+            .resolve(response.transaction_id()?, sent_by_uri_from(&via_header))
+            .await?;
+
+        Ok(ResponseMsg {
+            sip_response: response,
+            peer: target.socket_addr(),
+            transport: target.transport,
+        })
+    }
+
+    async fn failover(
+        &self,
+        previous: &TransactionId,
+        message: rsip::SipMessage,
+    ) -> Result<Option<TransportMsg>, Error> {
+        let transaction_targets = self.lookups.lock().await.by_transaction.remove(previous);
+        let mut transaction_targets = match transaction_targets {
+            Some(transaction_targets) => transaction_targets,
+            None => return Ok(None),
+        };
+
+        let target = match transaction_targets.remaining.pop_front() {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+
+        if let Some(transaction_id) = message.transaction_id()? {
+            self.lookups.lock().await.insert(
+                transaction_id,
+                TransactionTargets {
+                    current: target,
+                    ..transaction_targets
+                },
+            );
+        }
+
+        Ok(Some(TransportMsg {
+            sip_message: message,
+            peer: target.socket_addr(),
+            transport: target.transport,
+        }))
+    }
+}
+
+impl Lookups {
+    fn insert(&mut self, transaction_id: TransactionId, transaction_targets: TransactionTargets) {
+        self.expirations
+            .push_back((transaction_targets.created_at, transaction_id.clone()));
+        self.by_transaction.insert(transaction_id, transaction_targets);
+    }
+
+    fn expire(&mut self, lookup_ttl: Duration) {
+        while let Some((created_at, _)) = self.expirations.front() {
+            if created_at.elapsed() < lookup_ttl {
+                break;
+            }
+            if let Some((created_at, transaction_id)) = self.expirations.pop_front() {
+                let is_stale = matches!(
+                    self.by_transaction.get(&transaction_id),
+                    Some(transaction_targets) if transaction_targets.created_at <= created_at
+                );
+                if is_stale {
+                    self.by_transaction.remove(&transaction_id);
+                }
+            }
+        }
+    }
+}
+
+fn srv_name_for(domain: &str, transport: rsip::Transport) -> String {
+    match transport {
+        rsip::Transport::Tls => format!("_sips._tcp.{}", domain),
+        transport => format!("_sip._{}.{}", transport.to_string().to_lowercase(), domain),
+    }
+}
+
+//a domain in sent-by is resolved like a uri with the transport of the Via (RFC3263 5)
+fn sent_by_uri_from(via_header: &rsip::typed::Via) -> rsip::Uri {
+    let scheme = if via_header.transport.is_secure() {
+        rsip::Scheme::Sips
+    } else {
+        rsip::Scheme::Sip
+    };
+
+    rsip::Uri {
+        scheme: Some(scheme),
+        host_with_port: via_header.sent_by().clone(),
+        params: vec![rsip::Param::Transport(via_header.transport)],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,88 @@
+mod caching;
+mod lookup;
+mod static_resolver;
+mod trust_dns;
+
+pub use caching::CachingResolver;
+pub use lookup::{weighted_order, DefaultDnsLookup, Rfc3263Lookup, StaticDnsLookup};
+pub use static_resolver::StaticResolver;
+pub use trust_dns::TrustDnsResolver;
+
+use crate::Error;
+use common::{async_trait::async_trait, rsip};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+//the record level DNS queries that RFC3263 needs
+#[async_trait]
+pub trait DnsResolver: Send + Sync + 'static {
+    async fn naptr_lookup(&self, domain: &str) -> Result<DnsAnswer<NaptrRecord>, Error>;
+    async fn srv_lookup(&self, name: &str) -> Result<DnsAnswer<SrvRecord>, Error>;
+    async fn ip_lookup(&self, domain: &str) -> Result<DnsAnswer<IpAddr>, Error>;
+}
+
+//a domain without records is not an error, but an empty answer that can be cached as well
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DnsAnswer<T> {
+    pub records: Vec<T>,
+    pub ttl: Duration,
+}
+
+impl<T> DnsAnswer<T> {
+    pub fn empty(ttl: Duration) -> Self {
+        Self {
+            records: vec![],
+            ttl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NaptrRecord {
+    pub order: u16,
+    pub preference: u16,
+    pub services: String,
+    pub replacement: String,
+}
+
+impl NaptrRecord {
+    //RFC3263 4.1 and RFC7118 9
+    pub fn transport(&self) -> Option<rsip::Transport> {
+        match self.services.to_uppercase().as_str() {
+            "SIP+D2U" => Some(rsip::Transport::Udp),
+            "SIP+D2T" => Some(rsip::Transport::Tcp),
+            "SIPS+D2T" => Some(rsip::Transport::Tls),
+            "SIP+D2W" => Some(rsip::Transport::Ws),
+            "SIPS+D2W" => Some(rsip::Transport::Wss),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Target {
+    pub ip_addr: IpAddr,
+    pub port: u16,
+    pub transport: rsip::Transport,
+}
+
+impl Target {
+    pub fn socket_addr(&self) -> SocketAddr {
+        (self.ip_addr, self.port).into()
+    }
+}
+
+//names are case insensitive, and the trailing dot of fully qualified names is optional
+fn normalized(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
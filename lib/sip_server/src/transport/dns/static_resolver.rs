@@ -0,0 +1,126 @@
+use super::{DnsAnswer, DnsResolver, NaptrRecord, SrvRecord};
+use crate::Error;
+use common::async_trait::async_trait;
+use std::{collections::HashMap, net::IpAddr, str::FromStr, time::Duration};
+
+//static records never change, but whatever caches them shouldn't keep them forever
+const STATIC_TTL: Duration = Duration::from_secs(3600);
+
+//answers from a fixed set of records, so that lab setups and tests can use domains
+//without a DNS server. Records can be added one by one, or loaded from a hosts-like file:
+//
+//  # comments and empty lines are ignored
+//  NAPTR example.com 10 50 SIP+D2T _sip._tcp.example.com
+//  SRV _sip._tcp.example.com 10 60 5060 sip1.example.com
+//  A sip1.example.com 192.0.2.1
+//  AAAA sip1.example.com 2001:db8::1
+//  192.0.2.2 sip2.example.com sip2
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    naptr: HashMap<String, Vec<NaptrRecord>>,
+    srv: HashMap<String, Vec<SrvRecord>>,
+    ip: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+    pub fn with_naptr(mut self, domain: &str, record: NaptrRecord) -> Self {
+        self.naptr
+            .entry(super::normalized(domain))
+            .or_default()
+            .push(record);
+        self
+    }
+
+    pub fn with_srv(mut self, name: &str, record: SrvRecord) -> Self {
+        self.srv
+            .entry(super::normalized(name))
+            .or_default()
+            .push(record);
+        self
+    }
+
+    pub fn with_ip(mut self, domain: &str, ip_addr: IpAddr) -> Self {
+        self.ip
+            .entry(super::normalized(domain))
+            .or_default()
+            .push(ip_addr);
+        self
+    }
+
+    pub fn from_hosts_file(path: &str) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    fn with_line(self, line: &str) -> Result<Self, Error> {
+        let invalid = || Error::custom(format!("invalid static dns record: {}", line));
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            ["NAPTR", domain, order, preference, services, replacement] => Ok(self.with_naptr(
+                domain,
+                NaptrRecord {
+                    order: order.parse().map_err(|_| invalid())?,
+                    preference: preference.parse().map_err(|_| invalid())?,
+                    services: services.to_string(),
+                    replacement: replacement.to_string(),
+                },
+            )),
+            ["SRV", name, priority, weight, port, target] => Ok(self.with_srv(
+                name,
+                SrvRecord {
+                    priority: priority.parse().map_err(|_| invalid())?,
+                    weight: weight.parse().map_err(|_| invalid())?,
+                    port: port.parse().map_err(|_| invalid())?,
+                    target: target.to_string(),
+                },
+            )),
+            ["A", domain, ip_addr] | ["AAAA", domain, ip_addr] => {
+                Ok(self.with_ip(domain, ip_addr.parse().map_err(|_| invalid())?))
+            }
+            [ip_addr, domains @ ..] if !domains.is_empty() => {
+                let ip_addr: IpAddr = ip_addr.parse().map_err(|_| invalid())?;
+                Ok(domains
+                    .iter()
+                    .fold(self, |resolver, domain| resolver.with_ip(domain, ip_addr)))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl FromStr for StaticResolver {
+    type Err = Error;
+
+    fn from_str(records: &str) -> Result<Self, Self::Err> {
+        records
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .try_fold(Self::default(), |resolver, line| resolver.with_line(line))
+    }
+}
+
+#[async_trait]
+impl DnsResolver for StaticResolver {
+    async fn naptr_lookup(&self, domain: &str) -> Result<DnsAnswer<NaptrRecord>, Error> {
+        Ok(answer_from(&self.naptr, domain))
+    }
+
+    async fn srv_lookup(&self, name: &str) -> Result<DnsAnswer<SrvRecord>, Error> {
+        Ok(answer_from(&self.srv, name))
+    }
+
+    async fn ip_lookup(&self, domain: &str) -> Result<DnsAnswer<IpAddr>, Error> {
+        Ok(answer_from(&self.ip, domain))
+    }
+}
+
+fn answer_from<T: Clone>(records: &HashMap<String, Vec<T>>, name: &str) -> DnsAnswer<T> {
+    DnsAnswer {
+        records: records
+            .get(&super::normalized(name))
+            .cloned()
+            .unwrap_or_default(),
+        ttl: STATIC_TTL,
+    }
+}
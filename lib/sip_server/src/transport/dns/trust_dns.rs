@@ -0,0 +1,123 @@
+use super::{DnsAnswer, DnsResolver, NaptrRecord, SrvRecord};
+use crate::Error;
+use common::{
+    async_trait::async_trait,
+    rsip_dns::trust_dns_resolver::{
+        error::{ResolveError, ResolveErrorKind},
+        proto::{
+            rr::{RData, RecordType},
+            xfer::DnsRequestOptions,
+        },
+        TokioAsyncResolver,
+    },
+};
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+//used when the SOA of the domain doesn't tell us for how long a missing record stays missing
+const NEGATIVE_TTL: Duration = Duration::from_secs(30);
+
+//queries the DNS servers of the system (resolv.conf)
+#[derive(Clone)]
+pub struct TrustDnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl TrustDnsResolver {
+    pub fn from_system_conf() -> Result<Self, Error> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|e| Error::custom(format!("failed to create dns resolver: {}", e)))?;
+
+        Ok(Self { resolver })
+    }
+}
+
+#[async_trait]
+impl DnsResolver for TrustDnsResolver {
+    async fn naptr_lookup(&self, domain: &str) -> Result<DnsAnswer<NaptrRecord>, Error> {
+        let lookup = match self
+            .resolver
+            .lookup(domain, RecordType::NAPTR, DnsRequestOptions::default())
+            .await
+        {
+            Ok(lookup) => lookup,
+            Err(err) => return empty_answer_from(domain, err),
+        };
+
+        let records = lookup
+            .record_iter()
+            .filter_map(|record| match record.rdata() {
+                RData::NAPTR(naptr) => Some(NaptrRecord {
+                    order: naptr.order(),
+                    preference: naptr.preference(),
+                    services: String::from_utf8_lossy(naptr.services()).into_owned(),
+                    replacement: naptr.replacement().to_string(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Ok(DnsAnswer {
+            records,
+            ttl: ttl_from(lookup.valid_until()),
+        })
+    }
+
+    async fn srv_lookup(&self, name: &str) -> Result<DnsAnswer<SrvRecord>, Error> {
+        let lookup = match self.resolver.srv_lookup(name).await {
+            Ok(lookup) => lookup,
+            Err(err) => return empty_answer_from(name, err),
+        };
+
+        let records = lookup
+            .iter()
+            .map(|srv| SrvRecord {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_string(),
+            })
+            .collect();
+
+        Ok(DnsAnswer {
+            records,
+            ttl: ttl_from(lookup.as_lookup().valid_until()),
+        })
+    }
+
+    async fn ip_lookup(&self, domain: &str) -> Result<DnsAnswer<IpAddr>, Error> {
+        let lookup = match self.resolver.lookup_ip(domain).await {
+            Ok(lookup) => lookup,
+            Err(err) => return empty_answer_from(domain, err),
+        };
+
+        Ok(DnsAnswer {
+            records: lookup.iter().collect(),
+            ttl: ttl_from(lookup.valid_until()),
+        })
+    }
+}
+
+impl std::fmt::Debug for TrustDnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrustDnsResolver").finish()
+    }
+}
+
+fn ttl_from(valid_until: Instant) -> Duration {
+    valid_until.saturating_duration_since(Instant::now())
+}
+
+//a name without records of the asked type is an answer too, only other errors are errors
+fn empty_answer_from<T>(name: &str, err: ResolveError) -> Result<DnsAnswer<T>, Error> {
+    match err.kind() {
+        ResolveErrorKind::NoRecordsFound { negative_ttl, .. } => Ok(DnsAnswer::empty(
+            negative_ttl
+                .map(|ttl| Duration::from_secs(ttl as u64))
+                .unwrap_or(NEGATIVE_TTL),
+        )),
+        _ => Err(Error::custom(format!("dns lookup of {} failed: {}", name, err))),
+    }
+}
@@ -0,0 +1,135 @@
+use common::{
+    rsip,
+    tokio::{sync::Mutex, time::Instant},
+};
+use models::transport::Flow;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    time::Duration,
+};
+
+//UAs behind a NAT send keepalives well within that (RFC5626 4.4), otherwise
+//the NAT binding, and with it the flow, is most likely gone anyway
+const FLOW_TIMEOUT: Duration = Duration::from_secs(180);
+//flows we stopped hearing from are thrown away every now and then, not on every message
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+//when we last heard from each flow, and which flow each registered contact is reachable over.
+//Stream flows also need their connection to be open, but datagram flows have nothing else to
+//tell whether they still work
+#[derive(Debug, Default)]
+pub struct Flows {
+    last_seen: Mutex<LastSeen>,
+    bindings: Mutex<Bindings>,
+}
+
+#[derive(Debug)]
+struct LastSeen {
+    by_flow: HashMap<Flow, Instant>,
+    last_pruned: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Bindings {
+    by_contact: HashMap<String, Binding>,
+    //registrations expire in any order, so the soonest one is kept on top
+    expirations: BinaryHeap<Reverse<(Instant, String)>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Binding {
+    flow: Flow,
+    expires_at: Instant,
+}
+
+impl Flows {
+    //only for traffic we accepted, anything else must not keep a flow alive
+    pub async fn touch(&self, flow: Flow) {
+        let mut last_seen = self.last_seen.lock().await;
+        last_seen.prune();
+
+        last_seen.by_flow.insert(flow, Instant::now());
+    }
+
+    pub async fn is_alive(&self, flow: &Flow) -> bool {
+        match self.last_seen.lock().await.by_flow.get(flow) {
+            Some(last_seen) => last_seen.elapsed() < FLOW_TIMEOUT,
+            None => false,
+        }
+    }
+
+    //filled by the registrar, so that finding the flow of a request target never has to
+    //leave memory
+    pub async fn bind(&self, contact: &rsip::Uri, flow: Flow, expires: Duration) {
+        let mut bindings = self.bindings.lock().await;
+        bindings.expire();
+
+        let key = contact.to_string();
+        let expires_at = Instant::now() + expires;
+        bindings
+            .by_contact
+            .insert(key.clone(), Binding { flow, expires_at });
+        bindings.expirations.push(Reverse((expires_at, key)));
+    }
+
+    pub async fn unbind(&self, contact: &rsip::Uri) {
+        self.bindings
+            .lock()
+            .await
+            .by_contact
+            .remove(&contact.to_string());
+    }
+
+    pub async fn bound_to(&self, contact: &rsip::Uri) -> Option<Flow> {
+        let mut bindings = self.bindings.lock().await;
+        bindings.expire();
+
+        bindings
+            .by_contact
+            .get(&contact.to_string())
+            .map(|binding| binding.flow)
+    }
+}
+
+impl Default for LastSeen {
+    fn default() -> Self {
+        Self {
+            by_flow: Default::default(),
+            last_pruned: Instant::now(),
+        }
+    }
+}
+
+impl LastSeen {
+    fn prune(&mut self) {
+        if self.last_pruned.elapsed() < PRUNE_INTERVAL {
+            return;
+        }
+
+        self.by_flow
+            .retain(|_, last_seen| last_seen.elapsed() < FLOW_TIMEOUT);
+        self.last_pruned = Instant::now();
+    }
+}
+
+impl Bindings {
+    //refreshed bindings leave their old expiration behind, which must not remove them
+    fn expire(&mut self) {
+        let now = Instant::now();
+        while let Some(Reverse((expires_at, _))) = self.expirations.peek() {
+            if *expires_at > now {
+                break;
+            }
+            if let Some(Reverse((expires_at, key))) = self.expirations.pop() {
+                let is_stale = matches!(
+                    self.by_contact.get(&key),
+                    Some(binding) if binding.expires_at <= expires_at
+                );
+                if is_stale {
+                    self.by_contact.remove(&key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{
+    loopback::{Loopback, LoopbackNetwork},
+    tcp::Tcp,
+    tls::Tls,
+    udp::Udp,
+    ws::Ws,
+};
+use crate::Error;
+use common::{
+    rsip,
+    tokio_rustls::{TlsAcceptor, TlsConnector},
+    ListenerConfig,
+};
+use models::{transport::TransportTuple, Handlers};
+use std::net::SocketAddr;
+
+//a socket bound to one of the configured listen addresses
+#[derive(Debug)]
+pub enum Listener {
+    Udp(Udp),
+    Tcp(Tcp),
+    Tls(Tls),
+    Ws(Ws),
+    Loopback(Loopback),
+}
+
+impl Listener {
+    pub fn transport(&self) -> rsip::Transport {
+        match self {
+            Self::Udp(_) => rsip::Transport::Udp,
+            Self::Tcp(_) => rsip::Transport::Tcp,
+            Self::Tls(_) => rsip::Transport::Tls,
+            Self::Ws(ws) => ws.transport(),
+            Self::Loopback(loopback) => loopback.transport(),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        match self {
+            Self::Udp(udp) => udp.local_addr(),
+            Self::Tcp(tcp) => tcp.local_addr(),
+            Self::Tls(tls) => tls.local_addr(),
+            Self::Ws(ws) => ws.local_addr(),
+            Self::Loopback(loopback) => loopback.local_addr(),
+        }
+    }
+
+    pub async fn has_connection(&self, peer: &SocketAddr) -> bool {
+        match self {
+            Self::Udp(_) => false,
+            Self::Tcp(tcp) => tcp.has_connection(peer).await,
+            Self::Tls(tls) => tls.has_connection(peer).await,
+            Self::Ws(ws) => ws.has_connection(peer).await,
+            Self::Loopback(loopback) => loopback.has_connection(peer),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        tuple: TransportTuple,
+        server_name: Option<String>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Udp(udp) => udp.send(tuple).await,
+            Self::Tcp(tcp) => tcp.send(tuple).await,
+            Self::Tls(tls) => tls.send(tuple, server_name).await,
+            Self::Ws(ws) => ws.send(tuple).await,
+            Self::Loopback(loopback) => loopback.send(tuple).await,
+        }
+    }
+
+    //a listener on an unspecified address (0.0.0.0) matches any IP of its family
+    fn matches_sent_by(&self, sent_by: &rsip::HostWithPort) -> bool {
+        let local_addr = self.local_addr();
+        let port: u16 = sent_by
+            .port
+            .map(|port| *port.value())
+            .unwrap_or_else(|| common::default_port_for(self.transport()));
+
+        let same_host = match &sent_by.host {
+            rsip::Host::IpAddr(ip_addr) => {
+                local_addr.ip() == *ip_addr
+                    || (local_addr.ip().is_unspecified()
+                        && local_addr.is_ipv4() == ip_addr.is_ipv4())
+            }
+            rsip::Host::Domain(_) => true,
+        };
+
+        same_host && local_addr.port() == port
+    }
+
+    fn can_reach(&self, peer: &SocketAddr) -> bool {
+        self.local_addr().is_ipv4() == peer.is_ipv4()
+    }
+}
+
+#[derive(Debug)]
+pub struct Listeners(Vec<Listener>);
+
+impl Listeners {
+    pub fn new(handlers: Handlers, configs: &[ListenerConfig]) -> Result<Self, Error> {
+        let tls_config = match &common::CONFIG.tls {
+            Some(tls_config) => Some(super::tls::tls_config_from(tls_config)?),
+            None => None,
+        };
+
+        let mut listeners = vec![];
+        for config in configs {
+            match listener_from(handlers.clone(), config, tls_config.as_ref())? {
+                Some(listener) => listeners.push(listener),
+                None => common::log::warn!(
+                    "no tls certificate configured, skipping {} listener on {}",
+                    config.transport,
+                    config.addr
+                ),
+            }
+        }
+
+        Ok(Self(listeners))
+    }
+
+    //listeners on an in-process network instead of real sockets, mostly for tests
+    pub fn loopback(
+        handlers: Handlers,
+        network: LoopbackNetwork,
+        configs: &[ListenerConfig],
+    ) -> Result<Self, Error> {
+        let listeners = configs
+            .iter()
+            .map(|config| {
+                Loopback::new(handlers.clone(), network.clone(), config.addr, config.transport)
+                    .map(Listener::Loopback)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self(listeners))
+    }
+
+    pub async fn has_connection(&self, transport: rsip::Transport, peer: &SocketAddr) -> bool {
+        for listener in self.0.iter().filter(|l| l.transport() == transport) {
+            if listener.has_connection(peer).await {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn supports(&self, transport: rsip::Transport) -> bool {
+        self.0.iter().any(|listener| listener.transport() == transport)
+    }
+
+    //stream based transports must reuse an existing connection with the peer, otherwise
+    //we pick the listener our Via points to, as long as it can reach the peer's address family.
+    //Returns the local address of the listener the message went out from
+    pub async fn send(
+        &self,
+        tuple: TransportTuple,
+        sent_by: Option<rsip::HostWithPort>,
+        server_name: Option<String>,
+    ) -> Result<SocketAddr, Error> {
+        let listener = self.find(&tuple, sent_by).await.ok_or_else(|| {
+            Error::custom(format!(
+                "no {} listener can reach {}",
+                tuple.transport, tuple.peer
+            ))
+        })?;
+
+        listener.send(tuple, server_name).await?;
+
+        Ok(listener.local_addr())
+    }
+
+    //the local address a message from that peer most likely arrived on
+    pub fn local_addr_for(
+        &self,
+        transport: rsip::Transport,
+        peer: &SocketAddr,
+    ) -> Option<SocketAddr> {
+        self.0
+            .iter()
+            .find(|listener| listener.transport() == transport && listener.can_reach(peer))
+            .map(Listener::local_addr)
+    }
+
+    async fn find(
+        &self,
+        tuple: &TransportTuple,
+        sent_by: Option<rsip::HostWithPort>,
+    ) -> Option<&Listener> {
+        let candidates = self
+            .0
+            .iter()
+            .filter(|listener| listener.transport() == tuple.transport)
+            .filter(|listener| listener.can_reach(&tuple.peer))
+            .collect::<Vec<_>>();
+
+        for listener in candidates.iter() {
+            if listener.has_connection(&tuple.peer).await {
+                return Some(*listener);
+            }
+        }
+
+        sent_by
+            .and_then(|sent_by| {
+                candidates
+                    .iter()
+                    .find(|listener| listener.matches_sent_by(&sent_by))
+            })
+            .or_else(|| candidates.first())
+            .copied()
+    }
+}
+
+fn listener_from(
+    handlers: Handlers,
+    config: &ListenerConfig,
+    tls_config: Option<&(TlsAcceptor, TlsConnector)>,
+) -> Result<Option<Listener>, Error> {
+    let listener = match (config.transport, tls_config) {
+        (rsip::Transport::Udp, _) => Listener::Udp(Udp::new(
+            handlers,
+            super::udp::create_socket(config.addr)?,
+        )?),
+        (rsip::Transport::Tcp, _) => Listener::Tcp(Tcp::new(
+            handlers,
+            super::tcp::create_listener(config.addr)?,
+        )?),
+        (rsip::Transport::Ws, _) => Listener::Ws(Ws::new(
+            handlers,
+            super::tcp::create_listener(config.addr)?,
+        )?),
+        (rsip::Transport::Tls, Some((acceptor, connector))) => Listener::Tls(Tls::new(
+            handlers,
+            super::tcp::create_listener(config.addr)?,
+            acceptor.clone(),
+            connector.clone(),
+        )?),
+        (rsip::Transport::Wss, Some((acceptor, _))) => Listener::Ws(Ws::secure(
+            handlers,
+            super::tcp::create_listener(config.addr)?,
+            acceptor.clone(),
+        )?),
+        (rsip::Transport::Tls, None) | (rsip::Transport::Wss, None) => return Ok(None),
+        (transport, _) => {
+            return Err(Error::custom(format!(
+                "transport {} is not supported",
+                transport
+            )))
+        }
+    };
+
+    Ok(Some(listener))
+}
@@ -0,0 +1,132 @@
+use crate::Error;
+use common::{
+    rsip,
+    tokio::{self, sync::mpsc},
+};
+use models::{transport::TransportTuple, Handlers, ResultExt};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+//same as a socket receive buffer: when it's full, new messages are dropped
+const INBOX_SIZE: usize = 100;
+
+//an in-process network: messages sent to an address are handed over to the loopback
+//listener bound to it, so that several elements can talk inside a single runtime
+//without ever touching a real socket
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackNetwork {
+    inboxes: Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<TransportTuple>>>>,
+}
+
+impl LoopbackNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_bound(&self, addr: &SocketAddr) -> bool {
+        self.inboxes().contains_key(addr)
+    }
+
+    fn bind(&self, addr: SocketAddr, inbox: mpsc::Sender<TransportTuple>) -> Result<(), Error> {
+        let mut inboxes = self.inboxes();
+        if inboxes.contains_key(&addr) {
+            return Err(Error::custom(format!("loopback address {} is already in use", addr)));
+        }
+        inboxes.insert(addr, inbox);
+
+        Ok(())
+    }
+
+    fn unbind(&self, addr: &SocketAddr) {
+        self.inboxes().remove(addr);
+    }
+
+    fn deliver(&self, to: SocketAddr, tuple: TransportTuple) -> Result<(), Error> {
+        let inbox = self
+            .inboxes()
+            .get(&to)
+            .cloned()
+            .ok_or_else(|| Error::custom(format!("nothing is listening on loopback {}", to)))?;
+
+        if inbox.try_send(tuple).is_err() {
+            common::log::warn!("loopback inbox of {} is full, dropping message", to);
+        }
+
+        Ok(())
+    }
+
+    fn inboxes(&self) -> std::sync::MutexGuard<HashMap<SocketAddr, mpsc::Sender<TransportTuple>>> {
+        self.inboxes.lock().expect("loopback network lock poisoned")
+    }
+}
+
+//a listener of the loopback network that behaves like the given transport, so that
+//Vias, flows and listener selection work exactly like on a real socket
+#[derive(Debug)]
+pub struct Loopback {
+    local_addr: SocketAddr,
+    transport: rsip::Transport,
+    network: LoopbackNetwork,
+}
+
+impl Loopback {
+    pub fn new(
+        handlers: Handlers,
+        network: LoopbackNetwork,
+        local_addr: SocketAddr,
+        transport: rsip::Transport,
+    ) -> Result<Self, Error> {
+        let (tx, mut rx) = mpsc::channel::<TransportTuple>(INBOX_SIZE);
+        network.bind(local_addr, tx)?;
+        common::log::debug!("starting loopback {} listener on {}", transport, local_addr);
+
+        tokio::spawn(async move {
+            while let Some(tuple) = rx.recv().await {
+                handlers
+                    .transport
+                    .process(tuple)
+                    .await
+                    .log_error("failed to pass incoming loopback message to transport");
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            transport,
+            network,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn transport(&self) -> rsip::Transport {
+        self.transport
+    }
+
+    //there are no real connections, but any bound peer is as good as a connected one
+    pub fn has_connection(&self, peer: &SocketAddr) -> bool {
+        self.network.is_bound(peer)
+    }
+
+    pub async fn send(&self, tuple: TransportTuple) -> Result<(), Error> {
+        self.network.deliver(
+            tuple.peer,
+            TransportTuple {
+                bytes: tuple.bytes,
+                peer: self.local_addr,
+                transport: self.transport,
+            },
+        )
+    }
+}
+
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        self.network.unbind(&self.local_addr);
+    }
+}
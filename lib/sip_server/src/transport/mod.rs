@@ -1,16 +1,34 @@
-//pub mod dns_lookup;
+pub mod capture;
+pub mod dns;
+pub mod flows;
+pub mod listeners;
+pub mod loopback;
 pub mod processor;
+pub mod rate_limiter;
+pub mod stream;
+pub mod tcp;
+pub mod tls;
 #[allow(clippy::module_inception)]
 pub mod transport;
 pub mod uac;
 pub mod uas;
+pub mod udp;
+pub mod validation;
+pub mod ws;
 
+pub use dns::{DefaultDnsLookup, StaticDnsLookup};
+pub use listeners::Listeners;
+pub use loopback::LoopbackNetwork;
 pub use processor::DefaultProcessor;
+pub use rate_limiter::{Rate, RateLimitingProcessor, RateLimits};
 pub use transport::Transport;
 
 use crate::Error;
 use common::{async_trait::async_trait, rsip};
-use models::transport::{RequestMsg, ResponseMsg, TransportMsg};
+use models::{
+    transaction::TransactionId,
+    transport::{RequestMsg, ResponseMsg, TransportMsg},
+};
 
 #[async_trait]
 pub trait DnsLookup: Send + Sync + 'static {
@@ -26,6 +44,16 @@ pub trait DnsLookup: Send + Sync + 'static {
             }
         }
     }
+
+    //the next target of a transaction after the current one failed (RFC3263 4.3), bound to
+    //the transaction of the given message, which might be a new one
+    async fn failover(
+        &self,
+        _previous: &TransactionId,
+        _message: rsip::SipMessage,
+    ) -> Result<Option<TransportMsg>, Error> {
+        Ok(None)
+    }
 }
 
 #[async_trait]
@@ -65,4 +93,10 @@ pub trait TransportProcessor: Send + Sync + 'static {
             transport,
         }: ResponseMsg,
     ) -> Result<Option<ResponseMsg>, Error>;
+
+    //asked instead of process_incoming_request for requests that are about to be rejected
+    //as malformed, so that peers that wouldn't get through are not answered either
+    async fn admits_incoming_request(&self, _msg: &RequestMsg) -> bool {
+        true
+    }
 }
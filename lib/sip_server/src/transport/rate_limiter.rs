@@ -0,0 +1,218 @@
+use super::{DefaultProcessor, TransportProcessor};
+use crate::Error;
+use common::{
+    async_trait::async_trait,
+    rsip,
+    tokio::{sync::Mutex, time::Instant},
+};
+use models::transport::{RequestMsg, ResponseMsg};
+use std::{collections::HashMap, net::IpAddr, time::Duration};
+
+pub use common::{Rate, RateLimits};
+
+//idle buckets are refilled anyway, so every now and then they are thrown away
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+//drops incoming traffic of denied networks, of banned IPs, and requests over the limits,
+//before handing whatever is left to the wrapped processor
+#[derive(Debug)]
+pub struct RateLimitingProcessor<P: TransportProcessor = DefaultProcessor> {
+    processor: P,
+    limits: RateLimits,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    ip_buckets: HashMap<IpAddr, TokenBucket>,
+    //keyed by the index of the limit in per_method
+    method_buckets: HashMap<(IpAddr, usize), TokenBucket>,
+    bans: HashMap<IpAddr, Instant>,
+    last_pruned: Instant,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl<P: TransportProcessor> RateLimitingProcessor<P> {
+    pub fn new(processor: P, limits: RateLimits) -> Self {
+        Self {
+            processor,
+            limits,
+            state: Mutex::new(State {
+                ip_buckets: Default::default(),
+                method_buckets: Default::default(),
+                bans: Default::default(),
+                last_pruned: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn ban(&self, ip_addr: IpAddr, duration: Duration) {
+        common::log::warn!("banning {} for {:?}", ip_addr, duration);
+        self.state
+            .lock()
+            .await
+            .bans
+            .insert(ip_addr, Instant::now() + duration);
+    }
+
+    pub async fn unban(&self, ip_addr: &IpAddr) {
+        self.state.lock().await.bans.remove(ip_addr);
+    }
+
+    pub async fn is_banned(&self, ip_addr: &IpAddr) -> bool {
+        match self.state.lock().await.bans.get(ip_addr) {
+            Some(banned_until) => *banned_until > Instant::now(),
+            None => false,
+        }
+    }
+
+    fn is_allowed(&self, ip_addr: &IpAddr) -> bool {
+        self.limits.allow.iter().any(|network| network.contains(*ip_addr))
+    }
+
+    fn is_denied(&self, ip_addr: &IpAddr) -> bool {
+        self.limits.deny.iter().any(|network| network.contains(*ip_addr))
+    }
+
+    //whether anything from that IP should be looked at all
+    async fn accepts(&self, ip_addr: &IpAddr) -> bool {
+        if self.is_allowed(ip_addr) {
+            return true;
+        }
+
+        !self.is_denied(ip_addr) && !self.is_banned(ip_addr).await
+    }
+
+    //takes a token from every bucket the request falls in, banning the IP if any is empty
+    async fn admits(&self, ip_addr: IpAddr, method: &rsip::Method) -> bool {
+        if self.is_allowed(&ip_addr) {
+            return true;
+        }
+
+        let mut state = self.state.lock().await;
+        state.prune(&self.limits);
+
+        let mut admitted = true;
+        if let Some(rate) = &self.limits.per_ip {
+            admitted &= state
+                .ip_buckets
+                .entry(ip_addr)
+                .or_insert_with(|| TokenBucket::full(rate))
+                .take(rate);
+        }
+        if let Some(index) = self.limits.method_limit_index(method) {
+            let rate = &self.limits.per_method[index].1;
+            admitted &= state
+                .method_buckets
+                .entry((ip_addr, index))
+                .or_insert_with(|| TokenBucket::full(rate))
+                .take(rate);
+        }
+
+        if !admitted {
+            if let Some(ban_duration) = self.limits.ban_duration {
+                common::log::warn!("{} went over its rate limits, banning it", ip_addr);
+                state.bans.insert(ip_addr, Instant::now() + ban_duration);
+            }
+        }
+
+        admitted
+    }
+}
+
+impl Default for RateLimitingProcessor<DefaultProcessor> {
+    fn default() -> Self {
+        Self::new(DefaultProcessor::default(), common::CONFIG.rate_limits.clone())
+    }
+}
+
+#[async_trait]
+impl<P: TransportProcessor> TransportProcessor for RateLimitingProcessor<P> {
+    async fn process_outgoing_request(&self, msg: RequestMsg) -> Result<Option<RequestMsg>, Error> {
+        self.processor.process_outgoing_request(msg).await
+    }
+
+    async fn process_incoming_response(
+        &self,
+        msg: ResponseMsg,
+    ) -> Result<Option<ResponseMsg>, Error> {
+        if !self.accepts(&msg.peer.ip()).await {
+            return Ok(None);
+        }
+
+        self.processor.process_incoming_response(msg).await
+    }
+
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<Option<RequestMsg>, Error> {
+        if !self.admits_incoming_request(&msg).await {
+            common::log::debug!("dropping {} from {}", msg.sip_request.method, msg.peer);
+            return Ok(None);
+        }
+
+        self.processor.process_incoming_request(msg).await
+    }
+
+    async fn process_outgoing_response(
+        &self,
+        msg: ResponseMsg,
+    ) -> Result<Option<ResponseMsg>, Error> {
+        self.processor.process_outgoing_response(msg).await
+    }
+
+    //malformed requests count against the limits too, otherwise they would be a free way
+    //to make us send responses
+    async fn admits_incoming_request(&self, msg: &RequestMsg) -> bool {
+        let ip_addr = msg.peer.ip();
+
+        self.accepts(&ip_addr).await && self.admits(ip_addr, &msg.sip_request.method).await
+    }
+}
+
+impl State {
+    fn prune(&mut self, limits: &RateLimits) {
+        if self.last_pruned.elapsed() < PRUNE_INTERVAL {
+            return;
+        }
+
+        let now = Instant::now();
+        self.bans.retain(|_, banned_until| *banned_until > now);
+        if let Some(rate) = &limits.per_ip {
+            self.ip_buckets
+                .retain(|_, bucket| bucket.last_refill.elapsed() < rate.refill_time());
+        }
+        self.method_buckets
+            .retain(|(_, index), bucket| match limits.per_method.get(*index) {
+                Some((_, rate)) => bucket.last_refill.elapsed() < rate.refill_time(),
+                None => false,
+            });
+        self.last_pruned = now;
+    }
+}
+
+impl TokenBucket {
+    fn full(rate: &Rate) -> Self {
+        Self {
+            tokens: rate.burst as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, rate: &Rate) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second).min(rate.burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
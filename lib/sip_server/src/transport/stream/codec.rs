@@ -0,0 +1,96 @@
+use crate::Error;
+use common::{
+    bytes::{Buf, Bytes, BytesMut},
+    tokio_util::codec::{Decoder, Encoder},
+};
+
+//a single SIP message should never go over that, anything bigger is probably garbage
+//or someone trying to exhaust our memory
+const MAX_MESSAGE_SIZE: usize = 65535;
+const HEADERS_DELIMITER: &[u8] = b"\r\n\r\n";
+const CRLF: &[u8] = b"\r\n";
+//RFC5626 4.4.1
+const KEEPALIVE_PING: &[u8] = b"\r\n\r\n";
+
+//splits a byte stream into SIP messages using the Content-Length header
+//as described in section 18.3 of RFC3261
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SipCodec;
+
+impl Decoder for SipCodec {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        //keepalive pings are passed on as they are, so that they can be answered
+        while src.starts_with(CRLF) {
+            if src.starts_with(KEEPALIVE_PING) {
+                return Ok(Some(src.split_to(KEEPALIVE_PING.len()).freeze()));
+            }
+            //could be the first half of a ping
+            if src.len() == CRLF.len() {
+                return Ok(None);
+            }
+            src.advance(CRLF.len());
+        }
+
+        let headers_end = match find_headers_end(src) {
+            Some(headers_end) => headers_end,
+            None if src.len() > MAX_MESSAGE_SIZE => {
+                return Err(Error::custom("stream message headers are too large"))
+            }
+            None => return Ok(None),
+        };
+
+        let message_len = headers_end + content_length_from(&src[..headers_end])?;
+        if message_len > MAX_MESSAGE_SIZE {
+            return Err(Error::custom(format!(
+                "stream message is too large: {} bytes",
+                message_len
+            )));
+        }
+
+        if src.len() < message_len {
+            src.reserve(message_len - src.len());
+            return Ok(None);
+        }
+
+        Ok(Some(src.split_to(message_len).freeze()))
+    }
+}
+
+impl Encoder<Bytes> for SipCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&item);
+
+        Ok(())
+    }
+}
+
+//returns the index right after the empty line that separates headers from body
+fn find_headers_end(src: &[u8]) -> Option<usize> {
+    src.windows(HEADERS_DELIMITER.len())
+        .position(|window| window == HEADERS_DELIMITER)
+        .map(|position| position + HEADERS_DELIMITER.len())
+}
+
+fn content_length_from(headers: &[u8]) -> Result<usize, Error> {
+    let headers = std::str::from_utf8(headers)
+        .map_err(|e| Error::custom(format!("stream message headers are not utf8: {}", e)))?;
+
+    headers
+        .split("\r\n")
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            Some((parts.next()?.trim(), parts.next()?.trim()))
+        })
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length") || *name == "l")
+        .map(|(_, value)| {
+            value
+                .parse::<usize>()
+                .map_err(|e| Error::custom(format!("invalid Content-Length {}: {}", value, e)))
+        })
+        .unwrap_or_else(|| Err(Error::custom("missing Content-Length in stream message")))
+}
@@ -0,0 +1,129 @@
+use super::SipCodec;
+use crate::Error;
+use common::{
+    bytes::Bytes,
+    futures::{Sink, SinkExt, Stream},
+    futures_util::stream::StreamExt,
+    rsip,
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncWrite},
+        sync::{mpsc, Mutex},
+    },
+    tokio_util::codec::Framed,
+};
+use models::{transport::TransportTuple, Handlers, ResultExt};
+use std::{collections::HashMap, fmt::Display, net::SocketAddr, sync::Arc};
+
+pub type ConnectionTx = mpsc::Sender<Bytes>;
+
+//table of open connections of a stream based transport, keyed by the remote address
+#[derive(Debug)]
+pub struct Connections {
+    transport: rsip::Transport,
+    handlers: Handlers,
+    table: Mutex<HashMap<SocketAddr, ConnectionTx>>,
+}
+
+impl Connections {
+    pub fn new(transport: rsip::Transport, handlers: Handlers) -> Self {
+        Self {
+            transport,
+            handlers,
+            table: Default::default(),
+        }
+    }
+
+    pub fn transport(&self) -> rsip::Transport {
+        self.transport
+    }
+
+    //registers the connection and starts reading incoming messages from it
+    pub async fn add<S>(self: &Arc<Self>, stream: S, peer: SocketAddr) -> ConnectionTx
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let (sink, stream) = Framed::new(stream, SipCodec::default()).split();
+
+        self.add_framed(sink, stream, peer).await
+    }
+
+    //same as add, but for connections that already know how to split the byte stream
+    //into SIP messages (like websockets, where each frame is a message)
+    pub async fn add_framed<Si, St, E>(
+        self: &Arc<Self>,
+        mut sink: Si,
+        mut stream: St,
+        peer: SocketAddr,
+    ) -> ConnectionTx
+    where
+        Si: Sink<Bytes> + Send + Unpin + 'static,
+        Si::Error: Display,
+        St: Stream<Item = Result<Bytes, E>> + Send + Unpin + 'static,
+        E: Display,
+    {
+        let (tx, mut rx) = mpsc::channel::<Bytes>(10);
+
+        self.table.lock().await.insert(peer, tx.clone());
+        common::log::debug!("new {} connection with {}", self.transport, peer);
+
+        tokio::spawn(async move {
+            while let Some(bytes) = rx.recv().await {
+                if let Err(err) = sink.send(bytes).await {
+                    common::log::error!("failed to write to connection with {}: {}", peer, err);
+                    break;
+                }
+            }
+        });
+
+        let connections = self.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = stream.next().await {
+                match frame {
+                    Ok(bytes) => connections
+                        .handlers
+                        .transport
+                        .process(TransportTuple {
+                            bytes,
+                            peer,
+                            transport: connections.transport,
+                        })
+                        .await
+                        .log_error("failed to pass incoming stream message to transport"),
+                    Err(err) => {
+                        common::log::error!("closing connection with {}: {}", peer, err);
+                        break;
+                    }
+                }
+            }
+
+            connections.remove(&peer).await;
+        });
+
+        tx
+    }
+
+    //only the connection with that exact address will do: several UAs behind the same NAT
+    //(or several tabs of the same browser) share an IP, but never a port
+    pub async fn find(&self, peer: &SocketAddr) -> Option<ConnectionTx> {
+        self.table.lock().await.get(peer).cloned()
+    }
+
+    pub async fn remove(&self, peer: &SocketAddr) {
+        if self.table.lock().await.remove(peer).is_some() {
+            common::log::debug!("{} connection with {} closed", self.transport, peer);
+        }
+    }
+
+    pub async fn send(&self, connection: ConnectionTx, tuple: TransportTuple) -> Result<(), Error> {
+        if let Err(err) = connection.send(tuple.bytes).await {
+            self.remove(&tuple.peer).await;
+            return Err(Error::custom(format!(
+                "{} connection with {} is closed: {}",
+                self.transport, tuple.peer, err
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod codec;
+mod connections;
+
+pub use codec::SipCodec;
+pub use connections::{ConnectionTx, Connections};
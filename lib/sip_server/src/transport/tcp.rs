@@ -0,0 +1,97 @@
+use super::stream::{ConnectionTx, Connections};
+use crate::Error;
+use common::{
+    rsip,
+    tokio::{
+        self,
+        net::{TcpListener, TcpSocket, TcpStream},
+    },
+};
+use models::{transport::TransportTuple, Handlers};
+use std::{net::SocketAddr, sync::Arc};
+
+#[derive(Debug)]
+pub struct Tcp {
+    local_addr: SocketAddr,
+    connections: Arc<Connections>,
+}
+
+impl Tcp {
+    pub fn new(handlers: Handlers, listener: TcpListener) -> Result<Self, Error> {
+        let me = Self {
+            local_addr: listener.local_addr()?,
+            connections: Arc::new(Connections::new(rsip::Transport::Tcp, handlers)),
+        };
+
+        me.run(listener);
+
+        Ok(me)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub async fn has_connection(&self, peer: &SocketAddr) -> bool {
+        self.connections.find(peer).await.is_some()
+    }
+
+    fn run(&self, listener: TcpListener) {
+        let connections = self.connections.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        connections.add(stream, peer).await;
+                    }
+                    Err(err) => common::log::error!("failed to accept tcp connection: {}", err),
+                }
+            }
+        });
+    }
+
+    //reuses the connection the peer has opened with us, otherwise opens a new one
+    pub async fn send(&self, tuple: TransportTuple) -> Result<(), Error> {
+        let connection = match self.connections.find(&tuple.peer).await {
+            Some(connection) => connection,
+            None => self.connect(tuple.peer).await?,
+        };
+
+        self.connections.send(connection, tuple).await
+    }
+
+    async fn connect(&self, peer: SocketAddr) -> Result<ConnectionTx, Error> {
+        let stream = connect_from(self.local_addr, peer).await?;
+
+        Ok(self.connections.add(stream, peer).await)
+    }
+}
+
+//opens a connection from the IP of the given listener, so that the peer sees the same
+//address it would see in our Via
+pub async fn connect_from(local_addr: SocketAddr, peer: SocketAddr) -> Result<TcpStream, Error> {
+    let socket = match peer {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    socket.bind((local_addr.ip(), 0).into())?;
+
+    Ok(socket.connect(peer).await?)
+}
+
+pub fn create_listener(addr: SocketAddr) -> Result<TcpListener, Error> {
+    use common::socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    //IPv4 is handled by its own listener, so that both can bind the same port
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    common::log::debug!("starting tcp server listening in {}", addr);
+
+    Ok(TcpListener::from_std(socket.into())?)
+}
@@ -0,0 +1,183 @@
+use super::stream::{ConnectionTx, Connections};
+use crate::Error;
+use common::{
+    rsip::{self, prelude::*},
+    tokio::{self, net::TcpListener},
+    tokio_rustls::{
+        rustls::{
+            internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+            ClientConfig, NoClientAuth, PrivateKey, ServerConfig,
+        },
+        webpki::DNSNameRef,
+        TlsAcceptor, TlsConnector,
+    },
+    TlsConfig,
+};
+use models::{
+    transport::{TransportError, TransportTuple},
+    Handlers,
+};
+use std::{fs::File, io::BufReader, net::SocketAddr, sync::Arc};
+
+pub struct Tls {
+    local_addr: SocketAddr,
+    connections: Arc<Connections>,
+    connector: TlsConnector,
+}
+
+impl Tls {
+    pub fn new(
+        handlers: Handlers,
+        listener: TcpListener,
+        acceptor: TlsAcceptor,
+        connector: TlsConnector,
+    ) -> Result<Self, Error> {
+        let me = Self {
+            local_addr: listener.local_addr()?,
+            connections: Arc::new(Connections::new(rsip::Transport::Tls, handlers)),
+            connector,
+        };
+
+        me.run(listener, acceptor);
+
+        Ok(me)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub async fn has_connection(&self, peer: &SocketAddr) -> bool {
+        self.connections.find(peer).await.is_some()
+    }
+
+    fn run(&self, listener: TcpListener, acceptor: TlsAcceptor) {
+        let connections = self.connections.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let acceptor = acceptor.clone();
+                        let connections = connections.clone();
+                        //handshake shouldn't block the accept loop
+                        tokio::spawn(async move {
+                            match acceptor.accept(stream).await {
+                                Ok(stream) => {
+                                    connections.add(stream, peer).await;
+                                }
+                                Err(err) => common::log::warn!(
+                                    "tls handshake with {} failed: {}",
+                                    peer,
+                                    err
+                                ),
+                            }
+                        });
+                    }
+                    Err(err) => common::log::error!("failed to accept tls connection: {}", err),
+                }
+            }
+        });
+    }
+
+    //server_name is the domain the certificate of the peer is verified against,
+    //only needed when a new connection has to be opened
+    pub async fn send(
+        &self,
+        tuple: TransportTuple,
+        server_name: Option<String>,
+    ) -> Result<(), Error> {
+        let connection = match self.connections.find(&tuple.peer).await {
+            Some(connection) => connection,
+            None => self.connect(tuple.peer, server_name).await?,
+        };
+
+        self.connections.send(connection, tuple).await
+    }
+
+    async fn connect(
+        &self,
+        peer: SocketAddr,
+        server_name: Option<String>,
+    ) -> Result<ConnectionTx, Error> {
+        let server_name = server_name.ok_or_else(|| {
+            Error::custom(format!("can't open tls connection to {} without a domain", peer))
+        })?;
+        let domain = DNSNameRef::try_from_ascii_str(&server_name)
+            .map_err(|_| Error::custom(format!("invalid tls server name: {}", server_name)))?;
+
+        let stream = super::tcp::connect_from(self.local_addr, peer).await?;
+        let stream = self
+            .connector
+            .connect(domain, stream)
+            .await
+            .map_err(|err| TransportError::TlsHandshake(format!("{} with {}", err, peer)))?;
+
+        Ok(self.connections.add(stream, peer).await)
+    }
+}
+
+impl std::fmt::Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tls")
+            .field("local_addr", &self.local_addr)
+            .field("connections", &self.connections)
+            .finish()
+    }
+}
+
+//the domain that the certificate of the remote end should have. For requests this is the
+//host of the Request-URI, for responses the sent-by host of the topmost Via
+pub fn server_name_for(sip_message: &rsip::SipMessage) -> Option<String> {
+    let host = match sip_message {
+        rsip::SipMessage::Request(request) => request.uri.host().clone(),
+        rsip::SipMessage::Response(response) => {
+            response.via_header().ok()?.typed().ok()?.sent_by().host().clone()
+        }
+    };
+
+    match host {
+        rsip::Host::Domain(domain) => Some(domain.to_string()),
+        rsip::Host::IpAddr(_) => None,
+    }
+}
+
+pub fn tls_config_from(config: &TlsConfig) -> Result<(TlsAcceptor, TlsConnector), Error> {
+    let certs = certs(&mut BufReader::new(File::open(&config.cert_path)?))
+        .map_err(|_| Error::custom(format!("invalid tls certificate in {}", config.cert_path)))?;
+
+    let mut server_config = ServerConfig::new(NoClientAuth::new());
+    server_config
+        .set_single_cert(certs, private_key_from(&config.key_path)?)
+        .map_err(|e| Error::custom(format!("invalid tls certificate/key pair: {}", e)))?;
+
+    let mut client_config = ClientConfig::new();
+    match &config.ca_path {
+        Some(ca_path) => {
+            client_config
+                .root_store
+                .add_pem_file(&mut BufReader::new(File::open(ca_path)?))
+                .map_err(|_| Error::custom(format!("invalid tls CA file in {}", ca_path)))?;
+        }
+        None => client_config
+            .root_store
+            .add_server_trust_anchors(&common::webpki_roots::TLS_SERVER_ROOTS),
+    };
+
+    Ok((
+        TlsAcceptor::from(Arc::new(server_config)),
+        TlsConnector::from(Arc::new(client_config)),
+    ))
+}
+
+fn private_key_from(key_path: &str) -> Result<PrivateKey, Error> {
+    let invalid_key = || Error::custom(format!("invalid tls private key in {}", key_path));
+
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(key_path)?))
+        .map_err(|_| invalid_key())?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut BufReader::new(File::open(key_path)?))
+            .map_err(|_| invalid_key())?;
+    }
+
+    keys.into_iter().next().ok_or_else(invalid_key)
+}
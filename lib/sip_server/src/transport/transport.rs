@@ -1,29 +1,38 @@
-use super::{DnsLookup, TransportProcessor};
+use super::{
+    capture::{Capture, CapturedMessage},
+    flows::Flows,
+    listeners::Listeners,
+    DnsLookup, TransportProcessor,
+};
 
 use crate::Error;
-use std::{convert::TryInto, fmt::Debug, net::SocketAddr, sync::Arc};
+use std::{
+    convert::TryFrom,
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 
 use common::{
     bytes::Bytes,
-    futures::{
-        stream::{SplitSink, SplitStream},
-        SinkExt,
-    },
-    futures_util::stream::StreamExt,
-    rsip,
-    tokio::{self, net::UdpSocket, sync::Mutex},
-    tokio_util::codec::BytesCodec,
-    tokio_util::udp::UdpFramed,
+    rsip::{self, prelude::*},
+    tokio,
 };
 use models::{
     receivers::TrReceiver,
+    rsip_ext::HostExt,
     transport::TransportLayerMsg,
-    transport::{RequestMsg, ResponseMsg, TransportMsg, UdpTuple},
+    transport::{Flow, RequestMsg, ResponseMsg, TransportError, TransportMsg, TransportTuple},
     Handlers,
 };
 
-type UdpSink = SplitSink<UdpFramed<BytesCodec>, (Bytes, SocketAddr)>;
-type UdpStream = SplitStream<UdpFramed<BytesCodec>>;
+//RFC5626 4.4.1
+const KEEPALIVE_PING: &[u8] = b"\r\n\r\n";
+const KEEPALIVE_PONG: &[u8] = b"\r\n";
 
 #[derive(Debug)]
 pub struct Transport<P: TransportProcessor, D: DnsLookup> {
@@ -34,7 +43,10 @@ pub struct Transport<P: TransportProcessor, D: DnsLookup> {
 pub struct Inner<P: TransportProcessor, D: DnsLookup> {
     processor: P,
     dns_lookup: D,
-    udp_sink: Mutex<UdpSink>,
+    listeners: Listeners,
+    flows: Flows,
+    capture: Capture,
+    dropped_messages: AtomicUsize,
     handlers: Handlers,
 }
 
@@ -45,27 +57,47 @@ impl<P: TransportProcessor, D: DnsLookup> Transport<P, D> {
         dns_lookup: D,
         messages_rx: TrReceiver,
     ) -> Result<Self, Error> {
-        let (udp_sink, udp_stream) = create_socket()?;
+        let listeners = Listeners::new(handlers.clone(), &common::CONFIG.listeners)?;
+
+        Self::with_listeners(handlers, processor, dns_lookup, listeners, messages_rx)
+    }
+
+    //same as new, but over the given listeners instead of the configured ones, for
+    //instance loopback listeners that never touch the network
+    pub fn with_listeners(
+        handlers: Handlers,
+        processor: P,
+        dns_lookup: D,
+        listeners: Listeners,
+        messages_rx: TrReceiver,
+    ) -> Result<Self, Error> {
+        let capture = Capture::new(&common::CONFIG)?;
 
         let me = Self {
             inner: Arc::new(Inner {
                 processor,
                 dns_lookup,
-                udp_sink: Mutex::new(udp_sink),
+                listeners,
+                flows: Default::default(),
+                capture,
+                dropped_messages: Default::default(),
                 handlers,
             }),
         };
 
-        me.run(messages_rx, udp_stream);
+        me.run(messages_rx);
 
         Ok(me)
     }
 
-    fn run(&self, messages: TrReceiver, udp_stream: UdpStream) {
+    //incoming messages that were dropped because they were malformed beyond answering
+    pub fn dropped_messages(&self) -> usize {
+        self.inner.dropped_messages.load(Ordering::Relaxed)
+    }
+
+    fn run(&self, messages: TrReceiver) {
         let inner = self.inner.clone();
         tokio::spawn(async move { inner.run(messages).await });
-        let socket_inner = self.inner.clone();
-        tokio::spawn(async move { socket_inner.run_socket(udp_stream).await });
     }
 }
 
@@ -78,10 +110,33 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
         }
     }
 
-    async fn udp_send(&self, udp_tuple: UdpTuple) -> Result<(), Error> {
-        debug_message(udp_tuple.bytes.to_vec());
+    async fn send(&self, transport_msg: TransportMsg) -> Result<(), Error> {
+        let server_name = super::tls::server_name_for(&transport_msg.sip_message);
+        let sent_by = sent_by_for(&transport_msg.sip_message);
+        let transport_tuple: TransportTuple = transport_msg.into();
+        debug_message(transport_tuple.bytes.to_vec());
+
+        let (bytes, peer, transport) = (
+            transport_tuple.bytes.clone(),
+            transport_tuple.peer,
+            transport_tuple.transport,
+        );
+        let local_addr = self
+            .listeners
+            .send(transport_tuple, sent_by, server_name)
+            .await?;
+
+        self.capture
+            .capture(CapturedMessage {
+                bytes: &bytes,
+                src: local_addr,
+                dst: peer,
+                transport,
+                timestamp: SystemTime::now(),
+            })
+            .await;
 
-        Ok(self.udp_sink.lock().await.send(udp_tuple.into()).await?)
+        Ok(())
     }
 
     //TODO: here we don't spawn, could lead to deadlocks
@@ -89,19 +144,173 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
         match msg {
             TransportLayerMsg::Outgoing(msg) => self.receive_outgoing_message(msg).await?,
             TransportLayerMsg::Incoming(msg) => self.receive_incoming_message(msg).await?,
+            TransportLayerMsg::Failover(msg) => self.receive_failover(msg).await?,
+            TransportLayerMsg::Bind(contact, flow, expires) => {
+                self.flows.bind(&contact, flow, expires).await
+            }
+            TransportLayerMsg::Unbind(contact) => self.flows.unbind(&contact).await,
         };
 
         Ok(())
     }
 
     async fn receive_outgoing_message(&self, msg: rsip::SipMessage) -> Result<(), Error> {
+        if let Some(flow) = self.registered_flow_for(&msg).await {
+            let transport_msg = TransportMsg {
+                sip_message: msg.clone(),
+                peer: flow.peer,
+                transport: flow.transport,
+            };
+            self.report_resolved(&msg, &transport_msg).await?;
+            return self.send_with_failover(transport_msg).await;
+        }
+
+        let transport_msg = match self.dns_lookup.transport_msg_from(msg.clone()).await {
+            Ok(transport_msg) => transport_msg,
+            Err(err) => {
+                return self
+                    .report_transport_error(msg, TransportError::Dns(err.to_string()))
+                    .await
+            }
+        };
+
+        let transport_msg = self.with_suitable_transport(transport_msg)?;
+        self.report_resolved(&msg, &transport_msg).await?;
+        self.send_with_failover(transport_msg).await
+    }
+
+    //client transactions can only pick the timers of a network once they know the address
+    //their domain target resolved to, and only know whether to retransmit once they know
+    //the transport that was picked instead of the one their Via had
+    async fn report_resolved(
+        &self,
+        msg: &rsip::SipMessage,
+        transport_msg: &TransportMsg,
+    ) -> Result<(), Error> {
+        let request = match msg {
+            rsip::SipMessage::Request(request) => request,
+            rsip::SipMessage::Response(_) => return Ok(()),
+        };
+
+        if request.uri.host().ip_addr().is_none()
+            || request.via_header()?.typed()?.transport != transport_msg.transport
+        {
+            self.handlers
+                .transaction
+                .resolved(request.clone(), transport_msg.peer, transport_msg.transport)
+                .await?
+        }
+
+        Ok(())
+    }
+
+    //requests to a registered contact must go over the flow the UA registered on (RFC5626 5.3),
+    //since behind a NAT that's the only way to reach it
+    async fn registered_flow_for(&self, msg: &rsip::SipMessage) -> Option<Flow> {
+        let request = match msg {
+            rsip::SipMessage::Request(request) => request,
+            rsip::SipMessage::Response(_) => return None,
+        };
+
+        match self.flows.bound_to(&request.uri).await {
+            Some(flow) if self.is_alive(&flow).await => Some(flow),
+            Some(flow) => {
+                common::log::debug!("flow {} is gone, resolving {} instead", flow, request.uri);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn is_alive(&self, flow: &Flow) -> bool {
+        match flow.transport {
+            rsip::Transport::Udp => self.flows.is_alive(flow).await,
+            transport => self.listeners.has_connection(transport, &flow.peer).await,
+        }
+    }
+
+    //RFC3261 18.1.1: requests that could get fragmented over UDP must go over a congestion
+    //controlled transport instead, to the same address, with the Via updated accordingly
+    fn with_suitable_transport(&self, transport_msg: TransportMsg) -> Result<TransportMsg, Error> {
         let TransportMsg {
             sip_message,
             peer,
             transport,
-        } = self.dns_lookup.transport_msg_from(msg).await?;
+        } = transport_msg;
+
+        match sip_message {
+            rsip::SipMessage::Request(mut request)
+                if transport == rsip::Transport::Udp
+                    && request.to_string().len() > common::CONFIG.max_udp_request_size()
+                    && self.listeners.supports(rsip::Transport::Tcp) =>
+            {
+                super::uac::apply_via_transport(request.via_header_mut()?, rsip::Transport::Tcp)?;
+                common::log::debug!("request to {} is too large for udp, using tcp", peer);
+
+                Ok(TransportMsg {
+                    sip_message: request.into(),
+                    peer,
+                    transport: rsip::Transport::Tcp,
+                })
+            }
+            sip_message => Ok(TransportMsg {
+                sip_message,
+                peer,
+                transport,
+            }),
+        }
+    }
 
-        let msg: Option<TransportMsg> = match sip_message {
+    //on transport errors the next target should be tried (RFC3263 4.3), and only once
+    //there are no more targets left, or trying them is pointless, the upper layers are notified
+    async fn send_with_failover(&self, mut transport_msg: TransportMsg) -> Result<(), Error> {
+        loop {
+            let original_msg = transport_msg.sip_message.clone();
+
+            let error = match self.process_outgoing_message(transport_msg).await? {
+                Some(transport_msg) => match self.send(transport_msg).await {
+                    Ok(()) => return Ok(()),
+                    Err(err) => err,
+                },
+                None => return Ok(()),
+            };
+
+            let error = TransportError::from(&error);
+            let next_transport_msg = match original_msg.transaction_id()? {
+                Some(transaction_id) if error.should_fail_over() => {
+                    self.dns_lookup
+                        .failover(&transaction_id, original_msg.clone())
+                        .await?
+                }
+                _ => None,
+            };
+
+            match next_transport_msg {
+                Some(next_transport_msg) => {
+                    common::log::warn!(
+                        "{}, failing over to {}",
+                        error,
+                        next_transport_msg.peer
+                    );
+                    transport_msg = self.with_suitable_transport(next_transport_msg)?;
+                    self.report_resolved(&original_msg, &transport_msg).await?;
+                }
+                None => {
+                    return self.report_transport_error(original_msg, error).await
+                }
+            }
+        }
+    }
+
+    async fn process_outgoing_message(
+        &self,
+        TransportMsg {
+            sip_message,
+            peer,
+            transport,
+        }: TransportMsg,
+    ) -> Result<Option<TransportMsg>, Error> {
+        Ok(match sip_message {
             rsip::SipMessage::Request(request) => self
                 .processor
                 .process_outgoing_request((request, peer, transport).into())
@@ -112,20 +321,45 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
                 .process_outgoing_response((response, peer, transport).into())
                 .await?
                 .map(Into::into),
-        };
+        })
+    }
 
-        if let Some(transport_msg) = msg {
-            //TODO: optimize clone here
-            if let Err(err) = self.udp_send(transport_msg.clone().into()).await {
-                self.report_transport_error(transport_msg, format!("{:?}", err))
-                    .await?;
+    //the current target of a client transaction timed out, so the request is sent again
+    //to the next target, in a new transaction (RFC3263 4.3)
+    async fn receive_failover(&self, request: rsip::Request) -> Result<(), Error> {
+        let previous = request
+            .transaction_id()?
+            .ok_or_else(|| Error::custom("failover request without transaction id"))?;
+        let request = with_new_branch(request)?;
+
+        match self
+            .dns_lookup
+            .failover(&previous, request.clone().into())
+            .await?
+        {
+            Some(_) if request.method == rsip::Method::Invite => {
+                self.handlers.transaction.new_uac_invite(request).await?
             }
-        }
+            Some(_) => self.handlers.transaction.new_uac(request).await?,
+            None => {
+                self.handlers
+                    .tu
+                    .transport_error(
+                        request.into(),
+                        TransportError::Timeout("no more targets to fail over to".into()),
+                    )
+                    .await?
+            }
+        };
 
         Ok(())
     }
 
-    async fn report_transport_error(&self, msg: TransportMsg, error: String) -> Result<(), Error> {
+    async fn report_transport_error(
+        &self,
+        msg: rsip::SipMessage,
+        error: TransportError,
+    ) -> Result<(), Error> {
         let transaction_id = msg.transaction_id()?;
 
         match transaction_id {
@@ -136,51 +370,66 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
                     .has_transaction_for(transaction_id)
                     .await?
                 {
-                    self.handlers
-                        .transaction
-                        .transport_error(msg.sip_message, error)
-                        .await?;
+                    self.handlers.transaction.transport_error(msg, error).await?;
                 } else {
-                    self.handlers
-                        .tu
-                        .transport_error(msg.sip_message, error)
-                        .await?;
+                    self.handlers.tu.transport_error(msg, error).await?;
                 }
             }
             None => {
-                self.handlers
-                    .tu
-                    .transport_error(msg.sip_message, error)
-                    .await?;
+                self.handlers.tu.transport_error(msg, error).await?;
             }
         };
 
         Ok(())
     }
 
-    async fn receive_incoming_message(&self, udp_tuple: UdpTuple) -> Result<(), Error> {
-        let TransportMsg {
-            sip_message,
-            peer,
-            transport,
-        } = udp_tuple.try_into()?;
+    async fn receive_incoming_message(&self, transport_tuple: TransportTuple) -> Result<(), Error> {
+        if is_keepalive(&transport_tuple.bytes) {
+            self.flows
+                .touch((transport_tuple.transport, transport_tuple.peer).into())
+                .await;
+            return self.receive_keepalive(transport_tuple).await;
+        }
+
+        debug_message(transport_tuple.bytes.to_vec());
+        self.capture_incoming(&transport_tuple).await;
+
+        let (peer, transport) = (transport_tuple.peer, transport_tuple.transport);
+        let sip_message = match TransportMsg::try_from(transport_tuple) {
+            Ok(transport_msg) => transport_msg.sip_message,
+            Err(err) => {
+                self.drop_message(peer, format!("unparseable message: {}", err));
+                return Ok(());
+            }
+        };
 
         match sip_message {
             rsip::SipMessage::Request(request) => {
+                if let Err(reason) = super::validation::validate_request(&request) {
+                    return self.reject_request(request, peer, transport, reason).await;
+                }
+
                 if let Some(msg) = self
                     .processor
                     .process_incoming_request((request, peer, transport).into())
                     .await?
                 {
+                    self.flows.touch((transport, peer).into()).await;
                     self.process_incoming_request(msg).await?;
                 }
             }
             rsip::SipMessage::Response(response) => {
+                if let Err(reason) = super::validation::validate_response(&response) {
+                    self.drop_message(peer, reason);
+                    return Ok(());
+                }
+
                 if let Some(msg) = self
                     .processor
                     .process_incoming_response((response, peer, transport).into())
                     .await?
                 {
+                    self.flows.touch((transport, peer).into()).await;
                     self.process_incoming_response(msg).await?;
                 }
             }
@@ -189,8 +438,106 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
         Ok(())
     }
 
+    async fn capture_incoming(&self, transport_tuple: &TransportTuple) {
+        let local_addr = self
+            .listeners
+            .local_addr_for(transport_tuple.transport, &transport_tuple.peer)
+            .unwrap_or_else(|| super::capture::unspecified_addr_like(&transport_tuple.peer));
+
+        self.capture
+            .capture(CapturedMessage {
+                bytes: &transport_tuple.bytes,
+                src: transport_tuple.peer,
+                dst: local_addr,
+                transport: transport_tuple.transport,
+                timestamp: SystemTime::now(),
+            })
+            .await;
+    }
+
+    //malformed requests are answered statelessly, straight to where they came from
+    async fn reject_request(
+        &self,
+        request: rsip::Request,
+        peer: SocketAddr,
+        transport: rsip::Transport,
+        reason: String,
+    ) -> Result<(), Error> {
+        let msg = RequestMsg::new(request, peer, transport);
+        if !self.processor.admits_incoming_request(&msg).await {
+            self.drop_message(peer, reason);
+            return Ok(());
+        }
+
+        let response = match super::validation::bad_request_for(&msg.sip_request, &reason) {
+            Some(response) => response,
+            None => {
+                self.drop_message(peer, reason);
+                return Ok(());
+            }
+        };
+
+        common::log::warn!("rejecting request from {}: {}", peer, reason);
+        //through the processor, like any other response we send
+        match self
+            .process_outgoing_message(TransportMsg {
+                sip_message: response.into(),
+                peer,
+                transport,
+            })
+            .await?
+        {
+            Some(transport_msg) => self.send(transport_msg).await,
+            None => Ok(()),
+        }
+    }
+
+    //messages that can't even be answered are just counted, so that a flood of
+    //garbage shows up somewhere without filling the logs
+    fn drop_message(&self, peer: SocketAddr, reason: String) {
+        let dropped = self.dropped_messages.fetch_add(1, Ordering::Relaxed) + 1;
+        common::log::debug!("dropping message from {} ({} so far): {}", peer, dropped, reason);
+    }
+
+    //a ping is answered with a pong over the same flow, a pong only keeps the flow alive
+    async fn receive_keepalive(&self, transport_tuple: TransportTuple) -> Result<(), Error> {
+        if transport_tuple.bytes != KEEPALIVE_PING {
+            return Ok(());
+        }
+
+        self.listeners
+            .send(
+                TransportTuple {
+                    bytes: Bytes::from_static(KEEPALIVE_PONG),
+                    ..transport_tuple
+                },
+                None,
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    //retransmissions (and ACKs to non-2xx responses) are absorbed by the server transaction
+    //that is already handling them, only new requests reach the TU
     async fn process_incoming_request(&self, request: RequestMsg) -> Result<(), Error> {
-        Ok(self.handlers.tu.process(request.sip_request.into()).await?)
+        if let Some(transaction_id) = request.transaction_id()? {
+            if self
+                .handlers
+                .transaction
+                .has_transaction_for(transaction_id)
+                .await?
+            {
+                return Ok(self
+                    .handlers
+                    .transaction
+                    .process(request.sip_request.into())
+                    .await?);
+            }
+        }
+
+        Ok(self.handlers.tu.process_request(request).await?)
     }
 
     async fn process_incoming_response(&self, response: ResponseMsg) -> Result<(), Error> {
@@ -225,46 +572,44 @@ impl<P: TransportProcessor, D: DnsLookup> Inner<P, D> {
 
         Ok(())
     }
+}
 
-    async fn run_socket(&self, mut udp_stream: UdpStream) {
-        loop {
-            match udp_stream.next().await {
-                Some(Ok((request, addr))) => {
-                    debug_message(request.clone().freeze().to_vec());
-
-                    match self
-                        .receive(UdpTuple::from((request.freeze(), addr)).into())
-                        .await
-                    {
-                        Ok(_) => (),
-                        Err(error) => {
-                            common::log::error!("failed to process incoming message: {:?}", error)
-                        }
-                    }
-                }
-                Some(Err(e)) => common::log::error!("{:?}", e),
-                None => common::log::error!("nothing here"),
-            }
-        }
-    }
+fn with_new_branch(mut request: rsip::Request) -> Result<rsip::Request, Error> {
+    use rsip::{param::Branch, Param};
+
+    let via_header = request.via_header_mut()?;
+    let mut typed_via_header = via_header.typed()?;
+    typed_via_header.params = typed_via_header
+        .params
+        .into_iter()
+        .map(|param| match param {
+            Param::Branch(_) => Param::Branch(Branch::new(format!(
+                "z9hG4bK{}",
+                common::uuid::Uuid::new_v4().to_simple()
+            ))),
+            param => param,
+        })
+        .collect();
+    via_header.replace(typed_via_header);
+
+    Ok(request)
 }
 
-fn create_socket() -> Result<(UdpSink, UdpStream), crate::Error> {
-    let socket = UdpSocket::from_std(std::net::UdpSocket::bind("0.0.0.0:5060")?)?;
-    common::log::debug!("starting udp server listening in port 5060");
-    let socket = UdpFramed::new(socket, BytesCodec::new());
-    Ok(socket.split())
+fn is_keepalive(bytes: &[u8]) -> bool {
+    bytes == KEEPALIVE_PING || bytes == KEEPALIVE_PONG
+}
+
+//only our own requests carry a Via pointing to one of our listeners
+fn sent_by_for(sip_message: &rsip::SipMessage) -> Option<rsip::HostWithPort> {
+    match sip_message {
+        rsip::SipMessage::Request(request) => {
+            Some(request.via_header().ok()?.typed().ok()?.sent_by().clone())
+        }
+        rsip::SipMessage::Response(_) => None,
+    }
 }
 
-#[allow(dead_code)]
+//full messages are only logged at trace level, captures are what production should use
 fn debug_message(bytes: Vec<u8>) {
-    let separator = "########################################################################";
-    println!(
-        "{}\n{}\n{}",
-        separator,
-        String::from_utf8_lossy(&bytes),
-        separator
-    );
-    //let message = String::from_utf8(bytes).expect("utf bytes to string");
-    //println!("{}\n{}\n{}", separator, message, separator);
+    common::log::trace!("{}", String::from_utf8_lossy(&bytes));
 }
@@ -1,22 +1,35 @@
 use crate::Error;
 use common::rsip::{self, prelude::*};
+use models::rsip_ext::{HostExt, ViaExt};
 use std::net::SocketAddr;
 
 //outgoing
 pub fn apply_request_defaults(
     mut request: rsip::Request,
     peer: SocketAddr,
-    _transport: rsip::Transport,
+    transport: rsip::Transport,
 ) -> Result<rsip::Request, Error> {
+    apply_via_transport(
+        request.via_header_mut().expect("via header is missing!"),
+        transport,
+    )?;
     apply_via_maddr_address(
         request.via_header_mut().expect("via header is missing!"),
         &peer,
     )?;
-    apply_via_ttl(
+    if transport == rsip::Transport::Udp {
+        apply_via_ttl(
+            request.via_header_mut().expect("via header is missing!"),
+            &peer,
+        )?;
+    }
+    apply_via_sent_by(
         request.via_header_mut().expect("via header is missing!"),
         &peer,
+        transport,
     )?;
-    apply_via_sent_by(request.via_header_mut().expect("via header is missing!"))?;
+    apply_via_rport(request.via_header_mut().expect("via header is missing!"))?;
+    apply_contact_host(&mut request)?;
 
     Ok(request)
 }
@@ -31,6 +44,23 @@ pub fn apply_response_defaults(
     Ok(response)
 }
 
+//the Via must reflect the transport the request is actually sent over
+pub fn apply_via_transport(
+    via_header: &mut rsip::headers::Via,
+    transport: rsip::Transport,
+) -> Result<(), Error> {
+    let typed_via_header = via_header.typed()?;
+
+    if typed_via_header.transport != transport {
+        via_header.replace(rsip::typed::Via {
+            transport,
+            ..typed_via_header
+        });
+    }
+
+    Ok(())
+}
+
 pub fn apply_via_maddr_address(
     via_header: &mut rsip::headers::Via,
     peer: &SocketAddr,
@@ -41,7 +71,9 @@ pub fn apply_via_maddr_address(
         via_header.replace(
             via_header
                 .typed()?
-                .with_param(Param::Maddr(Maddr::new(peer.ip().to_string()))),
+                .with_param(Param::Maddr(Maddr::new(
+                    rsip::Host::IpAddr(peer.ip()).bracketed().to_string(),
+                ))),
         );
     }
 
@@ -58,16 +90,54 @@ pub fn apply_via_ttl(via_header: &mut rsip::headers::Via, peer: &SocketAddr) ->
     Ok(())
 }
 
-pub fn apply_via_sent_by(via_header: &mut rsip::headers::Via) -> Result<(), Error> {
+//sent-by must be reachable by the peer, so it is the listener of the transport the request is
+//sent over, in the same IP family
+pub fn apply_via_sent_by(
+    via_header: &mut rsip::headers::Via,
+    peer: &SocketAddr,
+    transport: rsip::Transport,
+) -> Result<(), Error> {
+    let mut typed_via_header = via_header.typed()?;
+
+    typed_via_header.uri.host_with_port =
+        bracketed(common::CONFIG.sent_by_for(&peer.ip(), transport));
+    via_header.replace(typed_via_header);
+
+    Ok(())
+}
+
+//asks the server to send responses back to the port we sent the request from (RFC3581 3)
+pub fn apply_via_rport(via_header: &mut rsip::headers::Via) -> Result<(), Error> {
+    use rsip::{param::OtherParam, Param};
+
     let typed_via_header = via_header.typed()?;
+    if !typed_via_header.has_rport() {
+        via_header.replace(
+            typed_via_header.with_param(Param::Other(OtherParam::new("rport"), None)),
+        );
+    }
+
+    Ok(())
+}
 
-    let mut uri = typed_via_header.uri.clone();
-    uri.host_with_port = common::CONFIG.default_addr();
-    via_header.replace(typed_via_header.to_string());
+pub fn apply_contact_host(request: &mut rsip::Request) -> Result<(), Error> {
+    if let Ok(contact_header) = request.contact_header_mut() {
+        let mut typed_contact_header = contact_header.typed()?;
+        typed_contact_header.uri.host_with_port =
+            bracketed(typed_contact_header.uri.host_with_port);
+        contact_header.replace(typed_contact_header);
+    }
 
     Ok(())
 }
 
+fn bracketed(host_with_port: rsip::HostWithPort) -> rsip::HostWithPort {
+    rsip::HostWithPort {
+        host: host_with_port.host.bracketed(),
+        ..host_with_port
+    }
+}
+
 pub fn assert_sent_by_value(via_header: &rsip::headers::Via) -> Result<(), Error> {
     let typed_via_header = via_header.typed()?;
 
@@ -1,16 +1,23 @@
 use crate::Error;
 use common::rsip::{self, prelude::*};
+use models::rsip_ext::{TransportExt, ViaExt};
 use std::net::SocketAddr;
 
 //incoming
 pub fn apply_request_defaults(
     mut request: rsip::Request,
     peer: SocketAddr,
-    _transport: rsip::Transport,
+    transport: rsip::Transport,
 ) -> Result<rsip::Request, Error> {
     use super::uas::*;
 
+    assert_via_transport(request.via_header().expect("via header missing"), transport)?;
     apply_received_value(request.via_header_mut().expect("via header missing"), &peer)?;
+    apply_connection_rport(
+        request.via_header_mut().expect("via header missing"),
+        transport,
+    )?;
+    apply_rport_value(request.via_header_mut().expect("via header missing"), &peer)?;
     Ok(request)
 }
 
@@ -23,6 +30,8 @@ pub fn apply_response_defaults(
     response
 }
 
+//received is always set when sent-by is a domain, which covers the `.invalid` hosts that
+//websocket clients put in the Via (RFC7118 5.2), since these can never be resolved
 pub fn apply_received_value(
     via_header: &mut rsip::headers::Via,
     peer: &SocketAddr,
@@ -39,7 +48,7 @@ pub fn apply_received_value(
             },
             _,
         ) => via_header.replace(
-            typed_via_header.with_param(Param::Received(Received::new(peer.clone().to_string()))),
+            typed_via_header.with_param(Param::Received(Received::new(peer.ip().to_string()))),
         ),
         (
             HostWithPort {
@@ -48,7 +57,7 @@ pub fn apply_received_value(
             },
             _,
         ) if (listen_addr != peer.ip()) || (*port.value() != peer.port()) => via_header.replace(
-            typed_via_header.with_param(Param::Received(Received::new(peer.clone().to_string()))),
+            typed_via_header.with_param(Param::Received(Received::new(peer.ip().to_string()))),
         ),
         (
             HostWithPort {
@@ -57,10 +66,82 @@ pub fn apply_received_value(
             },
             _,
         ) if listen_addr != peer.ip() => via_header.replace(
-            typed_via_header.with_param(Param::Received(Received::new(peer.clone().to_string()))),
+            typed_via_header.with_param(Param::Received(Received::new(peer.ip().to_string()))),
         ),
         (_, _) => (),
     }
 
     Ok(())
 }
+
+//clients behind NAT ask with an empty rport for the source port we saw, and then
+//responses must go to received and rport (RFC3581 4)
+pub fn apply_rport_value(
+    via_header: &mut rsip::headers::Via,
+    peer: &SocketAddr,
+) -> Result<(), Error> {
+    use rsip::{
+        param::{OtherParamValue, Received},
+        Param,
+    };
+
+    let typed_via_header = via_header.typed()?;
+    if !typed_via_header.has_rport() || typed_via_header.rport_value().is_some() {
+        return Ok(());
+    }
+
+    let received_param = match typed_via_header.received_ip_addr() {
+        Some(_) => None,
+        None => Some(Param::Received(Received::new(peer.ip().to_string()))),
+    };
+    let mut params = typed_via_header
+        .params
+        .into_iter()
+        .map(|param| match param {
+            Param::Other(name, None) if name.value().eq_ignore_ascii_case("rport") => Param::Other(
+                name,
+                Some(OtherParamValue::new(peer.port().to_string())),
+            ),
+            param => param,
+        })
+        .collect::<Vec<_>>();
+    params.extend(received_param);
+
+    via_header.replace(rsip::typed::Via {
+        params,
+        ..typed_via_header
+    });
+
+    Ok(())
+}
+
+//responses must go back over the connection the request arrived on (RFC3261 18.2.2), but
+//only rport can tell its port apart from the sent-by one, so it is asked for on the client's
+//behalf. Connections are looked up by their exact address, since a NAT can hide many clients
+pub fn apply_connection_rport(
+    via_header: &mut rsip::headers::Via,
+    transport: rsip::Transport,
+) -> Result<(), Error> {
+    match transport {
+        rsip::Transport::Udp => Ok(()),
+        _ => super::uac::apply_via_rport(via_header),
+    }
+}
+
+//upper layers rely on the Via to know if the request arrived over TLS,
+//so a peer shouldn't be able to claim a secure transport over an insecure one
+pub fn assert_via_transport(
+    via_header: &rsip::headers::Via,
+    transport: rsip::Transport,
+) -> Result<(), Error> {
+    let typed_via_header = via_header.typed()?;
+
+    if typed_via_header.transport.is_secure() && !transport.is_secure() {
+        Err(Error::custom(format!(
+            "Via transport ({}) is secure but request arrived over {}",
+            typed_via_header.transport, transport
+        )))
+    } else {
+        Ok(())
+    }
+}
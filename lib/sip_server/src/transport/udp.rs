@@ -0,0 +1,72 @@
+use crate::Error;
+use common::{
+    bytes::Bytes,
+    futures::{stream::SplitSink, SinkExt},
+    futures_util::stream::StreamExt,
+    tokio::{self, net::UdpSocket, sync::Mutex},
+    tokio_util::{codec::BytesCodec, udp::UdpFramed},
+};
+use models::{
+    transport::{TransportTuple, UdpTuple},
+    Handlers, ResultExt,
+};
+use std::net::SocketAddr;
+
+type UdpSink = SplitSink<UdpFramed<BytesCodec>, (Bytes, SocketAddr)>;
+
+#[derive(Debug)]
+pub struct Udp {
+    local_addr: SocketAddr,
+    sink: Mutex<UdpSink>,
+}
+
+impl Udp {
+    pub fn new(handlers: Handlers, socket: UdpSocket) -> Result<Self, Error> {
+        let local_addr = socket.local_addr()?;
+        let (sink, mut stream) = UdpFramed::new(socket, BytesCodec::new()).split();
+
+        tokio::spawn(async move {
+            while let Some(frame) = stream.next().await {
+                match frame {
+                    Ok((bytes, peer)) => handlers
+                        .transport
+                        .process(UdpTuple {
+                            bytes: bytes.freeze(),
+                            peer,
+                        })
+                        .await
+                        .log_error("failed to pass incoming udp message to transport"),
+                    Err(err) => common::log::error!("udp socket {} error: {}", local_addr, err),
+                }
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            sink: Mutex::new(sink),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub async fn send(&self, tuple: TransportTuple) -> Result<(), Error> {
+        Ok(self.sink.lock().await.send((tuple.bytes, tuple.peer)).await?)
+    }
+}
+
+pub fn create_socket(addr: SocketAddr) -> Result<UdpSocket, Error> {
+    use common::socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    //IPv4 is handled by its own listener, so that both can bind the same port
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    common::log::debug!("starting udp server listening in {}", addr);
+
+    Ok(UdpSocket::from_std(socket.into())?)
+}
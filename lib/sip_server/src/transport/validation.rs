@@ -0,0 +1,101 @@
+use common::rsip::{self, headers::UntypedHeader, prelude::*};
+
+//RFC3261 8.2 and 16.3: a request missing any of these can't be processed by a UAS or a
+//proxy. Max-Forwards is not checked here, since its absence is tolerated (16.3 step 3)
+pub fn validate_request(request: &rsip::Request) -> Result<(), String> {
+    validate_common_headers(&request.headers)?;
+
+    let cseq_method = request
+        .cseq_header()
+        .map_err(|_| "missing CSeq header")?
+        .typed()
+        .map_err(|e| format!("invalid CSeq header: {}", e))?
+        .method;
+    if cseq_method != request.method {
+        return Err(format!(
+            "CSeq method {} does not match request method {}",
+            cseq_method, request.method
+        ));
+    }
+
+    Ok(())
+}
+
+//RFC3261 8.1.3: responses are matched with the same headers, nothing else is needed
+pub fn validate_response(response: &rsip::Response) -> Result<(), String> {
+    validate_common_headers(&response.headers)
+}
+
+fn validate_common_headers(headers: &rsip::Headers) -> Result<(), String> {
+    rsip::header_opt!(headers.iter(), rsip::Header::Via)
+        .ok_or("missing Via header")?
+        .typed()
+        .map_err(|e| format!("invalid Via header: {}", e))?;
+
+    rsip::header_opt!(headers.iter(), rsip::Header::From)
+        .ok_or("missing From header")?
+        .typed()
+        .map_err(|e| format!("invalid From header: {}", e))?;
+    rsip::header_opt!(headers.iter(), rsip::Header::To)
+        .ok_or("missing To header")?
+        .typed()
+        .map_err(|e| format!("invalid To header: {}", e))?;
+    rsip::header_opt!(headers.iter(), rsip::Header::CallId).ok_or("missing Call-ID header")?;
+    rsip::header_opt!(headers.iter(), rsip::Header::CSeq)
+        .ok_or("missing CSeq header")?
+        .typed()
+        .map_err(|e| format!("invalid CSeq header: {}", e))?;
+
+    Ok(())
+}
+
+//the 400 response to a malformed request (RFC3261 8.2.6), with the reason in a Warning
+//header (20.43). Without the headers that identify the transaction nobody could match the
+//response, and ACKs are never answered, so None is returned for these
+pub fn bad_request_for(request: &rsip::Request, reason: &str) -> Option<rsip::Response> {
+    use rsip::headers::{ContentLength, Server};
+
+    if request.method == rsip::Method::Ack {
+        return None;
+    }
+
+    let vias = request
+        .headers
+        .iter()
+        .filter(|header| matches!(header, rsip::Header::Via(_)))
+        .cloned()
+        .collect::<Vec<_>>();
+    if vias.is_empty() {
+        return None;
+    }
+    let call_id_header = request.call_id_header().ok()?;
+    let cseq_header = request.cseq_header().ok()?;
+    cseq_header.typed().ok()?;
+
+    let mut headers: rsip::Headers = Default::default();
+    for via in vias {
+        headers.push(via);
+    }
+    headers.push(call_id_header.clone().into());
+    headers.push(cseq_header.clone().into());
+    if let Ok(from_header) = request.from_header() {
+        headers.push(from_header.clone().into());
+    }
+    if let Ok(to_header) = request.to_header() {
+        match (to_header.tag(), to_header.typed()) {
+            (Ok(None), Ok(typed_to_header)) => {
+                headers.push(typed_to_header.with_tag(Default::default()).into())
+            }
+            _ => headers.push(to_header.clone().into()),
+        }
+    }
+    headers.push(crate::presets::warning_for(reason).into());
+    headers.push(ContentLength::default().into());
+    headers.push(Server::default().into());
+
+    Some(rsip::Response {
+        status_code: 400.into(),
+        headers,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,190 @@
+use super::stream::Connections;
+use crate::Error;
+use common::{
+    bytes::Bytes,
+    futures::{future, SinkExt},
+    futures_util::stream::StreamExt,
+    rsip,
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncWrite},
+        net::TcpListener,
+    },
+    tokio_rustls::TlsAcceptor,
+    tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::{
+            handshake::server::{ErrorResponse, Request, Response},
+            http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderValue, StatusCode},
+            Message,
+        },
+    },
+};
+use models::{transport::TransportTuple, Handlers};
+use std::{net::SocketAddr, sync::Arc};
+
+const SIP_SUBPROTOCOL: &str = "sip";
+
+//SIP over WebSocket (RFC7118). Only incoming connections are supported, since
+//the other side is usually a browser that can't accept connections anyway
+#[derive(Debug)]
+pub struct Ws {
+    local_addr: SocketAddr,
+    connections: Arc<Connections>,
+}
+
+impl Ws {
+    pub fn new(handlers: Handlers, listener: TcpListener) -> Result<Self, Error> {
+        Self::with_acceptor(handlers, listener, None)
+    }
+
+    //websockets over TLS (WSS)
+    pub fn secure(
+        handlers: Handlers,
+        listener: TcpListener,
+        acceptor: TlsAcceptor,
+    ) -> Result<Self, Error> {
+        Self::with_acceptor(handlers, listener, Some(acceptor))
+    }
+
+    fn with_acceptor(
+        handlers: Handlers,
+        listener: TcpListener,
+        acceptor: Option<TlsAcceptor>,
+    ) -> Result<Self, Error> {
+        let transport = match acceptor {
+            Some(_) => rsip::Transport::Wss,
+            None => rsip::Transport::Ws,
+        };
+        let me = Self {
+            local_addr: listener.local_addr()?,
+            connections: Arc::new(Connections::new(transport, handlers)),
+        };
+
+        me.run(listener, acceptor);
+
+        Ok(me)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn transport(&self) -> rsip::Transport {
+        self.connections.transport()
+    }
+
+    pub async fn has_connection(&self, peer: &SocketAddr) -> bool {
+        self.connections.find(peer).await.is_some()
+    }
+
+    fn run(&self, listener: TcpListener, acceptor: Option<TlsAcceptor>) {
+        let connections = self.connections.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let acceptor = acceptor.clone();
+                        let connections = connections.clone();
+                        tokio::spawn(async move {
+                            let result = match acceptor {
+                                Some(acceptor) => match acceptor.accept(stream).await {
+                                    Ok(stream) => accept(connections, stream, peer).await,
+                                    Err(err) => Err(err.into()),
+                                },
+                                None => accept(connections, stream, peer).await,
+                            };
+
+                            if let Err(err) = result {
+                                common::log::warn!(
+                                    "websocket handshake with {} failed: {}",
+                                    peer,
+                                    err
+                                )
+                            }
+                        });
+                    }
+                    Err(err) => {
+                        common::log::error!("failed to accept websocket connection: {}", err)
+                    }
+                }
+            }
+        });
+    }
+
+    //messages can only go through a websocket the peer has already opened
+    pub async fn send(&self, tuple: TransportTuple) -> Result<(), Error> {
+        match self.connections.find(&tuple.peer).await {
+            Some(connection) => self.connections.send(connection, tuple).await,
+            None => Err(Error::custom(format!(
+                "no {} connection found for {}",
+                self.connections.transport(),
+                tuple.peer
+            ))),
+        }
+    }
+}
+
+async fn accept<S>(connections: Arc<Connections>, stream: S, peer: SocketAddr) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (sink, stream) = accept_hdr_async(stream, negotiate_subprotocol)
+        .await
+        .map_err(|e| Error::custom(e.to_string()))?
+        .split();
+
+    //each websocket message carries exactly one SIP message, in a text or a binary frame
+    let sink = sink.with(|bytes: Bytes| {
+        future::ok::<_, common::tokio_tungstenite::tungstenite::Error>(message_from(bytes))
+    });
+    let stream = stream.filter_map(|message| {
+        future::ready(match message {
+            Ok(Message::Text(text)) => Some(Ok(Bytes::from(text))),
+            Ok(Message::Binary(binary)) => Some(Ok(Bytes::from(binary))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+    });
+
+    connections
+        .add_framed(Box::pin(sink), Box::pin(stream), peer)
+        .await;
+
+    Ok(())
+}
+
+//text frames must be valid UTF-8, so bodies that aren't have to go in a binary frame (RFC7118 5.1)
+pub fn message_from(bytes: Bytes) -> Message {
+    match String::from_utf8(bytes.to_vec()) {
+        Ok(text) => Message::Text(text),
+        Err(err) => Message::Binary(err.into_bytes()),
+    }
+}
+
+//RFC7118 4.1, the client must ask for the sip subprotocol, and we must echo it back
+fn negotiate_subprotocol(
+    request: &Request,
+    mut response: Response,
+) -> Result<Response, ErrorResponse> {
+    let asks_for_sip = request
+        .headers()
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim().eq_ignore_ascii_case(SIP_SUBPROTOCOL));
+
+    if !asks_for_sip {
+        let mut error = ErrorResponse::new(Some("sip subprotocol is required".into()));
+        *error.status_mut() = StatusCode::BAD_REQUEST;
+        return Err(error);
+    }
+
+    response.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(SIP_SUBPROTOCOL),
+    );
+
+    Ok(response)
+}
@@ -2,7 +2,8 @@
 use super::uac;
 //use models::transport::ResponseMsg;
 use crate::Error;
-use common::rsip;
+use common::{rsip, tokio::time::Instant};
+use models::transport::TransportError;
 
 #[derive(Debug)]
 pub enum DialogSm {
@@ -25,12 +26,19 @@ impl DialogSm {
         }
     }
 
-    pub async fn transport_error(&self, reason: String, msg: rsip::SipMessage) {
+    pub async fn transport_error(&self, error: TransportError, msg: rsip::SipMessage) {
         match self {
-            Self::Uac(uac) => uac.transport_error(reason, msg).await,
+            Self::Uac(uac) => uac.transport_error(error, msg).await,
             //Self::Uas(uas) => uas.process_response(msg).await?,
         }
     }
+
+    pub async fn finished_at(&self) -> Option<Instant> {
+        match self {
+            Self::Uac(uac) => uac.finished_at().await,
+            //Self::Uas(uas) => uas.finished_at().await,
+        }
+    }
 }
 
 impl From<uac::MultiDialog> for DialogSm {
@@ -3,24 +3,63 @@ pub mod uac;
 pub mod dialog_sm;
 
 pub use crate::error::{DialogError, Error};
-use common::{rsip, tokio::sync::RwLock};
+use common::{
+    rsip,
+    tokio::{
+        self,
+        sync::RwLock,
+        time::{self, Instant},
+    },
+};
 use dialog_sm::DialogSm;
-use models::{rsip_ext::*, tu::DialogId, Handlers};
-use std::collections::HashMap;
+use models::{rsip_ext::*, transport::TransportError, tu::DialogId, Handlers};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    time::Duration,
+};
+
+type Data = RwLock<HashMap<DialogId, DialogSm>>;
 
 #[derive(Debug)]
 pub struct Dialogs {
     handlers: Handlers,
     //TODO: convert to message passing
-    data: RwLock<HashMap<DialogId, DialogSm>>,
+    data: Arc<Data>,
+    reaped: Arc<AtomicUsize>,
 }
 
 impl Dialogs {
     pub fn new(handlers: Handlers) -> Self {
-        Self {
+        Self::with_linger(handlers, common::CONFIG.timers.dialog_linger)
+    }
+
+    //same as new, but finished dialogs are evicted after the given linger
+    pub fn with_linger(handlers: Handlers, linger: Duration) -> Self {
+        let me = Self {
             handlers,
             data: Default::default(),
-        }
+            reaped: Default::default(),
+        };
+
+        let data = Arc::downgrade(&me.data);
+        let reaped = me.reaped.clone();
+        tokio::spawn(async move { reap(data, reaped, linger).await });
+
+        me
+    }
+
+    //dialogs kept in memory, including finished ones that still linger
+    pub async fn dialogs(&self) -> usize {
+        self.data.read().await.len()
+    }
+
+    //finished dialogs that have been evicted so far
+    pub fn reaped_dialogs(&self) -> usize {
+        self.reaped.load(Ordering::Relaxed)
     }
 
     //TODO: add proper dialog id type
@@ -60,13 +99,43 @@ impl Dialogs {
     pub async fn transport_error(
         &self,
         msg: rsip::SipMessage,
-        reason: String,
+        error: TransportError,
     ) -> Result<(), Error> {
         if let Some(sm) = self.data.read().await.get(&msg.dialog_id()?) {
-            sm.transport_error(reason, msg).await;
+            sm.transport_error(error, msg).await;
             Ok(())
         } else {
             Err(Error::from(DialogError::NotFound))
         }
     }
 }
+
+//dialogs have no timers of their own, so finished ones are swept periodically, ending up
+//evicted between one and two lingers after they finished; stops once Dialogs is dropped
+async fn reap(data: Weak<Data>, reaped: Arc<AtomicUsize>, linger: Duration) {
+    let mut ticker = time::interval(std::cmp::max(linger, Duration::from_secs(1)));
+    loop {
+        ticker.tick().await;
+
+        let data = match data.upgrade() {
+            Some(data) => data,
+            None => break,
+        };
+
+        let mut lingered = vec![];
+        for (dialog_id, sm) in data.read().await.iter() {
+            if let Some(finished_at) = sm.finished_at().await {
+                if Instant::now() >= finished_at + linger {
+                    lingered.push(dialog_id.clone());
+                }
+            }
+        }
+
+        let mut data = data.write().await;
+        for dialog_id in lingered {
+            if data.remove(&dialog_id).is_some() {
+                reaped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@ use super::{
 use crate::{presets, Error};
 use common::rsip::{self, prelude::*, uri::UriWithParams};
 use common::tokio::time::Instant;
-use models::{rsip_ext::*, tu::DialogId, Handlers};
+use models::{rsip_ext::*, transport::TransportError, tu::DialogId, Handlers};
 
 #[derive(Debug)]
 pub struct DialogSm {
@@ -51,9 +51,16 @@ pub enum DialogState {
 //TODO: need to apply strict or loose routing (probably in a helper/module)
 #[allow(dead_code)]
 impl DialogSm {
-    pub async fn new(handlers: Handlers, request: rsip::Request) -> Result<Self, Error> {
+    pub async fn new(handlers: Handlers, mut request: rsip::Request) -> Result<Self, Error> {
         validations::run(&request)?;
 
+        //we PRACK reliable provisionals, so we let the UAS know (RFC3262 4)
+        if !request.headers.supports_100rel() {
+            request
+                .headers
+                .push(rsip::headers::Supported::new(OPTION_TAG_100REL).into());
+        }
+
         let mut route_set: Vec<UriWithParams> = request
             .record_route_header()
             .map(|h| h.typed().map(|h| h.uris().to_owned()))
@@ -79,8 +86,7 @@ impl DialogSm {
             remote_target: None,
             route_set,
             session_type: session_type(&request)?,
-            //TODO: need to check transport as well (arrived from TLS?)
-            secure: request.uri.is_sips()?,
+            secure: is_secure(&request)?,
             contact_header: request.contact_header()?.clone(),
             request: request.clone(),
             state: DialogState::Unconfirmed(Default::default()),
@@ -134,7 +140,7 @@ impl DialogSm {
 
     async fn _process_incoming_response(&mut self, response: rsip::Response) -> Result<(), Error> {
         match response.status_code().kind() {
-            rsip::StatusCodeKind::Provisional => self.early(response).await,
+            rsip::StatusCodeKind::Provisional => self.early(response).await?,
             rsip::StatusCodeKind::Successful => {
                 self.confirm(response.clone()).await?;
                 self.handlers
@@ -198,19 +204,47 @@ impl DialogSm {
         Ok(())
     }
 
-    pub async fn transport_error(&mut self, reason: String, msg: rsip::SipMessage) {
-        self.error(reason, Some(msg));
+    //there is no point in going on with a secure dialog over a peer we can't trust
+    pub async fn transport_error(&mut self, error: TransportError, msg: rsip::SipMessage) {
+        match error {
+            TransportError::TlsHandshake(_) if self.secure => self.terminate(msg),
+            error => self.error(error.to_string(), Some(msg)),
+        }
     }
 
-    async fn early(&mut self, response: rsip::Response) {
-        if !matches!(self.state, DialogState::Unconfirmed(_)) {
-            return self.wrong_transition("early", response.into());
+    //when the dialog reached a final state, if it has
+    pub fn finished_at(&self) -> Option<Instant> {
+        match &self.state {
+            DialogState::Terminated(terminated) => Some(terminated.entered_at),
+            DialogState::Errored(errored) => Some(errored.entered_at),
+            _ => None,
+        }
+    }
+
+    async fn early(&mut self, response: rsip::Response) -> Result<(), Error> {
+        if !matches!(
+            self.state,
+            DialogState::Unconfirmed(_) | DialogState::Early(_)
+        ) {
+            self.wrong_transition("early", response.into());
+            return Ok(());
+        }
+
+        //RFC3262 4, a reliable provisional is acknowledged by a PRACK inside the early dialog
+        if response.headers.requires_100rel() && response.headers.rseq().is_some() {
+            let seqn = self.increased_seqn();
+            self.handlers
+                .transaction
+                .new_uac(self.request.prack_request_from(&response, seqn))
+                .await?;
         }
 
         self.state = DialogState::Early(Early {
             response,
             entered_at: Instant::now(),
         });
+
+        Ok(())
     }
 
     async fn confirm(&mut self, response: rsip::Response) -> Result<(), Error> {
@@ -370,8 +404,9 @@ impl DialogSm {
     }
 }
 
+//RFC3261 12.1.2, the request must be sent over TLS with a SIPS Request-URI
 pub fn is_secure(request: &rsip::Request) -> Result<bool, Error> {
-    Ok(request.uri.is_sips()?)
+    Ok(request.uri.is_sips()? && request.via_header()?.typed()?.transport.is_secure())
 }
 
 pub fn session_type(request: &rsip::Request) -> Result<SessionType, Error> {
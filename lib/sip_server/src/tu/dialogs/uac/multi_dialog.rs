@@ -1,6 +1,9 @@
 use crate::Error;
-use common::{rsip, tokio::sync::Mutex};
-use models::{rsip_ext::*, tu::DialogId, Handlers};
+use common::{
+    rsip,
+    tokio::{sync::Mutex, time::Instant},
+};
+use models::{rsip_ext::*, transport::TransportError, tu::DialogId, Handlers};
 
 #[derive(Debug)]
 pub struct MultiDialog {
@@ -52,7 +55,18 @@ impl MultiDialog {
         Ok(())
     }
 
-    pub async fn transport_error(&self, reason: String, msg: rsip::SipMessage) {
+    //a forked session is finished only once all of its dialogs are
+    pub async fn finished_at(&self) -> Option<Instant> {
+        self.dialogs
+            .lock()
+            .await
+            .iter()
+            .map(|dialog| dialog.finished_at())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|finished_at| finished_at.into_iter().max())
+    }
+
+    pub async fn transport_error(&self, error: TransportError, msg: rsip::SipMessage) {
         let dialog_id = msg.dialog_id().expect("missing dialog_id to report error");
 
         //TODO: decouple the find part, will be needed all over the place
@@ -65,6 +79,6 @@ impl MultiDialog {
                 .expect("No dialog inside MultiDialog Vec ??"),
         };
 
-        dialog.transport_error(reason, msg).await;
+        dialog.transport_error(error, msg).await;
     }
 }
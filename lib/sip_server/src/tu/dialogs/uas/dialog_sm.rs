@@ -3,7 +3,12 @@ use super::states::{Confirmed, Early, Errored, Terminated};
 use crate::Error;
 use common::rsip::{self, prelude::*, uri::UriWithParams};
 use common::tokio::time::Instant;
-use models::{rsip_ext::*, transport::RequestMsg, tu::DialogId, Handlers};
+use models::{
+    rsip_ext::*,
+    transport::{RequestMsg, TransportError},
+    tu::DialogId,
+    Handlers,
+};
 
 #[derive(Debug)]
 pub struct DialogSm {
@@ -173,8 +178,8 @@ impl DialogSm {
         Ok(())
     }
 
-    pub async fn transport_error(&mut self, reason: String, msg: rsip::SipMessage) {
-        self.error(reason, Some(msg));
+    pub async fn transport_error(&mut self, error: TransportError, msg: rsip::SipMessage) {
+        self.error(error.to_string(), Some(msg));
     }
 
     async fn early(&mut self, response: rsip::Response) {
@@ -1,6 +1,6 @@
 use crate::Error;
 use common::{rsip, tokio::sync::Mutex};
-use models::Handlers;
+use models::{transport::TransportError, Handlers};
 
 #[derive(Debug)]
 pub struct MultiDialog {
@@ -39,5 +39,5 @@ impl MultiDialog {
         Ok(())
     }
 
-    pub async fn transport_error(&mut self, reason: String, msg: Option<rsip::SipMessage>) {}
+    pub async fn transport_error(&mut self, error: TransportError, msg: Option<rsip::SipMessage>) {}
 }
@@ -3,7 +3,7 @@ use common::{
     async_trait::async_trait,
     rsip::{self, prelude::*},
 };
-use models::Handlers;
+use models::{rsip_ext::OPTION_TAG_100REL, transport::RequestMsg, Handlers};
 
 #[derive(Debug)]
 pub struct Capabilities {
@@ -18,12 +18,13 @@ impl Capabilities {
 
 #[async_trait]
 impl ReqProcessor for Capabilities {
-    async fn process_incoming_request(&self, msg: rsip::Request) -> Result<(), Error> {
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<(), Error> {
+        let msg = msg.sip_request;
         apply_default_checks(&msg)?;
 
         let response = create_busy_here_from(msg.clone())?;
 
-        Ok(self.handlers.transport.send(response.into()).await?)
+        Ok(self.handlers.transaction.new_uas(msg, Some(response)).await?)
     }
 }
 
@@ -37,7 +38,9 @@ fn has_correct_request_uri(request_uri: &rsip::Uri) -> Result<(), Error> {
     if common::CONFIG.contains_addr(&request_uri.host_with_port) {
         Ok(())
     } else {
-        Err(Error::from("invalid request uri"))
+        Err(Error::from(crate::SipError::NotFound(
+            "invalid request uri".into(),
+        )))
     }
 }
 
@@ -67,6 +70,7 @@ fn create_busy_here_from(request: rsip::Request) -> Result<rsip::Response, crate
     headers.push(ContentLength::default().into());
     headers.push(Server::default().into());
     headers.push(Allow::default().into());
+    headers.push(Supported::new(OPTION_TAG_100REL).into());
     headers.push(Accept::new("application/sdp").into());
     headers.push(AcceptEncoding::new("gzip").into());
     headers.push(AcceptLanguage::new("english").into());
@@ -1,9 +1,9 @@
 mod capabilities;
 mod registrar;
-//mod ua;
+mod ua;
 //mod proxy;
 
 pub use capabilities::Capabilities;
 pub use registrar::Registrar;
-//pub use ua::UserAgent;
+pub use ua::{MergedRequests, UserAgent};
 //pub use proxy::{Proxy, ProxyProcessor};
@@ -1,9 +1,17 @@
-use crate::{Error, ReqProcessor};
+use crate::{Error, ReqProcessor, SipError};
 use common::{
     async_trait::async_trait,
     rsip::{self, prelude::*},
 };
-use models::Handlers;
+use models::{
+    rsip_ext::RequestExt,
+    transport::{Flow, RequestMsg},
+    Handlers,
+};
+use std::time::Duration;
+
+//shorter registrations would mostly keep the registrar busy with refreshes (RFC3261 10.3)
+const MIN_EXPIRES: u32 = 60;
 
 #[derive(Debug)]
 pub struct Registrar {
@@ -12,12 +20,12 @@ pub struct Registrar {
 
 #[async_trait]
 impl ReqProcessor for Registrar {
-    async fn process_incoming_request(&self, msg: rsip::Request) -> Result<(), Error> {
-        apply_default_checks(&msg)?;
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<(), Error> {
+        apply_default_checks(&msg.sip_request)?;
 
-        match msg.contact_header() {
+        match msg.sip_request.contact_header() {
             Ok(_) => self.handle_update(msg).await,
-            Err(_) => self.handle_query(msg).await,
+            Err(_) => self.handle_query(msg.sip_request).await,
         }
     }
 }
@@ -27,24 +35,44 @@ impl Registrar {
         Self { handlers }
     }
 
-    async fn handle_update(&self, msg: rsip::Request) -> Result<(), Error> {
+    //every contact is checked before any of them is touched, a REGISTER is either applied
+    //as a whole or not at all (RFC3261 10.3)
+    async fn handle_update(&self, msg: RequestMsg) -> Result<(), Error> {
         use std::convert::TryFrom;
 
-        for contact_header in msg.contact_headers() {
-            let typed_contact_header = contact_header.typed()?;
+        let request = &msg.sip_request;
+        let mut contacts = vec![];
+        for contact_header in request.contact_headers() {
+            let expires = expires_value_for(contact_header, request.expires_header())?;
+            if expires > 0 && expires < MIN_EXPIRES {
+                return Err(Error::from(SipError::IntervalTooBrief(MIN_EXPIRES)));
+            }
+
+            contacts.push((contact_header.typed()?.uri, expires));
+        }
 
-            match expires_value_for(contact_header, msg.expires_header())? {
+        for (contact_uri, expires) in contacts {
+            match expires {
                 0 => {
-                    store::Registration::delete_by_uri(typed_contact_header.uri.to_string())?;
+                    store::Registration::delete_by_uri(contact_uri.to_string())?;
+                    self.handlers.transport.unbind(contact_uri).await?;
                 }
-                _ => {
-                    println!("{:?}", typed_contact_header);
+                expires => {
                     store::Registration::upsert(store::DirtyRegistration::try_from(msg.clone())?)?;
+                    //the transport must reach the contact over the same flow (RFC5626 5.3)
+                    self.handlers
+                        .transport
+                        .bind(
+                            contact_uri,
+                            Flow::from((msg.transport, msg.peer)),
+                            Duration::from_secs(expires.into()),
+                        )
+                        .await?;
                 }
             }
         }
 
-        self.handle_query(msg).await
+        self.handle_query(msg.sip_request).await
     }
 
     async fn handle_query(&self, msg: rsip::Request) -> Result<(), Error> {
@@ -55,7 +83,8 @@ impl Registrar {
                 .map(Into::into)
                 .collect::<Vec<rsip::headers::Contact>>(),
         )?;
-        Ok(self.handlers.transport.send(response.into()).await?)
+
+        Ok(self.handlers.transaction.new_uas(msg, Some(response)).await?)
     }
 }
 
@@ -64,7 +93,8 @@ fn apply_default_checks(request: &rsip::Request) -> Result<(), Error> {
     let from_header = request.from_header()?;
 
     has_correct_request_uri(&request.uri)?;
-    extensions_are_supported()?;
+    arrived_securely_if_sips(request)?;
+    extensions_are_supported(request)?;
     has_correct_to_request_uri(to_header)?;
     has_same_from_to_header_uris(from_header, to_header)?;
 
@@ -79,23 +109,51 @@ fn has_same_from_to_header_uris(
     let typed_to_header = to_header.typed()?;
 
     if typed_from_header.uri != typed_to_header.uri {
-        return Err(Error::from("mismatch between to and from header uris!"));
+        return Err(Error::from(SipError::Forbidden(
+            "mismatch between to and from header uris!".into(),
+        )));
     }
 
     if typed_from_header.display_name != typed_to_header.display_name {
-        return Err(Error::from(
-            "mismatch between to and from header display names!",
-        ));
+        return Err(Error::from(SipError::Forbidden(
+            "mismatch between to and from header display names!".into(),
+        )));
     }
 
     Ok(())
 }
 
 fn has_correct_request_uri(request_uri: &rsip::Uri) -> Result<(), Error> {
+    if !matches!(
+        request_uri.scheme,
+        None | Some(rsip::Scheme::Sip) | Some(rsip::Scheme::Sips)
+    ) {
+        return Err(Error::from(SipError::UnsupportedUriScheme(format!(
+            "unsupported request uri scheme: {}",
+            request_uri
+        ))));
+    }
+
     if common::CONFIG.contains_addr(&request_uri.host_with_port) {
         Ok(())
     } else {
-        Err(Error::from("invalid request uri"))
+        Err(Error::from(SipError::NotFound("invalid request uri".into())))
+    }
+}
+
+//a SIPS registration must arrive over TLS, otherwise the binding can't be trusted
+fn arrived_securely_if_sips(request: &rsip::Request) -> Result<(), Error> {
+    let mut is_sips = request.uri.is_sips()?;
+    for contact_header in request.contact_headers() {
+        is_sips |= contact_header.typed()?.uri.is_sips()?;
+    }
+
+    if is_sips && !request.arrived_securely() {
+        Err(Error::from(SipError::Forbidden(
+            "sips registration did not arrive over a secure transport".into(),
+        )))
+    } else {
+        Ok(())
     }
 }
 
@@ -105,12 +163,29 @@ fn has_correct_to_request_uri(to_header: &rsip::headers::To) -> Result<(), Error
     if common::CONFIG.contains_addr(&typed_to_header.uri.host_with_port) {
         Ok(())
     } else {
-        Err(Error::from("record not found!"))
+        Err(Error::from(SipError::NotFound("record not found!".into())))
     }
 }
 
-fn extensions_are_supported() -> Result<(), Error> {
-    Ok(())
+//none of the extensions a REGISTER could require is implemented here (RFC3261 8.2.2.3)
+fn extensions_are_supported(request: &rsip::Request) -> Result<(), Error> {
+    let option_tags = request
+        .headers
+        .iter()
+        .filter_map(|header| match header {
+            rsip::Header::Require(require) => Some(require.value()),
+            _ => None,
+        })
+        .flat_map(|value| value.split(','))
+        .map(|option_tag| option_tag.trim().to_string())
+        .filter(|option_tag| !option_tag.is_empty())
+        .collect::<Vec<_>>();
+
+    if option_tags.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from(SipError::BadExtension(option_tags)))
+    }
 }
 
 fn create_registration_ok_from(
@@ -0,0 +1,79 @@
+use crate::Error;
+use common::{
+    rsip::{self, prelude::*},
+    tokio::{sync::Mutex, time::Instant},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+//remembers the top branch of out-of-dialog requests, so that a forked request reaching us
+//over a second path is recognized as merged (RFC3261 8.2.2.2)
+#[derive(Debug)]
+pub struct MergedRequests {
+    window: Duration,
+    seen: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    branches: HashMap<String, String>,
+    expirations: VecDeque<(Instant, String)>,
+}
+
+impl MergedRequests {
+    //a copy can only show up while the server transaction of the first one is around
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: Default::default(),
+        }
+    }
+
+    pub async fn is_merged(&self, request: &rsip::Request) -> Result<bool, Error> {
+        if request.to_header()?.tag()?.is_some() {
+            return Ok(false);
+        }
+
+        let key = format!(
+            "{} {} {}",
+            request
+                .from_header()?
+                .tag()?
+                .map(|tag| tag.to_string())
+                .unwrap_or_default(),
+            request.call_id_header()?.value(),
+            request.cseq_header()?.value()
+        );
+        let branch: String = request
+            .transaction_id()?
+            .map(Into::into)
+            .unwrap_or_default();
+
+        let mut seen = self.seen.lock().await;
+        seen.expire();
+        match seen.branches.get(&key) {
+            Some(seen_branch) => Ok(*seen_branch != branch),
+            None => {
+                seen.branches.insert(key.clone(), branch);
+                seen.expirations.push_back((Instant::now() + self.window, key));
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl Seen {
+    fn expire(&mut self) {
+        let now = Instant::now();
+        while let Some((expires_at, _)) = self.expirations.front() {
+            if *expires_at > now {
+                break;
+            }
+            if let Some((_, key)) = self.expirations.pop_front() {
+                self.branches.remove(&key);
+            }
+        }
+    }
+}
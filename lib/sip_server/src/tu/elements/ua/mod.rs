@@ -1,10 +1,22 @@
+mod merged_requests;
 //mod processor;
 
+pub use merged_requests::MergedRequests;
+
 use crate::{presets, tu::dialogs::Dialogs, Error, ReqProcessor};
-use common::{rsip, tokio};
+use common::{
+    rsip::{self, prelude::*},
+    tokio,
+};
 use std::sync::Arc;
 
-use models::{receivers::TuReceiver, tu::TuLayerMsg, Handlers};
+use models::{
+    receivers::TuReceiver,
+    rsip_ext::DialogExt,
+    transport::{RequestMsg, TransportError},
+    tu::TuLayerMsg,
+    Handlers,
+};
 
 //TODO: rename this to something else like ProxyTu etc
 #[derive(Debug)]
@@ -24,6 +36,7 @@ impl<R: ReqProcessor, C: ReqProcessor> UserAgent<R, C> {
                 registrar,
                 capabilities,
                 dialogs: Dialogs::new(handlers.clone()),
+                merged_requests: MergedRequests::new(common::CONFIG.timers.default.b()),
                 handlers,
             }),
         };
@@ -45,6 +58,7 @@ struct Inner<R: ReqProcessor, C: ReqProcessor> {
     capabilities: C,
     #[allow(dead_code)]
     dialogs: Dialogs,
+    merged_requests: MergedRequests,
     handlers: Handlers,
 }
 
@@ -61,6 +75,7 @@ impl<R: ReqProcessor, C: ReqProcessor> Inner<R, C> {
     async fn receive(&self, msg: TuLayerMsg) -> Result<(), Error> {
         match msg {
             TuLayerMsg::Incoming(msg) => self.process_incoming_message(msg).await?,
+            TuLayerMsg::IncomingRequest(msg) => self.process_incoming_request(msg).await?,
             TuLayerMsg::Outgoing(msg) => self.process_outgoing_message(msg).await?,
             TuLayerMsg::TransportError(msg, error) => {
                 self.process_transport_error(msg, error).await?
@@ -73,15 +88,21 @@ impl<R: ReqProcessor, C: ReqProcessor> Inner<R, C> {
     async fn process_transport_error(
         &self,
         msg: rsip::SipMessage,
-        error: String,
+        error: TransportError,
     ) -> Result<(), Error> {
         Ok(self.dialogs.transport_error(msg, error).await?)
     }
 
     async fn process_incoming_message(&self, msg: rsip::SipMessage) -> Result<(), Error> {
         match msg {
+            //the transaction layer already answered the INVITE with a 487 (RFC3261 9.2),
+            //and there is nothing else pending on our side for it
+            rsip::SipMessage::Request(request) if request.method == rsip::Method::Cancel => {
+                common::log::debug!("{} got cancelled", request.call_id_header()?)
+            }
+            //other requests only come from the transport, along with their flow
             rsip::SipMessage::Request(request) => {
-                self.handle_incoming_request(request).await?;
+                common::log::warn!("dropping {} request without a flow", request.method)
             }
             rsip::SipMessage::Response(response) => {
                 self.handle_incoming_response(response).await?;
@@ -91,6 +112,15 @@ impl<R: ReqProcessor, C: ReqProcessor> Inner<R, C> {
         Ok(())
     }
 
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<(), Error> {
+        let request = msg.sip_request.clone();
+        if let Err(error) = self.handle_incoming_request(msg).await {
+            self.reply_with_error(request, error).await?;
+        }
+
+        Ok(())
+    }
+
     async fn process_outgoing_message(&self, msg: rsip::SipMessage) -> Result<(), Error> {
         match msg {
             rsip::SipMessage::Request(request) => {
@@ -104,12 +134,25 @@ impl<R: ReqProcessor, C: ReqProcessor> Inner<R, C> {
         Ok(())
     }
 
-    async fn handle_incoming_request(&self, request: rsip::Request) -> Result<(), Error> {
+    async fn handle_incoming_request(&self, msg: RequestMsg) -> Result<(), Error> {
         use rsip::Method;
 
+        let request = msg.sip_request.clone();
+
+        //an ACK can't be answered, and a CANCEL shares the CSeq of the INVITE
+        if !matches!(request.method, Method::Ack | Method::Cancel) {
+            if let Some(status_code) = self.rejection_of(&request).await? {
+                return self.reject(request, status_code).await;
+            }
+        }
+
         match request.method {
-            Method::Register => self.registrar.process_incoming_request(request).await?,
-            Method::Options => self.capabilities.process_incoming_request(request).await?,
+            Method::Register => self.registrar.process_incoming_request(msg).await?,
+            Method::Options => self.capabilities.process_incoming_request(msg).await?,
+            //a CANCEL matching a pending INVITE is answered by the transaction layer (RFC3261 9.2)
+            Method::Cancel => self.reject(request, 481.into()).await?,
+            //the transaction layer matches the PRACK to our reliable provisional (RFC3262 3)
+            Method::PRack => self.handlers.transaction.new_uas(request, None).await?,
             _ => {
                 self.handlers
                     .transport
@@ -121,11 +164,86 @@ impl<R: ReqProcessor, C: ReqProcessor> Inner<R, C> {
         Ok(())
     }
 
+    //the peer gets a final response for whatever went wrong, instead of waiting for one,
+    //unless the processor already handed a response to a server transaction of its own
+    async fn reply_with_error(&self, request: rsip::Request, error: Error) -> Result<(), Error> {
+        common::log::warn!("{} to {} failed: {}", request.method, request.uri, error);
+        if request.method == rsip::Method::Ack {
+            return Ok(());
+        }
+
+        if let Some(transaction_id) = request.transaction_id()? {
+            if self
+                .handlers
+                .transaction
+                .has_transaction_for(transaction_id)
+                .await?
+            {
+                return Ok(());
+            }
+        }
+
+        let response = presets::error_response_from(request.clone(), &error)?;
+        self.respond(request, response).await
+    }
+
+    //RFC3261 8.2.2.2 for merged requests, and 16.3 for the Max-Forwards of the rest, apart
+    //from an OPTIONS that we answer ourselves (RFC3261 11)
+    async fn rejection_of(
+        &self,
+        request: &rsip::Request,
+    ) -> Result<Option<rsip::StatusCode>, Error> {
+        if self.merged_requests.is_merged(request).await? {
+            return Ok(Some(482.into()));
+        }
+
+        match request.max_forwards_header() {
+            Ok(max_forwards)
+                if max_forwards.value().trim() == "0"
+                    && request.method != rsip::Method::Options =>
+            {
+                Ok(Some(483.into()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn reject(
+        &self,
+        request: rsip::Request,
+        status_code: rsip::StatusCode,
+    ) -> Result<(), Error> {
+        let response = presets::response_from(request.clone(), status_code)?;
+        self.respond(request, response).await
+    }
+
+    //final responses of our own still get a server transaction, so that retransmissions of
+    //the request are absorbed and the response is retransmitted where needed
+    async fn respond(&self, request: rsip::Request, response: rsip::Response) -> Result<(), Error> {
+        match request.method {
+            rsip::Method::Invite => {
+                self.handlers
+                    .transaction
+                    .new_uas_invite(request, None)
+                    .await?;
+                self.handlers.transaction.reply(response).await?
+            }
+            _ => {
+                self.handlers
+                    .transaction
+                    .new_uas(request, Some(response))
+                    .await?
+            }
+        };
+
+        Ok(())
+    }
+
     async fn handle_incoming_response(&self, response: rsip::Response) -> Result<(), Error> {
         if let Ok(dialog_id) = response.dialog_id() {
             if self.dialogs.exists(dialog_id).await {
                 //TODO: this is wrong, uac dialogs can process requests as well
-                self.dialogs.uac_process_incoming_response(response).await?
+                self.dialogs.process_incoming_response(response).await?
             } else {
                 common::log::warn!("received response msg but no dialog exists for that msg");
             };
@@ -2,11 +2,14 @@ pub mod dialogs;
 pub mod elements;
 
 use common::{async_trait::async_trait, rsip};
+use models::transport::RequestMsg;
 use std::fmt::Debug;
 
+//requests come along with the flow they arrived over, for elements that need to reach the
+//peer the same way later on
 #[async_trait]
 pub trait ReqProcessor: Send + Sync + Debug + 'static {
-    async fn process_incoming_request(&self, msg: rsip::Request) -> Result<(), crate::Error>;
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<(), crate::Error>;
 }
 
 #[async_trait]
@@ -12,11 +12,11 @@ use diesel::{
     serialize::{Output, ToSql},
     sql_types::Text,
 };
+use models::transport::{Flow, RequestMsg};
 use std::{
     convert::TryFrom,
     fmt::{self, Debug},
     io::Write,
-    net::{IpAddr, Ipv4Addr},
 };
 
 #[derive(Debug, Default)]
@@ -150,6 +150,13 @@ impl Registration {
         )
     }
 
+    pub fn find_by_uri(uri: String) -> Result<Option<Self>, Error> {
+        Ok(registrations::table
+            .filter(registrations::contact_uri.eq(uri))
+            .get_result::<Registration>(&db_conn()?)
+            .optional()?)
+    }
+
     pub fn delete_by_uri(uri: String) -> Result<Self, Error> {
         Ok(
             diesel::delete(registrations::table.filter(registrations::contact_uri.eq(uri)))
@@ -235,10 +242,15 @@ impl From<rsip::Transport> for Transport {
     }
 }
 
-impl TryFrom<rsip::Request> for DirtyRegistration {
+//the flow is the one the REGISTER actually arrived over, the Via can't tell the port of a
+//connection unless the UA asked for rport
+impl TryFrom<RequestMsg> for DirtyRegistration {
     type Error = crate::Error;
 
-    fn try_from(request: rsip::Request) -> Result<Self, Self::Error> {
+    fn try_from(msg: RequestMsg) -> Result<Self, Self::Error> {
+        let flow = Flow::from((msg.transport, msg.peer));
+        let request = msg.sip_request;
+
         if request.method != rsip::Method::Register {
             return Err(Self::Error::custom(format!(
                 "cannot create registration from {} method",
@@ -278,23 +290,27 @@ impl TryFrom<rsip::Request> for DirtyRegistration {
             call_id: Some(request.call_id_header()?.clone().into()),
             cseq: Some(request.cseq_header()?.typed()?.seq as i32),
             user_agent: Some(request.user_agent_header().unwrap().clone().into()),
-            instance: Some("something".into()),
-            ip_address: Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 3)).into()),
-            port: Some(
-                request
-                    .from_header()?
-                    .typed()?
-                    .uri
-                    .port()
-                    .map(|s| *s.value() as i16)
-                    .unwrap_or(5060),
-            ),
+            instance: instance_of(&typed_contact_header),
+            ip_address: Some(flow.peer.ip().into()),
+            port: Some(flow.peer.port() as i16),
             contact_uri: Some(typed_contact_header.uri.to_string()),
-            transport: Some(Transport::Udp),
+            transport: Some(flow.transport.into()),
         })
     }
 }
 
+//the +sip.instance of the UA (RFC5626 4.1), if it sent one
+fn instance_of(contact: &rsip::typed::Contact) -> Option<String> {
+    contact.params.iter().find_map(|param| match param {
+        rsip::Param::Other(name, Some(value))
+            if name.value().eq_ignore_ascii_case("+sip.instance") =>
+        {
+            Some(value.value().trim_matches('"').to_string())
+        }
+        _ => None,
+    })
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<rsip::headers::Contact> for Registration {
     fn into(self) -> rsip::headers::Contact {
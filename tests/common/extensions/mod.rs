@@ -4,7 +4,9 @@ mod transaction_ext;
 mod uri_ext;
 
 pub use self::models::{TransactionLayerMsgExt, TransportLayerMsgExt};
-pub use transaction_ext::{TransactionUacExt, TransactionUasExt};
+pub use transaction_ext::{
+    TransactionUacExt, TransactionUacNonInviteExt, TransactionUasExt, TransactionUasNonInviteExt,
+};
 pub use uri_ext::{HostWithPortExt, UriExt};
 
 pub trait Randomized: Sized {
@@ -6,7 +6,7 @@ pub trait TransactionLayerMsgExt {
     fn new_uac_invite_msg(&self) -> rsip::Request;
     fn new_uas_invite_msg(&self) -> rsip::Request;
     fn new_uac_msg(&self) -> rsip::Request;
-    //fn new_uas_msg(&self) -> rsip::Response;
+    fn new_uas_response(&self) -> rsip::Response;
     fn reply_msg(&self) -> rsip::Response;
     fn incoming_msg(&self) -> rsip::SipMessage;
 }
@@ -33,12 +33,12 @@ impl TransactionLayerMsgExt for TransactionLayerMsg {
         }
     }
 
-    //fn new_uas_msg(&self) -> rsip::Response {
-    //    match self {
-    //        TransactionLayerMsg::NewUas(request, _) => request.clone(),
-    //        _ => panic!("not a NewUasInvite variant"),
-    //    }
-    //}
+    fn new_uas_response(&self) -> rsip::Response {
+        match self {
+            TransactionLayerMsg::NewUas(_, Some(response)) => response.clone(),
+            _ => panic!("not a NewUas variant with a response"),
+        }
+    }
 
     fn reply_msg(&self) -> rsip::Response {
         match self {
@@ -68,6 +68,9 @@ impl TryClone for TransactionLayerMsg {
                 Ok(Self::NewUas(request.clone(), opt_response.clone()))
             }
             TransactionLayerMsg::Reply(response) => Ok(Self::Reply(response.clone())),
+            TransactionLayerMsg::Cancel(transaction_id) => {
+                Ok(Self::Cancel(transaction_id.clone()))
+            }
             TransactionLayerMsg::Incoming(msg) => Ok(Self::Incoming(msg.clone())),
             TransactionLayerMsg::TransportError(msg, transport_error) => {
                 Ok(Self::TransportError(msg.clone(), transport_error.clone()))
@@ -75,6 +78,9 @@ impl TryClone for TransactionLayerMsg {
             TransactionLayerMsg::HasTransaction(_, _) => {
                 Err("can't clone HasTransaction variant, due to Sender".into())
             }
+            TransactionLayerMsg::Resolved(request, peer, transport) => {
+                Ok(Self::Resolved(request.clone(), *peer, *transport))
+            }
         }
     }
 }
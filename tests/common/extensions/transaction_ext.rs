@@ -2,7 +2,9 @@ use crate::common::delay_for;
 use common::async_trait::async_trait;
 use models::transaction::TransactionId;
 use sip_server::transaction::{
-    sm::uac::TrxState as UacTrxState, sm::uas::TrxState as UasTrxState, sm::TrxStateSm,
+    sm::uac::TrxState as UacTrxState, sm::uac_non_invite::TrxState as UacNonInviteTrxState,
+    sm::uas::TrxState as UasTrxState, sm::uas_non_invite::TrxState as UasNonInviteTrxState,
+    sm::TrxStateSm,
 };
 use std::time::Duration;
 
@@ -235,3 +237,197 @@ impl TransactionUasExt for sip_server::Transaction {
         }
     }
 }
+
+#[async_trait]
+pub trait TransactionUacNonInviteExt {
+    async fn is_non_invite_uac_trying(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uac_proceeding(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uac_completed(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uac_terminated(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uac_errored(&self, transaction_id: TransactionId) -> bool;
+}
+
+#[async_trait]
+impl TransactionUacNonInviteExt for sip_server::Transaction {
+    async fn is_non_invite_uac_trying(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UacNonInvite(sm) => {
+                matches!(sm.lock().await.state, UacNonInviteTrxState::Trying { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uac_proceeding(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UacNonInvite(sm) => {
+                matches!(sm.lock().await.state, UacNonInviteTrxState::Proceeding { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uac_completed(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UacNonInvite(sm) => {
+                matches!(sm.lock().await.state, UacNonInviteTrxState::Completed { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uac_terminated(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UacNonInvite(sm) => {
+                matches!(sm.lock().await.state, UacNonInviteTrxState::Terminated { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uac_errored(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UacNonInvite(sm) => {
+                matches!(sm.lock().await.state, UacNonInviteTrxState::Errored { .. })
+            }
+            _ => false,
+        }
+    }
+}
+
+#[async_trait]
+pub trait TransactionUasNonInviteExt {
+    async fn is_non_invite_uas_trying(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uas_proceeding(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uas_completed(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uas_terminated(&self, transaction_id: TransactionId) -> bool;
+    async fn is_non_invite_uas_errored(&self, transaction_id: TransactionId) -> bool;
+}
+
+#[async_trait]
+impl TransactionUasNonInviteExt for sip_server::Transaction {
+    async fn is_non_invite_uas_trying(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UasNonInvite(sm) => {
+                matches!(sm.lock().await.state, UasNonInviteTrxState::Trying { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uas_proceeding(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UasNonInvite(sm) => {
+                matches!(sm.lock().await.state, UasNonInviteTrxState::Proceeding { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uas_completed(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UasNonInvite(sm) => {
+                matches!(sm.lock().await.state, UasNonInviteTrxState::Completed { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uas_terminated(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UasNonInvite(sm) => {
+                matches!(sm.lock().await.state, UasNonInviteTrxState::Terminated { .. })
+            }
+            _ => false,
+        }
+    }
+
+    async fn is_non_invite_uas_errored(&self, transaction_id: TransactionId) -> bool {
+        delay_for(Duration::from_millis(1)).await;
+        match self
+            .inner
+            .state
+            .read()
+            .await
+            .get(&transaction_id)
+            .expect("getting transaction from state")
+        {
+            TrxStateSm::UasNonInvite(sm) => {
+                matches!(sm.lock().await.state, UasNonInviteTrxState::Errored { .. })
+            }
+            _ => false,
+        }
+    }
+}
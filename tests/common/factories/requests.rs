@@ -1,5 +1,6 @@
 use crate::common::factories::prelude::*;
 use common::rsip::{self, headers::*, Method, Uri, Version};
+use models::transport::RequestMsg;
 
 pub fn request(from_uri: Option<Uri>, to_uri: Option<Uri>) -> rsip::Request {
     let mut headers: Headers = Randomized::default();
@@ -135,3 +136,12 @@ pub fn options_request() -> rsip::Request {
         ..Randomized::default()
     }
 }
+
+//the request as the transport hands it over to the TU, from a UA behind a NAT
+pub fn request_msg_from(request: rsip::Request) -> RequestMsg {
+    RequestMsg::new(
+        request,
+        "203.0.113.1:41234".parse().expect("socket addr"),
+        rsip::Transport::Udp,
+    )
+}
@@ -0,0 +1,82 @@
+use common::{rsip, ListenerConfig};
+use std::net::IpAddr;
+
+fn listener(addr: &str, transport: rsip::Transport) -> ListenerConfig {
+    ListenerConfig {
+        addr: addr.parse().expect("socket addr"),
+        transport,
+    }
+}
+
+#[test]
+fn listen_addrs_entries_listen_on_udp_and_tcp_by_default() {
+    assert_eq!(
+        common::listeners_from("10.0.0.1:5070"),
+        vec![
+            listener("10.0.0.1:5070", rsip::Transport::Udp),
+            listener("10.0.0.1:5070", rsip::Transport::Tcp),
+        ]
+    );
+}
+
+#[test]
+fn listen_addrs_entries_can_be_restricted_to_a_transport() {
+    assert_eq!(
+        common::listeners_from(" 10.0.0.1:5071;transport=TLS "),
+        vec![listener("10.0.0.1:5071", rsip::Transport::Tls)]
+    );
+    assert_eq!(
+        common::listeners_from("[::1]:8081;transport=ws"),
+        vec![listener("[::1]:8081", rsip::Transport::Ws)]
+    );
+}
+
+#[test]
+fn listen_addrs_entries_without_a_port_use_the_default_one_of_their_transport() {
+    assert_eq!(
+        common::listeners_from("10.0.0.1;transport=wss"),
+        vec![listener("10.0.0.1:443", rsip::Transport::Wss)]
+    );
+    assert_eq!(
+        common::listeners_from("10.0.0.1"),
+        vec![
+            listener("10.0.0.1:5060", rsip::Transport::Udp),
+            listener("10.0.0.1:5060", rsip::Transport::Tcp),
+        ]
+    );
+}
+
+#[test]
+fn invalid_listen_addrs_entries_are_skipped() {
+    assert!(common::listeners_from("10.0.0.1:5060;transport=sctp").is_empty());
+    assert!(common::listeners_from("10.0.0.1:5060;maddr=10.0.0.2").is_empty());
+    assert!(common::listeners_from("sip.example.com:5060").is_empty());
+    assert!(common::listeners_from("not an address").is_empty());
+}
+
+#[test]
+fn default_listeners_listen_everywhere_on_the_default_port_of_each_transport() {
+    let listeners = common::default_listeners();
+
+    for transport in &[
+        rsip::Transport::Udp,
+        rsip::Transport::Tcp,
+        rsip::Transport::Tls,
+        rsip::Transport::Ws,
+        rsip::Transport::Wss,
+    ] {
+        let ipv4_listener = ListenerConfig {
+            addr: (
+                IpAddr::from([0, 0, 0, 0]),
+                common::default_port_for(*transport),
+            )
+                .into(),
+            transport: *transport,
+        };
+        assert!(listeners.contains(&ipv4_listener));
+    }
+    assert!(listeners.iter().all(|listener| {
+        listener.addr.ip().is_unspecified()
+            && listener.addr.port() == common::default_port_for(listener.transport)
+    }));
+}
@@ -1,3 +1,4 @@
+pub mod config_tests;
 pub mod sip_server;
 
 pub fn debug(udp_tuple: &models::transport::UdpTuple) {
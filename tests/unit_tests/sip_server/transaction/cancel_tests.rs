@@ -0,0 +1,210 @@
+use super::{setup, transaction_id_of};
+use crate::common::{
+    extensions::{TransactionUacExt, TransactionUasExt, TransactionUasNonInviteExt},
+    factories::prelude::*,
+};
+use common::rsip::{self, prelude::*};
+use models::{rsip_ext::*, transport::TransportLayerMsg, tu::TuLayerMsg};
+
+fn cseq_method_of(msg: TransportLayerMsg) -> rsip::Method {
+    match msg {
+        TransportLayerMsg::Outgoing(rsip::SipMessage::Response(response)) => {
+            response.cseq_header().unwrap().typed().unwrap().method
+        }
+        TransportLayerMsg::Outgoing(rsip::SipMessage::Request(request)) => request.method,
+        _ => panic!("not an outgoing message"),
+    }
+}
+
+fn status_code_of(msg: TransportLayerMsg) -> rsip::StatusCode {
+    match msg {
+        TransportLayerMsg::Outgoing(rsip::SipMessage::Response(response)) => response.status_code,
+        _ => panic!("not an outgoing response"),
+    }
+}
+
+#[tokio::test]
+async fn cancel_terminates_pending_invite_with_487() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), Some(request.provisional_of(180)))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    let cancel = request.cancel_request();
+    transaction
+        .handler()
+        .process(cancel.clone().into())
+        .await
+        .unwrap();
+
+    assert_eq!(transport.messages().await.len().await, 3);
+    let messages = transport.messages().await;
+    let invite_response = messages.0.lock().await[1].clone();
+    assert_eq!(status_code_of(invite_response.clone()), 487.into());
+    assert_eq!(cseq_method_of(invite_response), rsip::Method::Invite);
+    assert_eq!(status_code_of(messages.latest().await), 200.into());
+    assert_eq!(cseq_method_of(messages.latest().await), rsip::Method::Cancel);
+    assert!(
+        transaction
+            .is_uas_completed(transaction_id_of(&request))
+            .await
+    );
+    //the TU stops working on the INVITE
+    assert_eq!(tu.messages().await.len().await, 1);
+    assert!(matches!(
+        tu.messages().await.first().await,
+        TuLayerMsg::Incoming(rsip::SipMessage::Request(request)) if request == cancel
+    ));
+
+    //a retransmitted CANCEL gets the same 200, while the INVITE is left alone
+    transaction
+        .handler()
+        .process(cancel.into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 4);
+    assert_eq!(cseq_method_of(transport.messages().await.latest().await), rsip::Method::Cancel);
+    assert!(
+        transaction
+            .is_uas_completed(transaction_id_of(&request))
+            .await
+    );
+    assert_eq!(tu.messages().await.len().await, 1);
+}
+
+#[tokio::test]
+async fn cancel_after_final_response_has_no_effect() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = requests::invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), Some(request.provisional_of(180)))
+        .await
+        .unwrap();
+    transaction
+        .handler()
+        .reply(responses::ok_response_from(request.clone()))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    transaction
+        .handler()
+        .process(request.cancel_request().into())
+        .await
+        .unwrap();
+
+    assert_eq!(transport.messages().await.len().await, 3);
+    assert_eq!(status_code_of(transport.messages().await.latest().await), 200.into());
+    assert_eq!(cseq_method_of(transport.messages().await.latest().await), rsip::Method::Cancel);
+    assert!(
+        transaction
+            .is_uas_accepted(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn cancel_of_a_non_invite_request_is_answered_without_effect() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    transaction
+        .handler()
+        .new_uas(request.clone(), None)
+        .await
+        .unwrap();
+
+    transaction
+        .handler()
+        .process(request.cancel_request().into())
+        .await
+        .unwrap();
+
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert_eq!(status_code_of(transport.messages().await.latest().await), 200.into());
+    assert_eq!(cseq_method_of(transport.messages().await.latest().await), rsip::Method::Cancel);
+    assert!(
+        transaction
+            .is_non_invite_uas_trying(transaction_id_of(&request))
+            .await
+    );
+    assert_eq!(tu.messages().await.len().await, 0);
+}
+
+#[tokio::test]
+async fn client_cancel_waits_for_a_provisional() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::invite_request();
+    transaction
+        .handler()
+        .new_uac_invite(request.clone())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    //too early, the CANCEL waits for a provisional response
+    transaction
+        .handler()
+        .cancel(transaction_id_of(&request))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    transaction
+        .handler()
+        .process(request.provisional_of(180).into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    //and goes out only once
+    transaction
+        .handler()
+        .process(request.provisional_of(183).into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+    let cancel = match transport.messages().await.latest().await {
+        TransportLayerMsg::Outgoing(rsip::SipMessage::Request(cancel)) => cancel,
+        _ => panic!("not an outgoing request"),
+    };
+    assert_eq!(cancel, request.cancel_request());
+    assert_eq!(transaction_id_of(&cancel), transaction_id_of(&request));
+
+    //the 200 to the CANCEL doesn't affect the INVITE, the 487 does
+    transaction
+        .handler()
+        .process(responses::ok_response_from(cancel).into())
+        .await
+        .unwrap();
+    assert_eq!(tu.messages().await.len().await, 3);
+    assert!(
+        transaction
+            .is_uac_proceeding(transaction_id_of(&request))
+            .await
+    );
+
+    transaction
+        .handler()
+        .process(request.provisional_of(487).into())
+        .await
+        .unwrap();
+    assert!(matches!(
+        tu.messages().await.latest().await,
+        TuLayerMsg::Incoming(rsip::SipMessage::Response(response))
+            if response.status_code == 487.into()
+    ));
+    assert!(
+        transaction
+            .is_uac_completed(transaction_id_of(&request))
+            .await
+    );
+}
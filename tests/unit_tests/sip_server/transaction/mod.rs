@@ -1,8 +1,14 @@
+pub mod cancel_tests;
+pub mod prack_tests;
+pub mod scheduler_tests;
+pub mod uac_non_invite_tests;
 pub mod uac_tests;
+pub mod uas_non_invite_tests;
 pub mod uas_tests;
 
 use crate::common::snitches::SpySnitch;
-use models::{transport::TransportLayerMsg, tu::TuLayerMsg};
+use common::rsip::{self, prelude::*};
+use models::{transaction::TransactionId, transport::TransportLayerMsg, tu::TuLayerMsg};
 use sip_server::Transaction;
 
 pub async fn setup() -> (
@@ -18,3 +24,25 @@ pub async fn setup() -> (
 
     (tu, transaction, transport)
 }
+
+//same request, but its topmost Via says it travels over a reliable transport
+pub fn over_tcp(mut request: rsip::Request) -> rsip::Request {
+    let via = request.via_header().unwrap().typed().unwrap();
+    request.headers.unique_push(
+        rsip::typed::Via {
+            transport: rsip::Transport::Tcp,
+            ..via
+        }
+        .into(),
+    );
+
+    request
+}
+
+pub fn transaction_id_of(request: &rsip::Request) -> TransactionId {
+    request
+        .transaction_id()
+        .unwrap()
+        .expect("request transaction id")
+        .into()
+}
@@ -0,0 +1,239 @@
+use super::{setup, transaction_id_of};
+use crate::common::{
+    advance_for,
+    extensions::{TransactionUasExt, TransportLayerMsgExt},
+    factories::prelude::*,
+};
+use common::{
+    rsip::{self, prelude::*},
+    SipTimers,
+};
+use models::{rsip_ext::*, tu::TuLayerMsg};
+use std::time::Duration;
+
+fn reliable_invite_request() -> rsip::Request {
+    let mut request = requests::invite_request();
+    request
+        .headers
+        .push(rsip::headers::Supported::new(OPTION_TAG_100REL).into());
+
+    request
+}
+
+#[tokio::test]
+async fn reliable_provisionals_are_retransmitted_until_pracked() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = reliable_invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), None)
+        .await
+        .unwrap();
+    transaction
+        .handler()
+        .reply(request.provisional_of(180))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    let reliable = transport.messages().await.latest().await.outgoing_response();
+    assert_eq!(reliable.status_code, 180.into());
+    assert!(reliable.headers.requires_100rel());
+    assert!(reliable.headers.rseq().is_some());
+
+    advance_for(Duration::from_millis(500)).await;
+    assert_eq!(transport.messages().await.len().await, 3);
+    assert_eq!(
+        transport.messages().await.latest().await.outgoing_response(),
+        reliable
+    );
+
+    let prack = request.prack_request_from(&reliable, 2);
+    transaction
+        .handler()
+        .new_uas(prack.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 4);
+    let prack_response = transport.messages().await.latest().await.outgoing_response();
+    assert_eq!(prack_response.status_code, 200.into());
+    assert_eq!(
+        prack_response.cseq_header().unwrap().typed().unwrap().method,
+        rsip::Method::PRack
+    );
+
+    advance_for(Duration::from_millis(4000)).await;
+    assert_eq!(transport.messages().await.len().await, 4);
+    assert!(
+        transaction
+            .is_uas_proceeding(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn reliable_provisionals_wait_for_the_previous_prack() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = reliable_invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), None)
+        .await
+        .unwrap();
+    transaction
+        .handler()
+        .reply(request.provisional_of(180))
+        .await
+        .unwrap();
+    transaction
+        .handler()
+        .reply(request.provisional_of(183))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    let first = transport.messages().await.latest().await.outgoing_response();
+    transaction
+        .handler()
+        .new_uas(request.prack_request_from(&first, 2), None)
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 4);
+
+    let messages = transport.messages().await;
+    let second = messages.0.lock().await[2].outgoing_response();
+    assert_eq!(second.status_code, 183.into());
+    assert_eq!(second.headers.rseq(), first.headers.rseq().map(|rseq| rseq + 1));
+}
+
+#[tokio::test]
+async fn unacknowledged_reliable_provisional_fails_the_invite() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = reliable_invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), None)
+        .await
+        .unwrap();
+    transaction
+        .handler()
+        .reply(request.provisional_of(180))
+        .await
+        .unwrap();
+
+    let reliable = transport.messages().await.latest().await.outgoing_response();
+
+    advance_for(SipTimers::default().b()).await;
+    let failure = transport.messages().await.latest().await.outgoing_response();
+    assert_eq!(failure.status_code, 500.into());
+    assert_eq!(failure.headers.rseq(), None);
+    assert!(!failure.headers.requires_100rel());
+    assert_eq!(failure.to_header().unwrap(), reliable.to_header().unwrap());
+    assert!(
+        transaction
+            .is_uas_completed(transaction_id_of(&request))
+            .await
+    );
+    assert!(matches!(
+        tu.messages().await.latest().await,
+        TuLayerMsg::TransportError(rsip::SipMessage::Response(response), _)
+            if response.status_code == 500.into()
+    ));
+}
+
+#[tokio::test]
+async fn prack_without_reliable_provisional_gets_481() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = requests::invite_request();
+    transaction
+        .handler()
+        .new_uas_invite(request.clone(), None)
+        .await
+        .unwrap();
+    let mut provisional = request.provisional_of(180);
+    provisional.headers.push(rseq_header(1));
+
+    transaction
+        .handler()
+        .new_uas(request.prack_request_from(&provisional, 2), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        transport
+            .messages()
+            .await
+            .latest()
+            .await
+            .outgoing_response()
+            .status_code,
+        481.into()
+    );
+}
+
+#[tokio::test]
+async fn retransmitted_reliable_provisionals_are_absorbed() {
+    let (tu, transaction, _) = setup().await;
+
+    let request = reliable_invite_request();
+    transaction
+        .handler()
+        .new_uac_invite(request.clone())
+        .await
+        .unwrap();
+
+    let mut provisional = request.provisional_of(180);
+    provisional.headers.push(rseq_header(1));
+    for _ in 0..2 {
+        transaction
+            .handler()
+            .process(provisional.clone().into())
+            .await
+            .unwrap();
+    }
+    assert_eq!(tu.messages().await.len().await, 1);
+
+    let mut next = request.provisional_of(183);
+    next.headers.push(rseq_header(2));
+    transaction
+        .handler()
+        .process(next.into())
+        .await
+        .unwrap();
+    assert_eq!(tu.messages().await.len().await, 2);
+    assert!(matches!(
+        tu.messages().await.latest().await,
+        TuLayerMsg::Incoming(rsip::SipMessage::Response(response))
+            if response.status_code == 183.into()
+    ));
+}
+
+#[tokio::test]
+async fn each_early_dialog_of_a_forked_invite_has_its_own_rseq() {
+    let (tu, transaction, _) = setup().await;
+
+    let request = reliable_invite_request();
+    transaction
+        .handler()
+        .new_uac_invite(request.clone())
+        .await
+        .unwrap();
+
+    //each provisional gets a To-tag of its own, as if it came from another fork
+    let mut first_fork = request.provisional_of(180);
+    first_fork.headers.push(rseq_header(1));
+    let mut second_fork = request.provisional_of(180);
+    second_fork.headers.push(rseq_header(4000));
+    for provisional in &[first_fork.clone(), second_fork, first_fork] {
+        transaction
+            .handler()
+            .process(provisional.clone().into())
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(tu.messages().await.len().await, 2);
+}
@@ -0,0 +1,39 @@
+use super::transaction_id_of;
+use crate::common::factories::prelude::*;
+use common::tokio::time::{self, Duration, Instant};
+use sip_server::transaction::Scheduler;
+
+#[tokio::test]
+async fn wakes_transactions_in_deadline_order() {
+    time::pause();
+    let (scheduler, mut deadlines) = Scheduler::new();
+    let first = transaction_id_of(&requests::options_request());
+    let second = transaction_id_of(&requests::options_request());
+
+    scheduler.schedule(second.clone(), Some(Instant::now() + Duration::from_millis(500)));
+    scheduler.schedule(first.clone(), Some(Instant::now() + Duration::from_millis(200)));
+
+    assert_eq!(deadlines.next().await, Some(first));
+    assert_eq!(deadlines.next().await, Some(second));
+}
+
+#[tokio::test]
+async fn rescheduling_replaces_the_previous_deadline() {
+    time::pause();
+    let (scheduler, mut deadlines) = Scheduler::new();
+    let rescheduled = transaction_id_of(&requests::options_request());
+    let unscheduled = transaction_id_of(&requests::options_request());
+    let other = transaction_id_of(&requests::options_request());
+
+    scheduler.schedule(rescheduled.clone(), Some(Instant::now() + Duration::from_millis(100)));
+    scheduler.schedule(unscheduled.clone(), Some(Instant::now() + Duration::from_millis(200)));
+    scheduler.schedule(other.clone(), Some(Instant::now() + Duration::from_millis(300)));
+    scheduler.schedule(rescheduled.clone(), Some(Instant::now() + Duration::from_millis(400)));
+    scheduler.schedule(unscheduled, None);
+
+    assert_eq!(deadlines.next().await, Some(other));
+    assert_eq!(deadlines.next().await, Some(rescheduled));
+
+    drop(scheduler);
+    assert_eq!(deadlines.next().await, None);
+}
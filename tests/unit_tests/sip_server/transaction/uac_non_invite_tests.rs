@@ -0,0 +1,264 @@
+use super::{over_tcp, setup, transaction_id_of};
+use crate::common::{advance_for, extensions::TransactionUacNonInviteExt, factories::prelude::*};
+use common::{
+    rsip::{self, prelude::*},
+    timer_overrides_from, SipTimers, TimersConfig,
+};
+use models::{
+    transport::{TransportError, TransportLayerMsg},
+    tu::TuLayerMsg,
+};
+use sip_server::Transaction;
+use std::{net::IpAddr, time::Duration};
+
+#[tokio::test]
+async fn if_peer_not_responding() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert!(
+        transaction
+            .is_non_invite_uac_trying(transaction_id_of(&request))
+            .await
+    );
+
+    //timer E doubles up to T2
+    advance_for(Duration::from_millis(500)).await;
+    assert_eq!(transport.messages().await.len().await, 2);
+    advance_for(Duration::from_millis(1000)).await;
+    assert_eq!(transport.messages().await.len().await, 3);
+    advance_for(Duration::from_millis(2000)).await;
+    assert_eq!(transport.messages().await.len().await, 4);
+    advance_for(Duration::from_millis(4000)).await;
+    assert_eq!(transport.messages().await.len().await, 5);
+    advance_for(Duration::from_millis(4000)).await;
+    assert_eq!(transport.messages().await.len().await, 6);
+
+    //timer F
+    advance_for(Duration::from_millis(21000)).await;
+    assert!(matches!(
+        transport.messages().await.latest().await,
+        TransportLayerMsg::Failover(failover) if failover == request
+    ));
+    assert!(
+        transaction
+            .is_non_invite_uac_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn with_destination_override_uses_its_timers() {
+    let (handlers, receivers) = models::channels_builder();
+    let transport = crate::common::snitches::SpySnitch::new(handlers.clone(), receivers.transport)
+        .expect("transport");
+    let default = SipTimers::default();
+    let transaction = Transaction::with_timers(
+        handlers,
+        TimersConfig {
+            default,
+            overrides: timer_overrides_from("10.1.0.0/16=t1:2000", default),
+            ..Default::default()
+        },
+        receivers.transaction,
+    )
+    .expect("transaction");
+
+    let mut request = requests::options_request();
+    request.uri = request
+        .uri
+        .with_host(rsip::HostWithPort::from("10.1.2.3".parse::<IpAddr>().unwrap()));
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    //the default T1 of 500ms does not apply to this destination
+    advance_for(Duration::from_millis(1000)).await;
+    assert_eq!(transport.messages().await.len().await, 1);
+    advance_for(Duration::from_millis(1100)).await;
+    assert_eq!(transport.messages().await.len().await, 2);
+}
+
+#[tokio::test]
+async fn with_domain_target_uses_the_override_of_its_address() {
+    let (handlers, receivers) = models::channels_builder();
+    let transport = crate::common::snitches::SpySnitch::new(handlers.clone(), receivers.transport)
+        .expect("transport");
+    let default = SipTimers::default();
+    let transaction = Transaction::with_timers(
+        handlers,
+        TimersConfig {
+            default,
+            overrides: timer_overrides_from("10.1.0.0/16=t1:2000", default),
+            ..Default::default()
+        },
+        receivers.transaction,
+    )
+    .expect("transaction");
+
+    let mut request = requests::options_request();
+    request.uri = request.uri.with_host(rsip::HostWithPort {
+        host: rsip::Host::Domain(rsip::Domain::from("sat.example.com".to_string())),
+        port: None,
+    });
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+    transaction
+        .handler()
+        .resolved(
+            request.clone(),
+            "10.1.2.3:5060".parse().unwrap(),
+            rsip::Transport::Udp,
+        )
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    advance_for(Duration::from_millis(1000)).await;
+    assert_eq!(transport.messages().await.len().await, 1);
+    advance_for(Duration::from_millis(1100)).await;
+    assert_eq!(transport.messages().await.len().await, 2);
+}
+
+#[test]
+fn overrides_with_timers_that_cannot_work_are_ignored() {
+    let default = SipTimers::default();
+
+    assert!(timer_overrides_from("10.1.0.0/16=t1:0", default).is_empty());
+    assert!(timer_overrides_from("10.1.0.0/16=t1:5000,t2:4000", default).is_empty());
+    assert_eq!(timer_overrides_from("10.1.0.0/16=t1:4000", default).len(), 1);
+}
+
+#[tokio::test]
+async fn with_provisional_goes_through_proceeding() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+
+    let response = responses::trying_response_from(request.clone());
+    transaction.handler().process(response.into()).await.unwrap();
+
+    assert_eq!(tu.messages().await.len().await, 1);
+    assert!(
+        transaction
+            .is_non_invite_uac_proceeding(transaction_id_of(&request))
+            .await
+    );
+
+    //in proceeding, retransmissions happen every T2
+    advance_for(Duration::from_millis(2000)).await;
+    assert_eq!(transport.messages().await.len().await, 1);
+    advance_for(Duration::from_millis(2000)).await;
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    let response = responses::ok_response_from(request.clone());
+    transaction
+        .handler()
+        .process(response.clone().into())
+        .await
+        .unwrap();
+
+    assert_eq!(tu.messages().await.len().await, 2);
+    assert!(
+        transaction
+            .is_non_invite_uac_completed(transaction_id_of(&request))
+            .await
+    );
+
+    //retransmissions of the final response never reach the TU
+    transaction.handler().process(response.into()).await.unwrap();
+    assert_eq!(tu.messages().await.len().await, 2);
+
+    advance_for(SipTimers::default().k() + Duration::from_millis(100)).await;
+    assert_eq!(transport.messages().await.len().await, 2);
+    assert!(
+        transaction
+            .is_non_invite_uac_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn over_reliable_transport_neither_retransmits_nor_lingers() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = over_tcp(requests::options_request());
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+
+    advance_for(Duration::from_millis(4000)).await;
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    let response = responses::request_failure_response_from(request.clone());
+    transaction.handler().process(response.into()).await.unwrap();
+    assert_eq!(tu.messages().await.len().await, 1);
+
+    advance_for(Duration::from_millis(200)).await;
+    assert!(
+        transaction
+            .is_non_invite_uac_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn sent_over_another_transport_than_its_via_follows_that_transport() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+    transaction
+        .handler()
+        .resolved(
+            request.clone(),
+            "10.1.2.3:5060".parse().unwrap(),
+            rsip::Transport::Tcp,
+        )
+        .await
+        .unwrap();
+
+    advance_for(Duration::from_millis(4000)).await;
+    assert_eq!(transport.messages().await.len().await, 1);
+
+    let response = responses::request_failure_response_from(request.clone());
+    transaction.handler().process(response.into()).await.unwrap();
+    assert_eq!(tu.messages().await.len().await, 1);
+
+    advance_for(Duration::from_millis(200)).await;
+    assert!(
+        transaction
+            .is_non_invite_uac_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn with_transport_error_notifies_tu() {
+    let (tu, transaction, _) = setup().await;
+
+    let request = requests::options_request();
+    transaction.handler().new_uac(request.clone()).await.unwrap();
+
+    transaction
+        .handler()
+        .transport_error(
+            request.clone().into(),
+            TransportError::ConnectionRefused("127.0.0.1:5060".into()),
+        )
+        .await
+        .unwrap();
+
+    assert!(
+        transaction
+            .is_non_invite_uac_errored(transaction_id_of(&request))
+            .await
+    );
+    assert!(matches!(
+        tu.messages().await.latest().await,
+        TuLayerMsg::TransportError(
+            rsip::SipMessage::Request(failed),
+            TransportError::ConnectionRefused(_)
+        ) if failed == request
+    ));
+}
@@ -1,7 +1,10 @@
 use super::setup;
 use crate::common::{advance_for, extensions::TransactionUacExt, factories::prelude::*};
-use common::rsip::{self, prelude::*};
-use sip_server::transaction::sm::uac::TIMER_M;
+use common::{
+    rsip::{self, prelude::*},
+    SipTimers,
+};
+use models::transport::TransportLayerMsg;
 use std::time::Duration;
 
 #[tokio::test]
@@ -41,8 +44,12 @@ async fn if_peer_not_responding() {
     assert_eq!(transport.messages().await.len().await, 6);
     advance_for(Duration::from_millis(16000)).await;
     assert_eq!(transport.messages().await.len().await, 7);
-    advance_for(Duration::from_millis(50000)).await;
-    assert_eq!(transport.messages().await.len().await, 7);
+    advance_for(Duration::from_millis(1000)).await;
+    assert_eq!(transport.messages().await.len().await, 8);
+    assert!(matches!(
+        transport.messages().await.latest().await,
+        TransportLayerMsg::Failover(failover) if failover == request
+    ));
     assert_eq!(transaction.inner.state.read().await.len(), 1);
     assert!(
         transaction
@@ -126,7 +133,7 @@ async fn with_trying_goes_through_proceeding() {
             .await
     );
 
-    advance_for(Duration::from_millis(TIMER_M)).await;
+    advance_for(SipTimers::default().m()).await;
 
     assert!(
         transaction
@@ -191,7 +198,7 @@ async fn request_failure_goes_through_completed() {
             .await
     );
 
-    advance_for(Duration::from_millis(TIMER_M)).await;
+    advance_for(SipTimers::default().d()).await;
 
     assert!(
         transaction
@@ -281,7 +288,7 @@ async fn multiple_request_failure_goes_through_completed() {
             .await
     );
 
-    advance_for(Duration::from_millis(TIMER_M)).await;
+    advance_for(SipTimers::default().d()).await;
 
     assert!(
         transaction
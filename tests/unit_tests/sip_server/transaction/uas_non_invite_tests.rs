@@ -0,0 +1,177 @@
+use super::{over_tcp, setup, transaction_id_of};
+use crate::common::{advance_for, extensions::TransactionUasNonInviteExt, factories::prelude::*};
+use common::{rsip, SipTimers};
+use models::{rsip_ext::*, transport::TransportLayerMsg};
+use std::time::Duration;
+
+#[tokio::test]
+async fn with_final_response_absorbs_retransmissions() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    let response = responses::ok_response_from(request.clone());
+    transaction
+        .handler()
+        .new_uas(request.clone(), Some(response.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert!(
+        transaction
+            .is_non_invite_uas_completed(transaction_id_of(&request))
+            .await
+    );
+
+    //a retransmission of the request gets the final response again
+    transaction
+        .handler()
+        .process(request.clone().into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+    assert!(matches!(
+        transport.messages().await.latest().await,
+        TransportLayerMsg::Outgoing(msg) if msg == rsip::SipMessage::from(response.clone())
+    ));
+    assert_eq!(tu.messages().await.len().await, 0);
+
+    //timer J
+    advance_for(SipTimers::default().j() + Duration::from_millis(100)).await;
+    assert!(
+        transaction
+            .is_non_invite_uas_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn with_provisional_goes_through_proceeding() {
+    let (_, transaction, transport) = setup().await;
+
+    let request = requests::options_request();
+    transaction
+        .handler()
+        .new_uas(request.clone(), None)
+        .await
+        .unwrap();
+    assert!(
+        transaction
+            .is_non_invite_uas_trying(transaction_id_of(&request))
+            .await
+    );
+
+    //nothing to resend yet
+    transaction
+        .handler()
+        .process(request.clone().into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 0);
+
+    transaction
+        .handler()
+        .reply(request.provisional_of(100))
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert!(
+        transaction
+            .is_non_invite_uas_proceeding(transaction_id_of(&request))
+            .await
+    );
+
+    transaction
+        .handler()
+        .process(request.clone().into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 2);
+
+    let response = responses::ok_response_from(request.clone());
+    transaction
+        .handler()
+        .reply(response.clone())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 3);
+    assert!(
+        transaction
+            .is_non_invite_uas_completed(transaction_id_of(&request))
+            .await
+    );
+
+    //further responses from the TU are discarded
+    transaction.handler().reply(response).await.unwrap();
+    assert_eq!(transport.messages().await.len().await, 3);
+}
+
+#[tokio::test]
+async fn over_reliable_transport_does_not_linger() {
+    let (_, transaction, _) = setup().await;
+
+    let request = over_tcp(requests::options_request());
+    let response = responses::ok_response_from(request.clone());
+    transaction
+        .handler()
+        .new_uas(request.clone(), Some(response))
+        .await
+        .unwrap();
+
+    advance_for(Duration::from_millis(200)).await;
+    assert!(
+        transaction
+            .is_non_invite_uas_terminated(transaction_id_of(&request))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn terminated_transactions_are_reaped_after_lingering() {
+    let (tu, transaction, transport) = setup().await;
+
+    let request = over_tcp(requests::options_request());
+    let response = responses::ok_response_from(request.clone());
+    transaction
+        .handler()
+        .new_uas(request.clone(), Some(response))
+        .await
+        .unwrap();
+
+    advance_for(Duration::from_millis(200)).await;
+    assert!(
+        transaction
+            .is_non_invite_uas_terminated(transaction_id_of(&request))
+            .await
+    );
+
+    //while lingering, the transaction is still found and absorbs late retransmissions
+    let has_transaction = transaction
+        .handler()
+        .has_transaction_for(transaction_id_of(&request))
+        .await
+        .unwrap();
+    assert!(has_transaction);
+    transaction
+        .handler()
+        .process(request.clone().into())
+        .await
+        .unwrap();
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert_eq!(tu.messages().await.len().await, 0);
+    assert!(
+        transaction
+            .is_non_invite_uas_terminated(transaction_id_of(&request))
+            .await
+    );
+
+    advance_for(common::CONFIG.timers.transaction_linger).await;
+    let has_transaction = transaction
+        .handler()
+        .has_transaction_for(transaction_id_of(&request))
+        .await
+        .unwrap();
+    assert!(!has_transaction);
+    assert_eq!(transaction.transactions().await, 0);
+    assert_eq!(transaction.reaped_transactions(), 1);
+}
@@ -1,7 +1,13 @@
 use super::setup;
 use crate::common::{advance_for, extensions::TransactionUasExt, factories::prelude::*};
-use common::rsip::{self, prelude::*};
-use models::{rsip_ext::*, transport::TransportLayerMsg};
+use common::{
+    rsip::{self, prelude::*},
+    SipTimers,
+};
+use models::{
+    rsip_ext::*,
+    transport::{TransportError, TransportLayerMsg},
+};
 use std::time::Duration;
 
 /* ##### proceeding state ##### */
@@ -22,7 +28,10 @@ async fn if_peer_not_alive() {
 
     transaction
         .handler()
-        .transport_error(request.clone().into(), "some error".into())
+        .transport_error(
+            request.clone().into(),
+            TransportError::Other("some error".into()),
+        )
         .await
         .unwrap();
 
@@ -369,7 +378,7 @@ async fn ok_but_peer_not_responding_with_ack() {
             .await
     );
 
-    advance_for(Duration::from_millis(62 * 1000)).await;
+    advance_for(SipTimers::default().l()).await;
     assert_eq!(transport.messages().await.len().await, 2);
 
     assert!(
@@ -479,7 +488,10 @@ async fn with_error_on_second_ok_on_accepted() {
 
     transaction
         .handler()
-        .transport_error(request.clone().into(), "some error".into())
+        .transport_error(
+            request.clone().into(),
+            TransportError::Other("some error".into()),
+        )
         .await
         .unwrap();
 
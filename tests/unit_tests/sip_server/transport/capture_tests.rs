@@ -0,0 +1,158 @@
+use crate::common::factories::prelude::*;
+use common::{
+    bytes::Bytes,
+    rsip::{self, headers::UntypedHeader, prelude::*},
+    tokio::net::UdpSocket,
+    HepConfig, PcapConfig,
+};
+use sip_server::transport::capture::{hep_encode, CapturedMessage, HepCollector, PcapWriter};
+use std::time::{Duration, SystemTime};
+
+fn captured_message(bytes: &[u8]) -> CapturedMessage {
+    CapturedMessage {
+        bytes,
+        src: "192.0.2.1:5060".parse().unwrap(),
+        dst: "192.0.2.2:5070".parse().unwrap(),
+        transport: rsip::Transport::Udp,
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+    }
+}
+
+fn pcap_config(max_file_size: u64, max_files: usize) -> PcapConfig {
+    PcapConfig {
+        dir: std::env::temp_dir()
+            .join(format!("viska-pcap-{}", ::common::uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned(),
+        max_file_size,
+        max_files,
+    }
+}
+
+#[tokio::test]
+async fn hep_packets_reach_the_collector() -> Result<(), sip_server::Error> {
+    let collector = UdpSocket::bind("127.0.0.1:0").await?;
+    let hep = HepCollector::new(HepConfig {
+        collector: collector.local_addr()?,
+        capture_id: 42,
+        password: None,
+    })?;
+
+    let request = requests::request(None, None);
+    let call_id = request.call_id_header()?.value().to_string();
+    let bytes: Bytes = request.into();
+    hep.send(&captured_message(&bytes)).await;
+
+    let mut packet = vec![0; 65535];
+    let len = collector.recv(&mut packet).await?;
+    let packet = &packet[..len];
+
+    assert_eq!(&packet[..4], b"HEP3");
+    assert_eq!(u16::from_be_bytes([packet[4], packet[5]]) as usize, len);
+    assert!(packet.ends_with(&bytes));
+    assert!(packet
+        .windows(call_id.len())
+        .any(|window| window == call_id.as_bytes()));
+    assert!(packet.windows(4).any(|window| window == [192, 0, 2, 1]));
+    assert!(packet
+        .windows(4)
+        .any(|window| window == 1_600_000_000u32.to_be_bytes()));
+
+    Ok(())
+}
+
+#[test]
+fn messages_over_64kb_are_not_encoded() {
+    let bytes = vec![b'a'; 70000];
+
+    assert!(hep_encode(
+        &CapturedMessage {
+            bytes: &bytes,
+            src: "192.0.2.1:5060".parse().unwrap(),
+            dst: "192.0.2.2:5060".parse().unwrap(),
+            transport: rsip::Transport::Tcp,
+            timestamp: SystemTime::now(),
+        },
+        0,
+        None
+    )
+    .is_none());
+}
+
+#[test]
+fn pcap_records_are_wrapped_in_ip_and_udp_headers() -> Result<(), sip_server::Error> {
+    let pcap = PcapWriter::new(pcap_config(1024 * 1024, 2))?;
+    let bytes: Bytes = requests::request(None, None).into();
+
+    pcap.write(&captured_message(&bytes));
+    pcap.sync();
+
+    let files = pcap.files();
+    assert_eq!(files.len(), 1);
+    let file = std::fs::read(&files[0])?;
+
+    assert_eq!(&file[..4], &0xa1b2_c3d4u32.to_le_bytes());
+    assert_eq!(&file[20..24], &101u32.to_le_bytes());
+    let record = &file[24..];
+    let packet_len = 20 + 8 + bytes.len();
+    assert_eq!(&record[8..12], &(packet_len as u32).to_le_bytes());
+    let packet = &record[16..];
+    assert_eq!(packet.len(), packet_len);
+    assert_eq!(packet[9], 17);
+    assert_eq!(&packet[12..20], &[192, 0, 2, 1, 192, 0, 2, 2]);
+    assert_eq!(&packet[20..24], &[0x13, 0xc4, 0x13, 0xce]);
+    assert_eq!(&packet[28..], &bytes[..]);
+
+    Ok(())
+}
+
+#[test]
+fn pcap_files_are_rotated() -> Result<(), sip_server::Error> {
+    let bytes: Bytes = requests::request(None, None).into();
+    //room for a single record in each file
+    let pcap = PcapWriter::new(pcap_config(24 + 16 + 28 + bytes.len() as u64, 2))?;
+
+    for _ in 0..5 {
+        pcap.write(&captured_message(&bytes));
+    }
+    pcap.sync();
+
+    let files = pcap.files();
+    assert_eq!(files.len(), 2);
+    let dir = files[0].parent().expect("pcap dir");
+    assert_eq!(std::fs::read_dir(dir)?.count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn pcap_tcp_sequence_numbers_start_over_in_each_file() -> Result<(), sip_server::Error> {
+    let bytes: Bytes = requests::request(None, None).into();
+    let record_len = 16 + 40 + bytes.len();
+    //room for two records in each file
+    let pcap = PcapWriter::new(pcap_config(24 + 2 * record_len as u64, 3))?;
+    let message = CapturedMessage {
+        transport: rsip::Transport::Tcp,
+        ..captured_message(&bytes)
+    };
+
+    for _ in 0..3 {
+        pcap.write(&message);
+    }
+    pcap.sync();
+
+    let files = pcap.files();
+    assert_eq!(files.len(), 2);
+    //after the record header and the IPv4 header
+    let seq_of = |record: &[u8]| {
+        u32::from_be_bytes([record[40], record[41], record[42], record[43]])
+    };
+
+    let first = std::fs::read(&files[0])?;
+    assert_eq!(seq_of(&first[24..]), 1);
+    assert_eq!(seq_of(&first[24 + record_len..]), 1 + bytes.len() as u32);
+    let second = std::fs::read(&files[1])?;
+    assert_eq!(seq_of(&second[24..]), 1);
+
+    Ok(())
+}
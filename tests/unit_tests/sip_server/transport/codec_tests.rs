@@ -0,0 +1,80 @@
+use crate::common::factories::prelude::*;
+use common::{
+    bytes::{Bytes, BytesMut},
+    rsip,
+    tokio_util::codec::Decoder,
+};
+use sip_server::transport::stream::SipCodec;
+use std::convert::TryInto;
+
+#[test]
+fn decodes_consecutive_messages_from_a_stream() -> Result<(), sip_server::Error> {
+    let first: Bytes = requests::request(None, None).into();
+    let second: Bytes = requests::options_request().into();
+
+    let mut buffer = BytesMut::new();
+    buffer.extend_from_slice(&first);
+    buffer.extend_from_slice(&second);
+
+    let mut codec = SipCodec::default();
+    assert_eq!(codec.decode(&mut buffer)?, Some(first));
+    assert_eq!(codec.decode(&mut buffer)?, Some(second));
+    assert_eq!(codec.decode(&mut buffer)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn waits_until_the_whole_body_has_arrived() -> Result<(), sip_server::Error> {
+    let mut request = requests::invite_request();
+    request.body = b"v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\n".to_vec();
+    request
+        .headers
+        .unique_push(rsip::headers::ContentLength::new(request.body.len().to_string()).into());
+    let bytes: Bytes = request.clone().into();
+
+    let mut codec = SipCodec::default();
+    let mut buffer = BytesMut::from(&bytes[..bytes.len() - 5]);
+    assert_eq!(codec.decode(&mut buffer)?, None);
+
+    buffer.extend_from_slice(&bytes[bytes.len() - 5..]);
+    let decoded: rsip::Request = codec.decode(&mut buffer)?.expect("decoded message").try_into()?;
+    assert_eq!(decoded.body, request.body);
+
+    Ok(())
+}
+
+#[test]
+fn passes_keepalive_pings_between_messages() -> Result<(), sip_server::Error> {
+    let request: Bytes = requests::request(None, None).into();
+    let mut codec = SipCodec::default();
+
+    let mut buffer = BytesMut::from(&b"\r\n\r\n"[..]);
+    buffer.extend_from_slice(&request);
+
+    assert_eq!(codec.decode(&mut buffer)?, Some(Bytes::from_static(b"\r\n\r\n")));
+    assert_eq!(codec.decode(&mut buffer)?, Some(request));
+
+    Ok(())
+}
+
+#[test]
+fn skips_keepalive_pongs() -> Result<(), sip_server::Error> {
+    let request: Bytes = requests::request(None, None).into();
+
+    let mut buffer = BytesMut::from(&b"\r\n"[..]);
+    buffer.extend_from_slice(&request);
+
+    assert_eq!(SipCodec::default().decode(&mut buffer)?, Some(request));
+
+    Ok(())
+}
+
+#[test]
+fn fails_without_content_length() {
+    let mut buffer = BytesMut::from(
+        &b"OPTIONS sip:127.0.0.1 SIP/2.0\r\nCall-ID: 1234\r\nCSeq: 1 OPTIONS\r\n\r\n"[..],
+    );
+
+    assert!(SipCodec::default().decode(&mut buffer).is_err());
+}
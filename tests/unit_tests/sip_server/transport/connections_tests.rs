@@ -0,0 +1,22 @@
+use common::rsip;
+use sip_server::transport::stream::Connections;
+use std::{net::SocketAddr, sync::Arc};
+
+#[tokio::test]
+async fn connections_are_only_found_by_their_exact_address() {
+    let (handlers, _receivers) = models::channels_builder();
+    let connections = Arc::new(Connections::new(rsip::Transport::Tcp, handlers));
+
+    //two UAs behind the same NAT
+    let alice: SocketAddr = "203.0.113.1:40001".parse().unwrap();
+    let bob: SocketAddr = "203.0.113.1:40002".parse().unwrap();
+    let (alice_stream, _alice_remote) = tokio::io::duplex(1024);
+    connections.add(alice_stream, alice).await;
+
+    assert!(connections.find(&alice).await.is_some());
+    assert!(connections.find(&bob).await.is_none());
+    assert!(connections
+        .find(&"203.0.113.1:5060".parse().unwrap())
+        .await
+        .is_none());
+}
@@ -0,0 +1,229 @@
+use crate::common::factories::prelude::*;
+use common::{
+    async_trait::async_trait,
+    rsip::{self, prelude::*},
+};
+use sip_server::transport::{
+    dns::{
+        weighted_order, CachingResolver, DnsAnswer, DnsResolver, NaptrRecord, SrvRecord,
+        StaticResolver, Target,
+    },
+    DnsLookup, StaticDnsLookup,
+};
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+const RECORDS: &str = "
+    # tcp is preferred over udp
+    NAPTR example.com 20 50 SIP+D2U _sip._udp.example.com
+    NAPTR example.com 10 50 SIP+D2T _sip._tcp.example.com
+    SRV _sip._tcp.example.com 10 20 5070 sip2.example.com
+    SRV _sip._tcp.example.com 10 80 5060 sip1.example.com
+    SRV _sip._udp.example.com 10 50 5060 sip1.example.com
+    A sip1.example.com 192.0.2.1
+    192.0.2.2 sip2.example.com
+";
+
+fn uri_for(domain: &str) -> rsip::Uri {
+    rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: rsip::HostWithPort {
+            host: rsip::Host::Domain(rsip::Domain::from(domain.to_string())),
+            port: None,
+        },
+        ..Default::default()
+    }
+}
+
+fn target(ip_addr: &str, port: u16, transport: rsip::Transport) -> Target {
+    Target {
+        ip_addr: ip_addr.parse().expect("ip addr"),
+        port,
+        transport,
+    }
+}
+
+#[tokio::test]
+async fn static_lookup_follows_naptr_srv_and_a_records() -> Result<(), sip_server::Error> {
+    let dns_lookup = StaticDnsLookup::with_resolver(RECORDS.parse()?);
+
+    let targets = Vec::from(dns_lookup.targets_for(&uri_for("example.com")).await?);
+
+    //both tcp records share a priority, so their order is up to their weights
+    assert_eq!(targets.len(), 3);
+    assert!(targets[..2].contains(&target("192.0.2.1", 5060, rsip::Transport::Tcp)));
+    assert!(targets[..2].contains(&target("192.0.2.2", 5070, rsip::Transport::Tcp)));
+    assert_eq!(targets[2], target("192.0.2.1", 5060, rsip::Transport::Udp));
+
+    Ok(())
+}
+
+fn srv_record(priority: u16, weight: u16, target: &str) -> SrvRecord {
+    SrvRecord {
+        priority,
+        weight,
+        port: 5060,
+        target: target.into(),
+    }
+}
+
+#[test]
+fn srv_records_are_picked_by_weight_within_a_priority() {
+    use common::rand::SeedableRng;
+
+    let records = vec![
+        srv_record(20, 100, "backup.example.com"),
+        srv_record(10, 20, "light.example.com"),
+        srv_record(10, 80, "heavy.example.com"),
+    ];
+    let mut rng = common::rand_chacha::ChaCha8Rng::seed_from_u64(2782);
+
+    let mut heavy_first = 0;
+    for _ in 0..1000 {
+        let ordered = weighted_order(records.clone(), &mut rng);
+
+        assert_eq!(ordered.len(), 3);
+        assert_eq!(ordered[2].target, "backup.example.com");
+        if ordered[0].target == "heavy.example.com" {
+            heavy_first += 1;
+        }
+    }
+
+    //80% of the time, give or take
+    assert!((700..900).contains(&heavy_first), "heavy first {} times", heavy_first);
+}
+
+#[test]
+fn srv_records_of_zero_weight_are_picked_last() {
+    use common::rand::SeedableRng;
+
+    let records = vec![
+        srv_record(10, 0, "idle.example.com"),
+        srv_record(10, 10, "busy.example.com"),
+    ];
+    let mut rng = common::rand_chacha::ChaCha8Rng::seed_from_u64(2782);
+
+    let zero_first = (0..1000)
+        .filter(|_| weighted_order(records.clone(), &mut rng)[0].target == "idle.example.com")
+        .count();
+
+    assert!(zero_first < 200, "zero weight first {} times", zero_first);
+}
+
+#[tokio::test]
+async fn static_lookup_fails_over_to_next_target() -> Result<(), sip_server::Error> {
+    let dns_lookup = StaticDnsLookup::with_resolver(
+        StaticResolver::default()
+            .with_srv(
+                "_sip._udp.example.com",
+                SrvRecord {
+                    priority: 10,
+                    weight: 0,
+                    port: 5060,
+                    target: "sip1.example.com".into(),
+                },
+            )
+            .with_srv(
+                "_sip._udp.example.com",
+                SrvRecord {
+                    priority: 20,
+                    weight: 0,
+                    port: 5060,
+                    target: "sip2.example.com".into(),
+                },
+            )
+            .with_ip("sip1.example.com", "192.0.2.1".parse().expect("ip addr"))
+            .with_ip("sip2.example.com", "192.0.2.2".parse().expect("ip addr")),
+    );
+    let mut request = requests::request(None, None);
+    request.uri = uri_for("example.com");
+    request
+        .uri
+        .params
+        .push(rsip::Param::Transport(rsip::Transport::Udp));
+    let transaction_id = request.transaction_id()?.expect("transaction id");
+
+    let request_msg = dns_lookup.request_msg_from(request.clone()).await?;
+    assert_eq!(request_msg.peer, "192.0.2.1:5060".parse().expect("socket addr"));
+
+    let transport_msg = dns_lookup
+        .failover(&transaction_id, request.clone().into())
+        .await?
+        .expect("second target");
+    assert_eq!(transport_msg.peer, "192.0.2.2:5060".parse().expect("socket addr"));
+
+    assert!(dns_lookup
+        .failover(&transaction_id, request.into())
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn static_lookup_forgets_targets_after_the_lookup_ttl() -> Result<(), sip_server::Error> {
+    let dns_lookup = StaticDnsLookup::with_lookup_ttl(RECORDS.parse()?, Duration::from_secs(32));
+    let mut request = requests::request(None, None);
+    request.uri = uri_for("example.com");
+    let transaction_id = request.transaction_id()?.expect("transaction id");
+    dns_lookup.request_msg_from(request.clone()).await?;
+
+    advance_for(Duration::from_secs(33)).await;
+    let mut other_request = requests::request(None, None);
+    other_request.uri = uri_for("example.com");
+    dns_lookup.request_msg_from(other_request).await?;
+
+    assert!(dns_lookup
+        .failover(&transaction_id, request.into())
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct CountingResolver {
+    resolver: StaticResolver,
+    queries: AtomicUsize,
+}
+
+#[async_trait]
+impl DnsResolver for CountingResolver {
+    async fn naptr_lookup(
+        &self,
+        domain: &str,
+    ) -> Result<DnsAnswer<NaptrRecord>, sip_server::Error> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        self.resolver.naptr_lookup(domain).await
+    }
+
+    async fn srv_lookup(&self, name: &str) -> Result<DnsAnswer<SrvRecord>, sip_server::Error> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        self.resolver.srv_lookup(name).await
+    }
+
+    async fn ip_lookup(&self, domain: &str) -> Result<DnsAnswer<IpAddr>, sip_server::Error> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        self.resolver.ip_lookup(domain).await
+    }
+}
+
+#[tokio::test]
+async fn caching_resolver_caches_missing_records_until_ttl_expires(
+) -> Result<(), sip_server::Error> {
+    let resolver = CachingResolver::new(CountingResolver::default());
+
+    assert!(resolver.naptr_lookup("example.com").await?.records.is_empty());
+    assert!(resolver.naptr_lookup("EXAMPLE.com.").await?.records.is_empty());
+    assert_eq!(resolver.inner().queries.load(Ordering::SeqCst), 1);
+
+    advance_for(Duration::from_secs(3601)).await;
+
+    assert!(resolver.naptr_lookup("example.com").await?.records.is_empty());
+    assert_eq!(resolver.inner().queries.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::common::{factories::prelude::*, snitches::SpySnitch};
+use common::{
+    bytes::Bytes,
+    rsip::{self, prelude::*},
+    ListenerConfig,
+};
+use models::{
+    transaction::TransactionLayerMsg,
+    transport::{TransportLayerMsg, TransportMsg, TransportTuple},
+    tu::TuLayerMsg,
+};
+use sip_server::transport::{
+    dns::StaticResolver, DefaultProcessor, Listeners, LoopbackNetwork, RateLimitingProcessor,
+    RateLimits, StaticDnsLookup, Transport, TransportProcessor,
+};
+use std::{convert::TryFrom, net::SocketAddr};
+
+fn alice_addr() -> SocketAddr {
+    "192.0.2.1:5060".parse().unwrap()
+}
+
+fn bob_addr() -> SocketAddr {
+    "192.0.2.2:5060".parse().unwrap()
+}
+
+fn udp_listener(addr: SocketAddr) -> ListenerConfig {
+    ListenerConfig {
+        addr,
+        transport: rsip::Transport::Udp,
+    }
+}
+
+#[tokio::test]
+async fn listeners_exchange_bytes_without_sockets() -> Result<(), sip_server::Error> {
+    let network = LoopbackNetwork::new();
+
+    let (alice_handlers, _alice_receivers) = models::channels_builder();
+    let alice = Listeners::loopback(
+        alice_handlers,
+        network.clone(),
+        &[udp_listener(alice_addr())],
+    )?;
+    let (bob_handlers, bob_receivers) = models::channels_builder();
+    let _bob = Listeners::loopback(
+        bob_handlers.clone(),
+        network.clone(),
+        &[udp_listener(bob_addr())],
+    )?;
+    let bob_transport = SpySnitch::new(bob_handlers, bob_receivers.transport).expect("transport");
+
+    alice
+        .send(
+            TransportTuple {
+                bytes: Bytes::from_static(b"hello bob"),
+                peer: bob_addr(),
+                transport: rsip::Transport::Udp,
+            },
+            None,
+            None,
+        )
+        .await?;
+
+    assert_eq!(bob_transport.messages().await.len().await, 1);
+    match bob_transport.messages().await.first().await {
+        TransportLayerMsg::Incoming(tuple) => {
+            assert_eq!(tuple.bytes, Bytes::from_static(b"hello bob"));
+            assert_eq!(tuple.peer, alice_addr());
+            assert_eq!(tuple.transport, rsip::Transport::Udp);
+        }
+        _ => panic!("not an Incoming variant"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn addresses_are_released_on_drop() -> Result<(), sip_server::Error> {
+    let network = LoopbackNetwork::new();
+    let (handlers, _receivers) = models::channels_builder();
+
+    let listeners =
+        Listeners::loopback(handlers.clone(), network.clone(), &[udp_listener(bob_addr())])?;
+    assert!(network.is_bound(&bob_addr()));
+    assert!(
+        Listeners::loopback(handlers.clone(), network.clone(), &[udp_listener(bob_addr())])
+            .is_err()
+    );
+
+    drop(listeners);
+    assert!(!network.is_bound(&bob_addr()));
+    assert!(Listeners::loopback(handlers, network, &[udp_listener(bob_addr())]).is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sending_to_an_unbound_address_fails() -> Result<(), sip_server::Error> {
+    let network = LoopbackNetwork::new();
+    let (handlers, _receivers) = models::channels_builder();
+    let listeners = Listeners::loopback(handlers, network, &[udp_listener(alice_addr())])?;
+
+    let result = listeners
+        .send(
+            TransportTuple {
+                bytes: Bytes::from_static(b"anyone?"),
+                peer: bob_addr(),
+                transport: rsip::Transport::Udp,
+            },
+            None,
+            None,
+        )
+        .await;
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transports_exchange_sip_messages() -> Result<(), sip_server::Error> {
+    tokio::time::pause();
+    let network = LoopbackNetwork::new();
+
+    let (alice_handlers, alice_receivers) = models::channels_builder();
+    let _alice = Transport::with_listeners(
+        alice_handlers.clone(),
+        DefaultProcessor::default(),
+        StaticDnsLookup::with_resolver(StaticResolver::default()),
+        Listeners::loopback(
+            alice_handlers.clone(),
+            network.clone(),
+            &[udp_listener(alice_addr())],
+        )?,
+        alice_receivers.transport,
+    )?;
+
+    let (bob_handlers, bob_receivers) = models::channels_builder();
+    let _bob = Transport::with_listeners(
+        bob_handlers.clone(),
+        DefaultProcessor::default(),
+        StaticDnsLookup::with_resolver(StaticResolver::default()),
+        Listeners::loopback(
+            bob_handlers.clone(),
+            network.clone(),
+            &[udp_listener(bob_addr())],
+        )?,
+        bob_receivers.transport,
+    )?;
+    let _bob_transaction =
+        sip_server::Transaction::new(bob_handlers.clone(), bob_receivers.transaction)?;
+    let bob_tu = SpySnitch::new(bob_handlers, bob_receivers.tu).expect("tu");
+
+    let mut request = requests::options_request();
+    request.uri = request
+        .uri
+        .with_host(rsip::HostWithPort::from(bob_addr().ip()))
+        .with_port(bob_addr().port());
+    let call_id = request.call_id_header()?.clone();
+    alice_handlers.transport.send(request.into()).await?;
+
+    assert_eq!(bob_tu.messages().await.len().await, 1);
+    match bob_tu.messages().await.first().await {
+        TuLayerMsg::IncomingRequest(msg) => {
+            assert_eq!(msg.sip_request.method, rsip::Method::Options);
+            assert_eq!(msg.sip_request.call_id_header()?, &call_id);
+            assert_eq!(msg.peer, alice_addr());
+            assert_eq!(msg.transport, rsip::Transport::Udp);
+        }
+        _ => panic!("not an incoming request"),
+    }
+
+    Ok(())
+}
+
+fn alice_tcp_addr() -> SocketAddr {
+    "192.0.2.1:5061".parse().unwrap()
+}
+
+//alice can send over both udp and tcp, while bob only records what reaches him
+async fn sending_to_bob(
+    request: rsip::Request,
+) -> Result<(TransportTuple, SpySnitch<TransactionLayerMsg>), sip_server::Error> {
+    let network = LoopbackNetwork::new();
+
+    let (alice_handlers, alice_receivers) = models::channels_builder();
+    let alice_transaction =
+        SpySnitch::new(alice_handlers.clone(), alice_receivers.transaction).expect("transaction");
+    let _alice = Transport::with_listeners(
+        alice_handlers.clone(),
+        DefaultProcessor::default(),
+        StaticDnsLookup::with_resolver(StaticResolver::default()),
+        Listeners::loopback(
+            alice_handlers.clone(),
+            network.clone(),
+            &[
+                udp_listener(alice_addr()),
+                ListenerConfig {
+                    addr: alice_tcp_addr(),
+                    transport: rsip::Transport::Tcp,
+                },
+            ],
+        )?,
+        alice_receivers.transport,
+    )?;
+
+    let (bob_handlers, bob_receivers) = models::channels_builder();
+    let _bob = Listeners::loopback(
+        bob_handlers.clone(),
+        network.clone(),
+        &[udp_listener(bob_addr())],
+    )?;
+    let bob_transport = SpySnitch::new(bob_handlers, bob_receivers.transport).expect("transport");
+
+    let mut request = request;
+    request.uri = request
+        .uri
+        .with_host(rsip::HostWithPort::from(bob_addr().ip()))
+        .with_port(bob_addr().port());
+    alice_handlers.transport.send(request.into()).await?;
+
+    assert_eq!(bob_transport.messages().await.len().await, 1);
+    match bob_transport.messages().await.first().await {
+        TransportLayerMsg::Incoming(tuple) => Ok((tuple, alice_transaction)),
+        _ => panic!("not an Incoming variant"),
+    }
+}
+
+#[tokio::test]
+async fn small_requests_stay_on_udp() -> Result<(), sip_server::Error> {
+    tokio::time::pause();
+
+    let request = requests::options_request();
+    assert!(request.to_string().len() <= common::CONFIG.max_udp_request_size());
+
+    let (tuple, alice_transaction) = sending_to_bob(request).await?;
+    assert_eq!(tuple.transport, rsip::Transport::Udp);
+    assert_eq!(tuple.peer, alice_addr());
+    assert_eq!(alice_transaction.messages().await.len().await, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_over_the_udp_size_limit_switch_to_tcp() -> Result<(), sip_server::Error> {
+    tokio::time::pause();
+
+    let mut request = requests::options_request();
+    let padding = common::CONFIG.max_udp_request_size() - request.to_string().len() + 1;
+    request
+        .headers
+        .push(rsip::Header::Other("X-Padding".into(), "a".repeat(padding)));
+    assert!(request.to_string().len() > common::CONFIG.max_udp_request_size());
+
+    let (tuple, alice_transaction) = sending_to_bob(request).await?;
+    assert_eq!(tuple.transport, rsip::Transport::Tcp);
+    assert_eq!(tuple.peer, alice_tcp_addr());
+
+    //so that its transaction stops retransmitting
+    assert_eq!(alice_transaction.messages().await.len().await, 1);
+    assert!(matches!(
+        alice_transaction.messages().await.first().await,
+        TransactionLayerMsg::Resolved(_, peer, rsip::Transport::Tcp) if peer == bob_addr()
+    ));
+
+    let request = match TransportMsg::try_from(tuple)?.sip_message {
+        rsip::SipMessage::Request(request) => request,
+        _ => panic!("not a request"),
+    };
+    assert_eq!(request.via_header()?.typed()?.transport, rsip::Transport::Tcp);
+
+    Ok(())
+}
+
+//alice sends a request with a CSeq of another method straight over her listener to bob,
+//returning what bob answered, if anything
+async fn sending_malformed_request_to_bob<P: TransportProcessor>(
+    bob_processor: P,
+) -> Result<Option<TransportLayerMsg>, sip_server::Error> {
+    let network = LoopbackNetwork::new();
+
+    let (alice_handlers, alice_receivers) = models::channels_builder();
+    let alice = Listeners::loopback(
+        alice_handlers.clone(),
+        network.clone(),
+        &[udp_listener(alice_addr())],
+    )?;
+    let alice_transport =
+        SpySnitch::new(alice_handlers, alice_receivers.transport).expect("transport");
+
+    let (bob_handlers, bob_receivers) = models::channels_builder();
+    let _bob = Transport::with_listeners(
+        bob_handlers.clone(),
+        bob_processor,
+        StaticDnsLookup::with_resolver(StaticResolver::default()),
+        Listeners::loopback(
+            bob_handlers.clone(),
+            network.clone(),
+            &[udp_listener(bob_addr())],
+        )?,
+        bob_receivers.transport,
+    )?;
+
+    let mut request = requests::request(None, None);
+    request.method = rsip::Method::Options;
+    alice
+        .send(
+            TransportTuple {
+                bytes: Bytes::from(request.to_string()),
+                peer: bob_addr(),
+                transport: rsip::Transport::Udp,
+            },
+            None,
+            None,
+        )
+        .await?;
+
+    match alice_transport.messages().await.len().await {
+        0 => Ok(None),
+        1 => Ok(Some(alice_transport.messages().await.first().await)),
+        _ => panic!("more than one answer"),
+    }
+}
+
+#[tokio::test]
+async fn malformed_requests_get_a_bad_request() -> Result<(), sip_server::Error> {
+    tokio::time::pause();
+
+    let received = sending_malformed_request_to_bob(DefaultProcessor::default()).await?;
+
+    match received.expect("bad request") {
+        TransportLayerMsg::Incoming(tuple) => {
+            match TransportMsg::try_from(tuple)?.sip_message {
+                rsip::SipMessage::Response(response) => {
+                    assert_eq!(response.status_code, 400.into())
+                }
+                _ => panic!("not a response"),
+            }
+        }
+        _ => panic!("not an Incoming variant"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_requests_of_denied_networks_are_not_answered() -> Result<(), sip_server::Error>
+{
+    tokio::time::pause();
+
+    let received = sending_malformed_request_to_bob(RateLimitingProcessor::new(
+        DefaultProcessor::default(),
+        RateLimits {
+            deny: vec!["192.0.2.0/30".parse().expect("network")],
+            ..Default::default()
+        },
+    ))
+    .await?;
+
+    assert!(received.is_none());
+
+    Ok(())
+}
@@ -1 +1,9 @@
+pub mod capture_tests;
+pub mod codec_tests;
+pub mod connections_tests;
+pub mod dns_tests;
+pub mod loopback_tests;
 pub mod processor;
+pub mod transport_error_tests;
+pub mod validation_tests;
+pub mod ws_tests;
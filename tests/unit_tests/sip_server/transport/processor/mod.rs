@@ -1,2 +1,3 @@
+pub mod rate_limiter_tests;
 pub mod uac_tests;
 pub mod uas_tests;
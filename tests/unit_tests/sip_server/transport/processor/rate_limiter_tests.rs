@@ -0,0 +1,137 @@
+use crate::common::{advance_for, factories::prelude::*};
+use common::rsip;
+use models::transport::RequestMsg;
+use sip_server::transport::{
+    DefaultProcessor, Rate, RateLimitingProcessor, RateLimits, TransportProcessor,
+};
+use std::{net::SocketAddr, time::Duration};
+
+fn request_msg_from(peer: &str) -> RequestMsg {
+    let peer: SocketAddr = peer.parse().expect("socket addr");
+
+    (requests::request(None, None), peer, rsip::Transport::Udp).into()
+}
+
+fn processor_with(limits: RateLimits) -> RateLimitingProcessor {
+    RateLimitingProcessor::new(DefaultProcessor::default(), limits)
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_are_dropped_and_banned() -> Result<(), sip_server::Error> {
+    let processor = processor_with(RateLimits {
+        per_ip: None,
+        per_method: vec![(rsip::Method::Register, Rate::new(1.0, 2))],
+        ban_duration: Some(Duration::from_secs(60)),
+        ..Default::default()
+    });
+
+    for _ in 0..2 {
+        assert!(processor
+            .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+            .await?
+            .is_some());
+    }
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_none());
+    assert!(processor.is_banned(&"192.0.2.1".parse().unwrap()).await);
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.2:5060"))
+        .await?
+        .is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_are_not_banned_by_default() -> Result<(), sip_server::Error> {
+    let processor = processor_with(RateLimits {
+        per_ip: None,
+        per_method: vec![(rsip::Method::Register, Rate::new(1.0, 1))],
+        ..Default::default()
+    });
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_some());
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_none());
+    assert!(!processor.is_banned(&"192.0.2.1".parse().unwrap()).await);
+
+    advance_for(Duration::from_secs(1)).await;
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn bans_expire() -> Result<(), sip_server::Error> {
+    let processor = processor_with(Default::default());
+    processor
+        .ban("192.0.2.1".parse().unwrap(), Duration::from_secs(60))
+        .await;
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_none());
+
+    advance_for(Duration::from_secs(61)).await;
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("192.0.2.1:5060"))
+        .await?
+        .is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn denied_networks_are_dropped_and_allowed_ones_never_limited(
+) -> Result<(), sip_server::Error> {
+    let processor = processor_with(RateLimits {
+        per_ip: Some(Rate::new(1.0, 1)),
+        allow: vec!["198.51.100.0/24".parse().unwrap()],
+        deny: vec!["203.0.113.0/24".parse().unwrap()],
+        ..Default::default()
+    });
+
+    assert!(processor
+        .process_incoming_request(request_msg_from("203.0.113.7:5060"))
+        .await?
+        .is_none());
+
+    for _ in 0..10 {
+        assert!(processor
+            .process_incoming_request(request_msg_from("198.51.100.7:5060"))
+            .await?
+            .is_some());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn rates_that_can_not_refill_a_bucket_are_rejected() {
+    for rate in &["0/10", "-1/10", "inf/10", "NaN/10"] {
+        assert!(common::rate_from(rate).is_none());
+    }
+    assert_eq!(common::rate_from("2.5/10"), Some(Rate::new(2.5, 10)));
+}
+
+#[test]
+fn tiny_rates_never_refill_instead_of_panicking() {
+    assert_eq!(
+        Rate::new(f64::MIN_POSITIVE, 10).refill_time(),
+        Duration::from_secs(u64::MAX)
+    );
+}
@@ -164,7 +164,7 @@ async fn outgoing_transaction_request_applies_sent_by() -> Result<(), sip_server
 
     assert_eq!(
         typed_via_header.uri.host_with_port,
-        common::CONFIG.default_addr()
+        common::CONFIG.sent_by_for(&transport_msg.peer.ip(), transport_msg.transport)
     );
 
     Ok(())
@@ -210,6 +210,40 @@ async fn outgoing_core_request_applies_maddr() -> Result<(), sip_server::Error>
     Ok(())
 }
 
+#[tokio::test]
+async fn outgoing_core_request_applies_bracketed_ipv6_maddr() -> Result<(), sip_server::Error> {
+    use rsip::{param::Maddr, Param};
+
+    let processor = DefaultProcessor::default();
+
+    let transport_msg = TransportMsg {
+        peer: SocketAddrBuilder {
+            ip_addr: IpAddrBuilder {
+                version: IpVersion::V6,
+                multicast: true,
+            }
+            .build(),
+            ..Default::default()
+        }
+        .into(),
+        ..Randomized::default()
+    };
+
+    let message = processor
+        .process_outgoing_request(transport_msg.clone().try_into()?)
+        .await?
+        .unwrap();
+    let request: rsip::Request = message.sip_request;
+    let typed_via_header = &request.via_header()?.typed()?;
+
+    assert!(typed_via_header.params.contains(&Param::Maddr(Maddr::new(format!(
+        "[{}]",
+        transport_msg.peer.ip()
+    )))));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn outgoing_core_request_applies_ttl() -> Result<(), sip_server::Error> {
     use rsip::{param::Ttl, Param};
@@ -273,8 +307,80 @@ async fn outgoing_core_request_applies_sent_by() -> Result<(), sip_server::Error
 
     assert_eq!(
         typed_via_header.uri.host_with_port,
-        common::CONFIG.default_addr()
+        common::CONFIG.sent_by_for(&transport_msg.peer.ip(), transport_msg.transport)
     );
 
     Ok(())
 }
+
+#[tokio::test]
+async fn outgoing_core_request_applies_rport() -> Result<(), sip_server::Error> {
+    use rsip::{param::OtherParam, Param};
+
+    let processor = DefaultProcessor::default();
+
+    let transport_msg: TransportMsg = Randomized::default();
+
+    let message = processor
+        .process_outgoing_request(transport_msg.try_into()?)
+        .await?
+        .unwrap();
+    let typed_via_header = &message.sip_request.via_header()?.typed()?;
+
+    assert!(typed_via_header
+        .params
+        .contains(&Param::Other(OtherParam::new("rport"), None)));
+
+    Ok(())
+}
+
+#[test]
+fn sent_by_is_the_listener_of_the_transport_and_ip_family() {
+    use common::{Config, ListenerConfig};
+
+    let config = Config {
+        listeners: vec![
+            ListenerConfig {
+                addr: "0.0.0.0:5060".parse().unwrap(),
+                transport: rsip::Transport::Udp,
+            },
+            ListenerConfig {
+                addr: "[::1]:5061".parse().unwrap(),
+                transport: rsip::Transport::Tls,
+            },
+            ListenerConfig {
+                addr: "192.0.2.1:5061".parse().unwrap(),
+                transport: rsip::Transport::Tls,
+            },
+        ],
+        ..common::CONFIG.as_ref().clone()
+    };
+    let ipv4_peer = "198.51.100.1".parse().unwrap();
+    let ipv6_peer = "2001:db8::1".parse().unwrap();
+
+    assert_eq!(
+        config.sent_by_for(&ipv4_peer, rsip::Transport::Tls),
+        rsip::HostWithPort {
+            host: rsip::Host::IpAddr("192.0.2.1".parse().unwrap()),
+            port: Some(5061u16.into()),
+        }
+    );
+    assert_eq!(
+        config.sent_by_for(&ipv6_peer, rsip::Transport::Tls),
+        rsip::HostWithPort {
+            host: rsip::Host::IpAddr("::1".parse().unwrap()),
+            port: Some(5061u16.into()),
+        }
+    );
+    assert_eq!(
+        config.sent_by_for(&ipv4_peer, rsip::Transport::Udp),
+        rsip::HostWithPort {
+            host: config.default_addr_for(&ipv4_peer).host,
+            port: Some(5060u16.into()),
+        }
+    );
+    assert_eq!(
+        config.sent_by_for(&ipv4_peer, rsip::Transport::Tcp),
+        config.default_addr_for(&ipv4_peer)
+    );
+}
@@ -60,3 +60,119 @@ async fn incoming_request_with_same_sent_by_param() -> Result<(), sip_server::Er
 
     Ok(())
 }
+
+#[tokio::test]
+async fn incoming_request_claiming_tls_over_udp_fails() -> Result<(), sip_server::Error> {
+    let processor = DefaultProcessor::default();
+
+    let mut request: rsip::Request = requests::request(None, None);
+    let via_header = request.via_header_mut()?;
+    via_header.replace(rsip::typed::Via {
+        transport: rsip::Transport::Tls,
+        ..via_header.typed()?
+    });
+    let server_msg = models::transport::UdpTuple {
+        bytes: request.into(),
+        peer: (IpAddr::V4(Ipv4Addr::new(196, 168, 0, 1)), 5061).into(),
+    };
+
+    assert!(processor
+        .process_incoming_request(server_msg.try_into()?)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn incoming_websocket_request_with_invalid_sent_by_adds_received_ip(
+) -> Result<(), sip_server::Error> {
+    use rsip::{param::Received, Param};
+
+    let processor = DefaultProcessor::default();
+
+    let mut request: rsip::Request = requests::request(None, None);
+    *request.via_header_mut()? =
+        rsip::headers::Via::new("SIP/2.0/WS df7jal23ls0d.invalid;branch=z9hG4bKasudf");
+    let server_msg = models::transport::TransportTuple {
+        bytes: request.into(),
+        peer: (IpAddr::V4(Ipv4Addr::new(196, 168, 0, 1)), 52134).into(),
+        transport: rsip::Transport::Ws,
+    };
+
+    let message = processor
+        .process_incoming_request(server_msg.try_into()?)
+        .await?
+        .unwrap();
+    let typed_via_header = &message.sip_request.via_header()?.typed()?;
+    assert_eq!(typed_via_header.transport, rsip::Transport::Ws);
+    assert!(typed_via_header
+        .params
+        .contains(&Param::Received(Received::new("196.168.0.1"))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn incoming_request_with_empty_rport_gets_source_port() -> Result<(), sip_server::Error> {
+    use rsip::{
+        param::{OtherParam, OtherParamValue, Received},
+        Param,
+    };
+
+    let processor = DefaultProcessor::default();
+
+    let mut request: rsip::Request = requests::request(None, None);
+    let via_header = request.via_header_mut()?;
+    via_header.replace(
+        via_header
+            .typed()?
+            .with_param(Param::Other(OtherParam::new("rport"), None)),
+    );
+    let server_msg = models::transport::UdpTuple {
+        bytes: request.into(),
+        peer: (IpAddr::V4(Ipv4Addr::new(196, 168, 0, 1)), 41234).into(),
+    };
+
+    let message = processor
+        .process_incoming_request(server_msg.try_into()?)
+        .await?
+        .unwrap();
+    let typed_via_header = &message.sip_request.via_header()?.typed()?;
+    assert!(typed_via_header.params.contains(&Param::Other(
+        OtherParam::new("rport"),
+        Some(OtherParamValue::new("41234"))
+    )));
+    assert!(typed_via_header
+        .params
+        .contains(&Param::Received(Received::new("196.168.0.1"))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn incoming_request_over_a_connection_gets_its_port() -> Result<(), sip_server::Error> {
+    use rsip::{
+        param::{OtherParam, OtherParamValue},
+        Param,
+    };
+
+    let processor = DefaultProcessor::default();
+
+    let request: rsip::Request = requests::request(None, None);
+    let message = processor
+        .process_incoming_request(models::transport::RequestMsg::new(
+            request,
+            (IpAddr::V4(Ipv4Addr::new(196, 168, 0, 1)), 41234).into(),
+            rsip::Transport::Tcp,
+        ))
+        .await?
+        .unwrap();
+    let typed_via_header = &message.sip_request.via_header()?.typed()?;
+    assert!(typed_via_header.params.contains(&Param::Other(
+        OtherParam::new("rport"),
+        Some(OtherParamValue::new("41234"))
+    )));
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use common::libc;
+use models::transport::TransportError;
+use std::io;
+
+#[test]
+fn io_errors_are_classified() {
+    let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+    assert!(matches!(
+        TransportError::from(&refused),
+        TransportError::ConnectionRefused(_)
+    ));
+
+    let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+    assert!(matches!(TransportError::from(&timed_out), TransportError::Timeout(_)));
+
+    let unreachable = io::Error::from_raw_os_error(libc::EHOSTUNREACH);
+    assert!(matches!(
+        TransportError::from(&unreachable),
+        TransportError::Unreachable(_)
+    ));
+    let too_large = io::Error::from_raw_os_error(libc::EMSGSIZE);
+    assert!(matches!(
+        TransportError::from(&too_large),
+        TransportError::MessageTooLarge(_)
+    ));
+
+    let other = io::Error::new(io::ErrorKind::Other, "broken");
+    assert_eq!(TransportError::from(&other), TransportError::Other("broken".into()));
+}
+
+#[test]
+fn server_errors_keep_their_transport_error() {
+    let error = sip_server::Error::from(TransportError::TlsHandshake("bad certificate".into()));
+    assert_eq!(
+        TransportError::from(&error),
+        TransportError::TlsHandshake("bad certificate".into())
+    );
+
+    let error = sip_server::Error::from(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert!(matches!(
+        TransportError::from(&error),
+        TransportError::ConnectionRefused(_)
+    ));
+
+    let error = sip_server::Error::custom("no listener");
+    assert_eq!(TransportError::from(&error), TransportError::Other("no listener".into()));
+}
+
+#[test]
+fn only_target_related_errors_fail_over() {
+    assert!(TransportError::Dns("no records".into()).should_fail_over());
+    assert!(TransportError::ConnectionRefused("refused".into()).should_fail_over());
+    assert!(!TransportError::TlsHandshake("bad certificate".into()).should_fail_over());
+    assert!(!TransportError::MessageTooLarge("too large".into()).should_fail_over());
+}
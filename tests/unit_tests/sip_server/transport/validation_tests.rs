@@ -0,0 +1,72 @@
+use crate::common::factories::prelude::*;
+use common::rsip::{self, prelude::*};
+use sip_server::transport::validation;
+
+#[test]
+fn valid_request_passes() {
+    assert_eq!(
+        validation::validate_request(&requests::request(None, None)),
+        Ok(())
+    );
+}
+
+#[test]
+fn request_without_call_id_fails() {
+    let mut request = requests::request(None, None);
+    request
+        .headers
+        .retain(|header| !matches!(header, rsip::Header::CallId(_)));
+
+    let reason = validation::validate_request(&request).expect_err("invalid request");
+    assert!(reason.contains("Call-ID"));
+}
+
+#[test]
+fn request_with_other_cseq_method_fails() {
+    let mut request = requests::request(None, None);
+    request.method = rsip::Method::Options;
+
+    let reason = validation::validate_request(&request).expect_err("invalid request");
+    assert!(reason.contains("CSeq"));
+}
+
+#[test]
+fn bad_request_keeps_transaction_headers() -> Result<(), sip_server::Error> {
+    let mut request = requests::request(None, None);
+    request
+        .headers
+        .retain(|header| !matches!(header, rsip::Header::From(_)));
+
+    let response =
+        validation::bad_request_for(&request, "missing From header").expect("400 response");
+
+    assert_eq!(response.status_code, 400.into());
+    assert_eq!(response.via_header()?, request.via_header()?);
+    assert_eq!(response.call_id_header()?, request.call_id_header()?);
+    assert_eq!(response.cseq_header()?, request.cseq_header()?);
+    assert!(response.to_header()?.tag()?.is_some());
+    assert!(response.headers.iter().any(|header| matches!(
+        header,
+        rsip::Header::Warning(warning) if warning.to_string().contains("missing From header")
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn bad_request_is_not_sent_for_ack() {
+    let mut request = requests::request(None, None);
+    request.method = rsip::Method::Ack;
+
+    assert!(validation::bad_request_for(&request, "whatever").is_none());
+}
+
+#[test]
+fn bad_request_is_not_sent_without_via() {
+    let mut request = requests::request(None, None);
+    request
+        .headers
+        .retain(|header| !matches!(header, rsip::Header::Via(_)));
+
+    assert!(validation::bad_request_for(&request, "missing Via header").is_none());
+}
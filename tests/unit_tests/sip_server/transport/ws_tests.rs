@@ -0,0 +1,17 @@
+use common::{bytes::Bytes, tokio_tungstenite::tungstenite::Message};
+use sip_server::transport::ws::message_from;
+
+#[test]
+fn utf8_messages_go_in_text_frames() {
+    assert_eq!(
+        message_from(Bytes::from_static(b"OPTIONS sip:bob@example.com SIP/2.0\r\n\r\n")),
+        Message::Text("OPTIONS sip:bob@example.com SIP/2.0\r\n\r\n".into())
+    );
+}
+
+#[test]
+fn binary_bodies_go_in_binary_frames() {
+    let bytes = Bytes::from_static(b"MESSAGE sip:bob@example.com SIP/2.0\r\n\r\n\xff\xfe\x00");
+
+    assert_eq!(message_from(bytes.clone()), Message::Binary(bytes.to_vec()));
+}
@@ -18,18 +18,23 @@ pub async fn setup() -> (
 #[tokio::test]
 #[serial_test::serial]
 async fn sending_an_options_request_receives_busy() {
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let capabilities = Capabilities::new(transport.handlers());
+    let capabilities = Capabilities::new(transaction.handlers());
 
     capabilities
-        .process_incoming_request(requests::options_request())
+        .process_incoming_request(requests::request_msg_from(requests::options_request()))
         .await
         .unwrap();
-    assert_eq!(transport.messages().await.len().await, 1);
-    /*
+    assert_eq!(transaction.messages().await.len().await, 1);
     assert_eq!(
-        transport.messages.first_response().await.status_code,
+        transaction
+            .messages()
+            .await
+            .try_first()
+            .await
+            .new_uas_response()
+            .status_code,
         486.into()
-    );*/
+    );
 }
@@ -1 +1,2 @@
+pub mod reaper;
 pub mod uac;
@@ -0,0 +1,35 @@
+use crate::common::{advance_for, factories::prelude::*, snitches::SpySnitch};
+use models::transport::TransportError;
+use sip_server::tu::dialogs::Dialogs;
+use std::time::Duration;
+
+#[tokio::test]
+async fn finished_dialogs_are_reaped_after_lingering() {
+    let (handlers, receivers) = models::channels_builder();
+    let _transaction =
+        SpySnitch::new(handlers.clone(), receivers.transaction).expect("transaction");
+    let dialogs = Dialogs::with_linger(handlers, Duration::from_secs(1));
+
+    let request = requests::invite_request();
+    dialogs.new_uac_session(request.clone()).await.unwrap();
+    assert_eq!(dialogs.dialogs().await, 1);
+
+    advance_for(Duration::from_secs(3)).await;
+    assert_eq!(dialogs.dialogs().await, 1);
+    assert_eq!(dialogs.reaped_dialogs(), 0);
+
+    dialogs
+        .transport_error(
+            request.into(),
+            TransportError::ConnectionRefused("127.0.0.1:5060".into()),
+        )
+        .await
+        .unwrap();
+
+    advance_for(Duration::from_millis(500)).await;
+    assert_eq!(dialogs.dialogs().await, 1);
+
+    advance_for(Duration::from_secs(2)).await;
+    assert_eq!(dialogs.dialogs().await, 0);
+    assert_eq!(dialogs.reaped_dialogs(), 1);
+}
@@ -1,7 +1,11 @@
 use crate::common::{factories::prelude::*, snitches::SpySnitch};
 use common::rsip::{self, common::Uri, message::HeadersExt};
 use models::{
-    transaction::TransactionLayerMsg, transport::TransportLayerMsg, tu::TuLayerMsg, Handlers,
+    rsip_ext::{rseq_header, RAck, ReliableExt, OPTION_TAG_100REL},
+    transaction::TransactionLayerMsg,
+    transport::TransportLayerMsg,
+    tu::TuLayerMsg,
+    Handlers,
 };
 use sip_server::tu::dialogs::uac::dialog_sm::{DialogSm, DialogState};
 
@@ -112,6 +116,50 @@ async fn creates_a_confirmed_dialog() {
     );
 }
 
+#[tokio::test]
+async fn pracks_reliable_provisionals() {
+    let (handlers, (_, transaction, _)) = setup().await;
+
+    let request = requests::invite_request();
+    let mut dialog_sm = DialogSm::new(handlers, request.clone()).await.unwrap();
+    let invite_req = transaction
+        .messages()
+        .await
+        .try_latest()
+        .await
+        .new_uac_invite_msg();
+    assert!(invite_req.headers.supports_100rel());
+
+    let mut ringing_response = responses::ringing_response_from(request.clone());
+    ringing_response
+        .headers
+        .push(rsip::headers::Require::new(OPTION_TAG_100REL).into());
+    ringing_response.headers.push(rseq_header(1));
+    dialog_sm
+        .process_incoming_response(ringing_response.clone())
+        .await;
+    assert_eq!(transaction.messages().await.len().await, 2);
+    assert!(matches!(dialog_sm.state, DialogState::Early(..)));
+
+    let prack = transaction
+        .messages()
+        .await
+        .try_latest()
+        .await
+        .new_uac_msg();
+    assert_eq!(prack.method, rsip::Method::PRack);
+    assert_eq!(prack.cseq_header().unwrap().seq().unwrap(), 2);
+    assert_eq!(
+        prack.headers.rack(),
+        Some(RAck {
+            rseq: 1,
+            cseq: 1,
+            method: rsip::Method::Invite
+        })
+    );
+    assert_ne!(prack.via_header().unwrap(), request.via_header().unwrap());
+}
+
 #[tokio::test]
 async fn modifies_a_confirmed_dialog() {
     let (handlers, (tu, transaction, transport)) = setup().await;
@@ -1,3 +1,4 @@
 pub mod capabilities;
 pub mod dialogs;
 pub mod registrar;
+pub mod user_agent;
@@ -23,22 +23,22 @@ pub async fn setup() -> (
 #[serial_test::serial]
 async fn with_no_records_returns_empty_list() {
     let _ = crate::common::setup();
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let registrar = Registrar::new(transport.handlers());
+    let registrar = Registrar::new(transaction.handlers());
 
     registrar
-        .process_incoming_request(requests::register_query_request())
+        .process_incoming_request(requests::request_msg_from(requests::register_query_request()))
         .await
         .unwrap();
 
-    assert_eq!(transport.messages().await.len().await, 1);
-    let sent_response = transport
+    assert_eq!(transaction.messages().await.len().await, 1);
+    let sent_response = transaction
         .messages()
         .await
-        .latest()
+        .try_latest()
         .await
-        .outgoing_response();
+        .new_uas_response();
     assert_eq!(sent_response.status_code, 200.into());
     assert!(sent_response
         .headers
@@ -51,24 +51,24 @@ async fn with_no_records_returns_empty_list() {
 #[serial_test::serial]
 async fn with_records_returns_a_list_of_contacts() {
     let _ = crate::common::setup();
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let registrar = Registrar::new(transport.handlers());
+    let registrar = Registrar::new(transaction.handlers());
 
     create_registration();
     create_registration();
 
     registrar
-        .process_incoming_request(requests::register_query_request())
+        .process_incoming_request(requests::request_msg_from(requests::register_query_request()))
         .await
         .unwrap();
-    assert_eq!(transport.messages().await.len().await, 1);
-    let sent_response = transport
+    assert_eq!(transaction.messages().await.len().await, 1);
+    let sent_response = transaction
         .messages()
         .await
-        .latest()
+        .try_latest()
         .await
-        .outgoing_response();
+        .new_uas_response();
     assert_eq!(sent_response.status_code, 200.into());
     assert_eq!(
         sent_response
@@ -84,23 +84,23 @@ async fn with_records_returns_a_list_of_contacts() {
 #[serial_test::serial]
 async fn with_new_register_request_saves_the_contact() {
     let _ = crate::common::setup();
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let registrar = Registrar::new(transport.handlers());
+    let registrar = Registrar::new(transaction.handlers());
 
     create_registration();
 
     registrar
-        .process_incoming_request(requests::register_request())
+        .process_incoming_request(requests::request_msg_from(requests::register_request()))
         .await
         .unwrap();
-    assert_eq!(transport.messages().await.len().await, 1);
-    let sent_response = transport
+    assert_eq!(transaction.messages().await.len().await, 1);
+    let sent_response = transaction
         .messages()
         .await
-        .latest()
+        .try_latest()
         .await
-        .outgoing_response();
+        .new_uas_response();
     assert_eq!(sent_response.status_code, 200.into());
     assert_eq!(
         sent_response
@@ -117,47 +117,180 @@ async fn with_new_register_request_saves_the_contact() {
     )
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn with_new_register_request_stores_its_flow() {
+    use ::common::rsip::prelude::*;
+
+    let _ = crate::common::setup();
+    let (_, transaction, transport) = setup().await;
+
+    let registrar = Registrar::new(transaction.handlers());
+
+    let request = requests::register_request();
+    let contact_uri = request.contact_header().unwrap().typed().unwrap().uri;
+    let msg = requests::request_msg_from(request);
+    let flow = models::transport::Flow {
+        transport: msg.transport,
+        peer: msg.peer,
+    };
+
+    registrar.process_incoming_request(msg).await.unwrap();
+
+    let registration = store::Registration::find_by_uri(contact_uri.to_string())
+        .expect("registration find")
+        .expect("registration");
+    assert_eq!(registration.ip_address.ip(), flow.peer.ip());
+    assert_eq!(registration.port as u16, flow.peer.port());
+    assert_eq!(
+        Into::<rsip::Transport>::into(registration.transport),
+        flow.transport
+    );
+
+    assert_eq!(transport.messages().await.len().await, 1);
+    match transport.messages().await.first().await {
+        TransportLayerMsg::Bind(contact, bound_flow, _) => {
+            assert_eq!(contact, contact_uri);
+            assert_eq!(bound_flow, flow);
+        }
+        _ => panic!("not a Bind variant"),
+    }
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn with_wrong_from_to_register() {
     use rsip::Uri;
 
     let _ = crate::common::setup();
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let registrar = Registrar::new(transport.handlers());
+    let registrar = Registrar::new(transaction.handlers());
 
     let mut request = requests::register_request();
     request
         .headers
         .unique_push(rsip::typed::To::from(Uri::default().with_user("another")).into());
 
-    let res = registrar.process_incoming_request(request).await;
+    let res = registrar
+        .process_incoming_request(requests::request_msg_from(request))
+        .await;
     assert!(res.is_err());
+    assert_eq!(transaction.messages().await.len().await, 0);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn with_required_extension_fails_with_420() {
+    let _ = crate::common::setup();
+    let (_, transaction, _) = setup().await;
+
+    let registrar = Registrar::new(transaction.handlers());
+
+    let mut request = requests::register_request();
+    request
+        .headers
+        .push(rsip::headers::Require::new("gruu").into());
+
+    let error = registrar
+        .process_incoming_request(requests::request_msg_from(request.clone()))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status_code(), 420.into());
+    assert_eq!(transaction.messages().await.len().await, 0);
+
+    let response = sip_server::presets::error_response_from(request, &error).unwrap();
+    assert_eq!(response.status_code, 420.into());
+    assert!(response.headers.iter().any(|header| matches!(
+        header,
+        rsip::Header::Unsupported(unsupported) if unsupported.value() == "gruu"
+    )));
+    assert!(response.headers.iter().any(|header| matches!(
+        header,
+        rsip::Header::Warning(warning) if warning.value().contains("gruu")
+    )));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn with_too_brief_expiration_fails_with_423() {
+    let _ = crate::common::setup();
+    let (_, transaction, _) = setup().await;
+
+    let registrar = Registrar::new(transaction.handlers());
+
+    let mut request = requests::register_request();
+    request
+        .headers
+        .unique_push(rsip::headers::Expires::new("10").into());
+
+    let error = registrar
+        .process_incoming_request(requests::request_msg_from(request.clone()))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status_code(), 423.into());
+    assert_eq!(transaction.messages().await.len().await, 0);
+
+    let response = sip_server::presets::error_response_from(request, &error).unwrap();
+    assert!(response
+        .headers
+        .iter()
+        .any(|header| matches!(header, rsip::Header::MinExpires(_))));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn with_one_too_brief_expiration_saves_none_of_the_contacts() {
+    let _ = crate::common::setup();
+    let (_, transaction, transport) = setup().await;
+
+    let registrar = Registrar::new(transaction.handlers());
+
+    let mut request = requests::register_request();
+    request.headers.push(
+        rsip::headers::Contact::new(format!(
+            "<sip:filippos@{}>;expires=10",
+            IpAddrBuilder::localhost()
+        ))
+        .into(),
+    );
+
+    let error = registrar
+        .process_incoming_request(requests::request_msg_from(request))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status_code(), 423.into());
+    assert_eq!(transaction.messages().await.len().await, 0);
     assert_eq!(transport.messages().await.len().await, 0);
+    assert_eq!(
+        store::Registration::count(Default::default()).expect("registrations count"),
+        0
+    )
 }
 
 #[tokio::test]
 #[serial_test::serial]
 async fn delete_registration() {
     let _ = crate::common::setup();
-    let (_, _, transport) = setup().await;
+    let (_, transaction, _) = setup().await;
 
-    let registrar = Registrar::new(transport.handlers());
+    let registrar = Registrar::new(transaction.handlers());
 
     let (_registration, uri) = create_registration();
 
     registrar
-        .process_incoming_request(requests::register_delete_request_with_uri(uri))
+        .process_incoming_request(requests::request_msg_from(
+            requests::register_delete_request_with_uri(uri),
+        ))
         .await
         .unwrap();
-    assert_eq!(transport.messages().await.len().await, 1);
-    let sent_response = transport
+    assert_eq!(transaction.messages().await.len().await, 1);
+    let sent_response = transaction
         .messages()
         .await
-        .latest()
+        .try_latest()
         .await
-        .outgoing_response();
+        .new_uas_response();
     assert_eq!(sent_response.status_code, 200.into());
     assert_eq!(
         sent_response
@@ -174,6 +307,31 @@ async fn delete_registration() {
     )
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn registration_round_trips_ipv6_peer() {
+    use std::net::SocketAddr;
+
+    let _ = crate::common::setup();
+
+    let (registration, _) = create_registration();
+    let peer: SocketAddr = "[2001:db8::1]:5070".parse().expect("ipv6 socket addr");
+
+    store::Registration::update(
+        store::DirtyRegistration {
+            ip_address: Some(peer.ip().into()),
+            port: Some(peer.port() as i16),
+            ..Default::default()
+        },
+        registration.id,
+    )
+    .expect("registration update");
+
+    let registration = store::Registration::find(registration.id).expect("registration find");
+    assert_eq!(registration.ip_address.ip(), peer.ip());
+    assert_eq!(registration.port as u16, peer.port());
+}
+
 fn create_registration() -> (store::Registration, rsip::Uri) {
     use ::common::chrono::{Duration, Utc};
     use std::convert::TryInto;
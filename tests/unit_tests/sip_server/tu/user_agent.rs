@@ -0,0 +1,299 @@
+use crate::common::{
+    advance_for,
+    extensions::{TransactionLayerMsgExt, TransportLayerMsgExt},
+    factories::prelude::*,
+    snitches::SpySnitch,
+};
+use common::{
+    async_trait::async_trait,
+    rsip::{self, prelude::*},
+};
+use models::{
+    transaction::TransactionLayerMsg,
+    transport::{RequestMsg, TransportLayerMsg},
+    Handlers,
+};
+use sip_server::{
+    tu::elements::{Capabilities, MergedRequests, Registrar, UserAgent},
+    Error, ReqProcessor, SipError, Transaction,
+};
+use std::time::Duration;
+
+pub async fn setup() -> (
+    Handlers,
+    SpySnitch<TransactionLayerMsg>,
+    SpySnitch<TransportLayerMsg>,
+) {
+    let (handlers, receivers) = models::channels_builder();
+    let transport = SpySnitch::new(handlers.clone(), receivers.transport).expect("transport");
+    let transaction = SpySnitch::new(handlers.clone(), receivers.transaction).expect("transaction");
+    let _ = UserAgent::new(
+        handlers.clone(),
+        receivers.tu,
+        Registrar::new(handlers.clone()),
+        Capabilities::new(handlers.clone()),
+    )
+    .expect("user agent");
+
+    (handlers, transaction, transport)
+}
+
+//a processor that fails, after it answered the request if told so
+#[derive(Debug)]
+struct FailingProcessor {
+    handlers: Handlers,
+    answers_first: bool,
+}
+
+#[async_trait]
+impl ReqProcessor for FailingProcessor {
+    async fn process_incoming_request(&self, msg: RequestMsg) -> Result<(), Error> {
+        if self.answers_first {
+            let response = sip_server::presets::response_from(msg.sip_request.clone(), 200.into())?;
+            self.handlers
+                .transaction
+                .new_uas(msg.sip_request, Some(response))
+                .await?;
+        }
+
+        Err(Error::from(SipError::Forbidden("not allowed here".into())))
+    }
+}
+
+async fn setup_with_failing_registrar(
+    answers_first: bool,
+) -> (Handlers, Transaction, SpySnitch<TransportLayerMsg>) {
+    let (handlers, receivers) = models::channels_builder();
+    let transport = SpySnitch::new(handlers.clone(), receivers.transport).expect("transport");
+    let transaction =
+        Transaction::new(handlers.clone(), receivers.transaction).expect("transaction");
+    let _ = UserAgent::new(
+        handlers.clone(),
+        receivers.tu,
+        FailingProcessor {
+            handlers: handlers.clone(),
+            answers_first,
+        },
+        Capabilities::new(handlers.clone()),
+    )
+    .expect("user agent");
+
+    (handlers, transaction, transport)
+}
+
+//the same request, as it would reach us over a second path
+fn forked(request: &rsip::Request) -> rsip::Request {
+    use rsip::{param::Branch, Param};
+
+    let mut via_header = request.via_header().unwrap().typed().unwrap();
+    via_header.params = via_header
+        .params
+        .into_iter()
+        .map(|param| match param {
+            Param::Branch(_) => Param::Branch(Branch::new(format!(
+                "z9hG4bK{}",
+                ::common::uuid::Uuid::new_v4().to_simple()
+            ))),
+            param => param,
+        })
+        .collect();
+
+    let mut request = request.clone();
+    request.headers.unique_push(via_header.into());
+    request
+}
+
+fn with_exhausted_max_forwards(mut request: rsip::Request) -> rsip::Request {
+    request
+        .headers
+        .unique_push(rsip::headers::MaxForwards::new("0").into());
+    request
+}
+
+async fn incoming(handlers: &Handlers, request: rsip::Request) {
+    handlers
+        .tu
+        .process_request(requests::request_msg_from(request))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn merged_requests_get_loop_detected() {
+    let (handlers, transaction, _) = setup().await;
+
+    let request = requests::options_request();
+    incoming(&handlers, request.clone()).await;
+    incoming(&handlers, forked(&request)).await;
+
+    assert_eq!(transaction.messages().await.len().await, 2);
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .first()
+            .await
+            .new_uas_response()
+            .status_code,
+        486.into()
+    );
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .latest()
+            .await
+            .new_uas_response()
+            .status_code,
+        482.into()
+    );
+}
+
+#[tokio::test]
+async fn merged_invites_get_loop_detected_through_an_invite_transaction() {
+    let (handlers, transaction, _) = setup().await;
+
+    let request = requests::invite_request();
+    incoming(&handlers, request.clone()).await;
+    incoming(&handlers, forked(&request)).await;
+
+    assert_eq!(transaction.messages().await.len().await, 2);
+    assert!(matches!(
+        transaction.messages().await.first().await,
+        TransactionLayerMsg::NewUasInvite(_, None)
+    ));
+    assert_eq!(
+        transaction.messages().await.latest().await.reply_msg().status_code,
+        482.into()
+    );
+}
+
+#[tokio::test]
+async fn retransmissions_are_not_taken_for_merged_requests() {
+    let (handlers, transaction, _) = setup().await;
+
+    let request = requests::options_request();
+    incoming(&handlers, request.clone()).await;
+    incoming(&handlers, request).await;
+
+    assert_eq!(transaction.messages().await.len().await, 2);
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .latest()
+            .await
+            .new_uas_response()
+            .status_code,
+        486.into()
+    );
+}
+
+#[tokio::test]
+async fn exhausted_max_forwards_get_too_many_hops() {
+    let (handlers, transaction, transport) = setup().await;
+
+    incoming(&handlers, with_exhausted_max_forwards(requests::bye_request())).await;
+
+    assert_eq!(transaction.messages().await.len().await, 1);
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .first()
+            .await
+            .new_uas_response()
+            .status_code,
+        483.into()
+    );
+    assert_eq!(transport.messages().await.len().await, 0);
+}
+
+#[tokio::test]
+async fn options_with_exhausted_max_forwards_are_answered() {
+    let (handlers, transaction, _) = setup().await;
+
+    incoming(&handlers, with_exhausted_max_forwards(requests::options_request())).await;
+
+    assert_eq!(transaction.messages().await.len().await, 1);
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .first()
+            .await
+            .new_uas_response()
+            .status_code,
+        486.into()
+    );
+}
+
+#[tokio::test]
+async fn merged_requests_are_forgotten_after_the_window() {
+    let merged_requests = MergedRequests::new(Duration::from_secs(32));
+
+    let request = requests::options_request();
+    assert!(!merged_requests.is_merged(&request).await.unwrap());
+    assert!(merged_requests.is_merged(&forked(&request)).await.unwrap());
+
+    advance_for(Duration::from_secs(33)).await;
+    assert!(!merged_requests.is_merged(&forked(&request)).await.unwrap());
+}
+
+#[tokio::test]
+async fn failed_requests_get_a_final_response_through_a_transaction() {
+    let (handlers, transaction, transport) = setup_with_failing_registrar(false).await;
+
+    incoming(&handlers, requests::register_request()).await;
+
+    assert_eq!(transaction.transactions().await, 1);
+    assert_eq!(transport.messages().await.len().await, 1);
+    let response = transport.messages().await.first().await.outgoing_response();
+    assert_eq!(response.status_code, 403.into());
+    assert!(response.headers.iter().any(|header| matches!(
+        header,
+        rsip::Header::Warning(warning) if warning.value().contains("not allowed here")
+    )));
+}
+
+#[tokio::test]
+async fn failed_requests_that_were_already_answered_get_no_other_response() {
+    let (handlers, transaction, transport) = setup_with_failing_registrar(true).await;
+
+    incoming(&handlers, requests::register_request()).await;
+
+    assert_eq!(transaction.transactions().await, 1);
+    assert_eq!(transport.messages().await.len().await, 1);
+    assert_eq!(
+        transport
+            .messages()
+            .await
+            .first()
+            .await
+            .outgoing_response()
+            .status_code,
+        200.into()
+    );
+}
+
+#[tokio::test]
+async fn cancels_matching_nothing_get_a_481_through_a_transaction() {
+    use models::rsip_ext::RequestExt;
+
+    let (handlers, transaction, transport) = setup().await;
+
+    incoming(&handlers, requests::invite_request().cancel_request()).await;
+
+    assert_eq!(transaction.messages().await.len().await, 1);
+    assert_eq!(
+        transaction
+            .messages()
+            .await
+            .first()
+            .await
+            .new_uas_response()
+            .status_code,
+        481.into()
+    );
+    assert_eq!(transport.messages().await.len().await, 0);
+}